@@ -0,0 +1,238 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use log::{debug, warn};
+
+use crate::file::{error::Result, loader::{FontLoader, TableDirectoryEntry}, table_checksum};
+
+/// The value the checksum of a whole font adds up to once `checksumAdjustment` is filled in
+pub const CHECKSUM_MAGIC: u32 = 0xB1B0AFBA;
+
+const HEAD_TAG: u32 = 0x68656164;
+
+/// Offset of `checksumAdjustment` from the start of the head table
+const ADJUSTMENT_OFFSET: usize = 8;
+
+/// The checksum stored in a table directory entry and the one calculated from the table data
+#[derive(Debug, Clone, Copy)]
+pub struct TableChecksum {
+    pub tag: u32,
+    pub offset: u32,
+    pub length: u32,
+
+    pub stored: u32,
+    pub calculated: u32,
+
+    /// Set if the table reaches past the end of the file
+    pub truncated: bool
+}
+
+impl TableChecksum {
+    pub fn is_valid(&self) -> bool {
+        self.stored == self.calculated && !self.truncated
+    }
+
+    pub fn tag_name(&self) -> String {
+        self.tag.to_be_bytes().iter().map(|&byte| char::from(byte)).collect()
+    }
+}
+
+/// The `checksumAdjustment` stored in head and the one calculated for the whole font
+#[derive(Debug, Clone, Copy)]
+pub struct ChecksumAdjustment {
+    /// Offset of the field in the file
+    pub offset: u32,
+
+    pub stored: u32,
+    pub calculated: u32
+}
+
+impl ChecksumAdjustment {
+    pub fn is_valid(&self) -> bool {
+        self.stored == self.calculated
+    }
+}
+
+/// The result of verifying the checksums of every table of a face
+#[derive(Debug, Clone, Default)]
+pub struct ChecksumReport {
+    /// All tables, ordered by their offset
+    pub tables: Vec<TableChecksum>,
+
+    /// `None` if the font has no head table
+    pub adjustment: Option<ChecksumAdjustment>,
+
+    /// Set for WOFF2 fonts. They don't store checksums, so the decoder has to calculate them
+    /// from the decoded tables and they can't be checked against the original font.
+    pub unverifiable: bool
+}
+
+impl ChecksumReport {
+    /// Calculates the checksums of all tables in the loader's table directory. The whole font
+    /// checksum is the sum of the table directory and all table checksums, so faces of
+    /// collections are checked as if they were stored on their own.
+    pub fn verify<S>(loader: &mut FontLoader<S>) -> Result<ChecksumReport>
+        where S: Read + Seek
+    {
+        let directory_offset = loader.table_directory_offset();
+
+        let mut entries: Vec<TableDirectoryEntry> = loader.get_table_dir().get_tables().values().copied().collect();
+        entries.sort_by_key(|entry| (entry.offset, entry.tag));
+
+        let stream = loader.get_stream();
+
+        let mut directory = Vec::new();
+        stream.seek(SeekFrom::Start(directory_offset as u64))?;
+        stream.take(12 + 16 * entries.len() as u64).read_to_end(&mut directory)?;
+        let mut font_checksum = table_checksum(&directory);
+
+        let mut report = ChecksumReport::default();
+        for entry in entries {
+            let mut data = Vec::with_capacity(entry.length as usize);
+            stream.seek(SeekFrom::Start(entry.offset as u64))?;
+            stream.take(entry.length as u64).read_to_end(&mut data)?;
+
+            let truncated = data.len() < entry.length as usize;
+            if entry.tag == HEAD_TAG && data.len() >= ADJUSTMENT_OFFSET + 4 {
+                let stored = u32::from_be_bytes(data[ADJUSTMENT_OFFSET..ADJUSTMENT_OFFSET + 4].try_into().unwrap());
+                data[ADJUSTMENT_OFFSET..ADJUSTMENT_OFFSET + 4].fill(0);
+
+                report.adjustment = Some(ChecksumAdjustment {
+                    offset: entry.offset + ADJUSTMENT_OFFSET as u32,
+                    stored,
+                    calculated: 0
+                });
+            }
+
+            let table = TableChecksum {
+                tag: entry.tag,
+                offset: entry.offset,
+                length: entry.length,
+                stored: entry.checksum,
+                calculated: table_checksum(&data),
+                truncated
+            };
+
+            match table.is_valid() {
+                true => debug!("checksum of table {} is valid", table.tag_name()),
+                false if truncated => warn!("table {} at 0x{:08x} is truncated, only {} of {} bytes are in the file", table.tag_name(), table.offset, data.len(), table.length),
+                false => warn!("checksum mismatch in table {} at 0x{:08x}: stored 0x{:08x}, calculated 0x{:08x}", table.tag_name(), table.offset, table.stored, table.calculated)
+            }
+
+            font_checksum = font_checksum.wrapping_add(table.calculated);
+            report.tables.push(table);
+        }
+
+        if let Some(adjustment) = &mut report.adjustment {
+            adjustment.calculated = CHECKSUM_MAGIC.wrapping_sub(font_checksum);
+
+            if !adjustment.is_valid() {
+                warn!("checksum adjustment mismatch at 0x{:08x}: stored 0x{:08x}, calculated 0x{:08x}", adjustment.offset, adjustment.stored, adjustment.calculated);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Returns the tables whose checksum doesn't match the table directory
+    pub fn mismatches(&self) -> impl Iterator<Item = &TableChecksum> {
+        self.tables.iter().filter(|table| !table.is_valid())
+    }
+
+    /// Returns whether all checksums match. Unverifiable checksums are never valid.
+    pub fn is_valid(&self) -> bool {
+        !self.unverifiable && self.tables.iter().all(TableChecksum::is_valid) && self.adjustment.is_none_or(|adjustment| adjustment.is_valid())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::file::{loader::FontLoader, table_checksum};
+
+    use super::{ChecksumReport, ADJUSTMENT_OFFSET, CHECKSUM_MAGIC, HEAD_TAG};
+
+    /// Builds a font with a head table and a table whose length isn't a multiple of 4.
+    /// Returns the font and the offset of the checksum adjustment.
+    fn font() -> (Vec<u8>, usize) {
+        let mut head = vec![0u8; 54];
+        head[0..4].copy_from_slice(&0x00010000u32.to_be_bytes());
+        let tables = [(HEAD_TAG, head), (0x7A7A7A7A, vec![1, 2, 3, 4, 5])];
+
+        let mut font = Vec::new();
+        font.extend(0x00010000u32.to_be_bytes());
+        font.extend([0, 2, 0, 32, 0, 1, 0, 0]);
+
+        let mut offset = 12 + 16 * tables.len() as u32;
+        for (tag, data) in &tables {
+            font.extend(tag.to_be_bytes());
+            font.extend(table_checksum(data).to_be_bytes());
+            font.extend(offset.to_be_bytes());
+            font.extend((data.len() as u32).to_be_bytes());
+            offset += (data.len() as u32).next_multiple_of(4);
+        }
+
+        let adjustment = font.len() + ADJUSTMENT_OFFSET;
+        for (_, data) in &tables {
+            font.extend(data);
+            font.resize(font.len().next_multiple_of(4), 0);
+        }
+
+        let value = CHECKSUM_MAGIC.wrapping_sub(table_checksum(&font));
+        font[adjustment..adjustment + 4].copy_from_slice(&value.to_be_bytes());
+
+        (font, adjustment)
+    }
+
+    fn verify(font: Vec<u8>) -> ChecksumReport {
+        ChecksumReport::verify(&mut FontLoader::new(Cursor::new(font)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn partial_words_are_padded_with_zeros() {
+        assert_eq!(table_checksum(&[]), 0);
+        assert_eq!(table_checksum(&[1, 2, 3]), 0x01020300);
+        assert_eq!(table_checksum(&[1, 2, 3, 4, 5]), 0x01020304 + 0x05000000);
+        assert_eq!(table_checksum(&[0xFF; 8]), 0xFFFFFFFE);
+    }
+
+    #[test]
+    fn valid_checksums() {
+        let report = verify(font().0);
+
+        assert!(report.is_valid());
+        assert_eq!(report.tables.len(), 2);
+    }
+
+    #[test]
+    fn head_checksum_excludes_the_adjustment() {
+        let (mut font, adjustment) = font();
+        font[adjustment] ^= 0xFF;
+        let report = verify(font);
+
+        assert!(report.tables.iter().all(|table| table.is_valid()));
+        assert!(!report.adjustment.unwrap().is_valid());
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn table_checksums_end_at_the_table_length() {
+        let (mut font, _) = font();
+        let end = font.len() - 3;
+        font[end] = 0xFF;
+
+        assert_eq!(verify(font.clone()).mismatches().count(), 0);
+
+        font[end - 1] ^= 0xFF;
+        let report = verify(font);
+        assert_eq!(report.mismatches().map(|table| table.tag_name()).collect::<Vec<_>>(), ["zzzz"]);
+    }
+
+    #[test]
+    fn unverifiable_checksums_are_never_valid() {
+        let mut report = verify(font().0);
+        report.unverifiable = true;
+
+        assert!(!report.is_valid());
+    }
+}
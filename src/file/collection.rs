@@ -0,0 +1,91 @@
+use std::{collections::BTreeMap, io::Seek, sync::Arc};
+
+use log::info;
+
+use crate::file::{error::{FontError, Result}, loader::{CollectionHeader, FontLoader, TableCache, TableDirectory, TableDirectoryEntry}, stream::FontStream, table::DEFAULT_ENCODING_PRIORITY, OpenTypeFont};
+
+/// A table used by more than one face of a collection
+#[derive(Debug, Clone)]
+pub struct SharedTable {
+    pub entry: TableDirectoryEntry,
+    pub faces: Vec<u32>
+}
+
+/// The table directories of all faces in a font collection. Single fonts are treated
+/// as collections with one face.
+#[derive(Debug, Clone)]
+pub struct FontCollection {
+    file: String,
+    header: Option<CollectionHeader>,
+    table_dirs: Vec<TableDirectory>,
+
+    /// Tables loaded by the faces of the collection, shared between faces pointing to the same data
+    cache: Arc<TableCache>
+}
+
+impl FontCollection {
+    pub fn load(filepath: &str) -> Result<FontCollection> {
+        info!("loading font collection from file '{}'", filepath);
+
+        let mut stream = FontStream::open(filepath)?;
+        let header = CollectionHeader::load(&mut stream)?;
+
+        let table_dirs = match &header {
+            Some(header) => header.table_directory_offsets.iter()
+                .map(|&offset| {
+                    stream.seek(std::io::SeekFrom::Start(offset as u64))?;
+                    TableDirectory::load(&mut stream)
+                })
+                .collect::<Result<Vec<TableDirectory>>>()?,
+            None => vec![TableDirectory::load(&mut stream)?]
+        };
+
+        Ok(FontCollection {
+            file: String::from(filepath),
+            header,
+            table_dirs,
+            cache: Arc::default()
+        })
+    }
+
+    /// Returns the collection header, `None` for single fonts
+    pub fn header(&self) -> Option<&CollectionHeader> {
+        self.header.as_ref()
+    }
+
+    pub fn num_faces(&self) -> u32 {
+        self.table_dirs.len() as u32
+    }
+
+    pub fn table_directory(&self, face_index: u32) -> Option<&TableDirectory> {
+        self.table_dirs.get(face_index as usize)
+    }
+
+    /// Returns the tables that several faces point to, ordered by their offset. Faces
+    /// sharing a table read the same data and load it from the same offset.
+    pub fn shared_tables(&self) -> Vec<SharedTable> {
+        let mut tables: BTreeMap<(u32, u32, u32), SharedTable> = BTreeMap::new();
+
+        for (face_index, table_dir) in self.table_dirs.iter().enumerate() {
+            for entry in table_dir.get_tables().values() {
+                tables.entry((entry.offset, entry.length, entry.tag))
+                    .or_insert_with(|| SharedTable { entry: *entry, faces: vec![] })
+                    .faces.push(face_index as u32);
+            }
+        }
+
+        tables.into_values()
+            .filter(|table| table.faces.len() > 1)
+            .collect()
+    }
+
+    /// Loads one face of the collection. Tables shared with faces loaded before are reused.
+    pub fn face(&self, face_index: u32) -> Result<OpenTypeFont> {
+        if face_index >= self.num_faces() {
+            return Err(FontError::FontFormatError(None, format!("Face {} requested, but the collection only has {} faces", face_index, self.num_faces())));
+        }
+
+        let loader = FontLoader::from_file_with_face(&self.file, face_index)?.with_table_cache(self.cache.clone());
+        OpenTypeFont::from_loader(&self.file, loader, DEFAULT_ENCODING_PRIORITY)
+    }
+}
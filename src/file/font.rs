@@ -1,32 +1,106 @@
-use log::{debug, warn};
+use std::sync::Arc;
 
-use crate::file::{error::{FontError, Result}, loader::FontLoader, outlines::{self, OutlineLoadConfig, Outlines}, table::{CharacterMap, FontHeader, HorizontalHeader, MaximumProfile}};
+use log::{debug, info, warn};
+
+use crate::file::{checksum::ChecksumReport, validation::ValidationReport, error::{FontError, Result}, loader::FontLoader, stream::FontStream, outlines::{Outline, OutlineLoadConfig, Outlines}, table::{CharacterMap, CmapHeader, Direction, EncodingRecord, FeatureSetting, FontHeader, GlyphClass, GlyphDefinitionTable, GlyphInfo, GlyphPosition, GlyphPositioningTable, GlyphSubstitutionTable, ShapedGlyph, HorizontalHeader, HorizontalMetrics, KerningTable, LongVerMetric, MaximumProfile, NamingTable, OS2, PostScriptTable, ReverseCharacterMap, VariationGlyph, VariationSequences, VerticalHeader, VerticalMetrics, VerticalOrigin, DEFAULT_ENCODING_PRIORITY, DEFAULT_FEATURES}};
+
+fn log_and_none<T>(err: FontError) -> Option<T> {
+    warn!("{}", err);
+    None
+}
+
+fn is_variation_selector(character: char) -> bool {
+    matches!(character, '\u{FE00}'..='\u{FE0F}' | '\u{E0100}'..='\u{E01EF}')
+}
 
 #[derive(Debug)]
 pub struct OpenTypeFont {
     file: String,
+    loader: FontLoader<FontStream>,
+
+    header: Arc<FontHeader>,
+    hheader: Arc<HorizontalHeader>,
+    hmetrics: Arc<HorizontalMetrics>,
+    vheader: Option<Arc<VerticalHeader>>,
+    vmetrics: Option<Arc<VerticalMetrics>>,
+    vorg: Option<Arc<VerticalOrigin>>,
+    kern: Option<Arc<KerningTable>>,
+    gdef: Option<Arc<GlyphDefinitionTable>>,
+    gsub: Option<Arc<GlyphSubstitutionTable>>,
+    gpos: Option<Arc<GlyphPositioningTable>>,
+    names: Arc<NamingTable>,
+    os2: Arc<OS2>,
+    post: Arc<PostScriptTable>,
+    cmap: Arc<CmapHeader>,
+    encoding: EncodingRecord,
+    mapping: CharacterMap,
+    variations: Arc<VariationSequences>,
+    profile: Option<MaximumProfile>,
+
     outlines: Outlines
 }
 
 impl OpenTypeFont {
-    const REQUIRED_TAGS: [&'static str; 8] = ["cmap", "head", "hhea", "hmtx", "maxp", "name", "OS/2", "post"];
+    pub(crate) const REQUIRED_TAGS: [&'static str; 8] = ["cmap", "head", "hhea", "hmtx", "maxp", "name", "OS/2", "post"];
 
     pub fn load(filepath: &str) -> Result<OpenTypeFont> {
-        let mut loader = FontLoader::from_file(filepath)?;
+        OpenTypeFont::load_with_encoding_priority(filepath, DEFAULT_ENCODING_PRIORITY)
+    }
+
+    /// Loads a face of a font collection. Single fonts only have face 0.
+    pub fn load_face(filepath: &str, face_index: u32) -> Result<OpenTypeFont> {
+        OpenTypeFont::load_face_with_encoding_priority(filepath, face_index, DEFAULT_ENCODING_PRIORITY)
+    }
 
+    /// Loads a font, using the first character map whose (platform id, encoding id) pair
+    /// appears in `priority`
+    pub fn load_with_encoding_priority(filepath: &str, priority: &[(u16, u16)]) -> Result<OpenTypeFont> {
+        OpenTypeFont::load_face_with_encoding_priority(filepath, 0, priority)
+    }
+
+    pub fn load_face_with_encoding_priority(filepath: &str, face_index: u32, priority: &[(u16, u16)]) -> Result<OpenTypeFont> {
+        let loader = FontLoader::from_file_with_face(filepath, face_index)?;
+        OpenTypeFont::from_loader(filepath, loader, priority)
+    }
+
+    /// Loads the face a loader points to. Tables are loaded through the loader's table cache.
+    pub(crate) fn from_loader(filepath: &str, mut loader: FontLoader<FontStream>, priority: &[(u16, u16)]) -> Result<OpenTypeFont> {
         if let Some(missing_tags) = loader.check_tables_present(OpenTypeFont::REQUIRED_TAGS.iter()) {
             return Err(FontError::FontFormatError(None, format!("The following tables are required, but were missing from the table directory: {}", missing_tags)));
         }
 
-        let log_and_none = |err: FontError| {
-            warn!("{}", err);
-            None
-        };
         // Parse font header first (head)
-        let header: FontHeader                  = loader.load_table("head", ())?;
-        let hheader: HorizontalHeader           = loader.load_table("hhea", ())?;
-        let mapping: CharacterMap               = loader.load_table("cmap", ())?;
-        let profile: Option<MaximumProfile>     = loader.load_table("maxp", ()).map_or_else(log_and_none, |result| Some(result));
+        let header: Arc<FontHeader>             = loader.load_shared_table("head", ())?;
+        let hheader: Arc<HorizontalHeader>      = loader.load_shared_table("hhea", ())?;
+        let names: Arc<NamingTable>             = loader.load_shared_table("name", ())?;
+        let os2: Arc<OS2>                       = loader.load_shared_table("OS/2", ())?;
+        let post: Arc<PostScriptTable>          = loader.load_shared_table("post", ())?;
+        let cmap: Arc<CmapHeader>               = loader.load_shared_table("cmap", ())?;
+        let variations: Arc<VariationSequences> = loader.load_shared_table("cmap", ())?;
+        let profile: Option<MaximumProfile>     = loader.load_table("maxp", ()).map_or_else(log_and_none, Some);
+
+        let Some(encoding) = cmap.select_encoding(priority) else {
+            return Err(FontError::FontFormatError(Some(cmap.offset), "The character map has none of the requested encodings".into()));
+        };
+        info!("using character map for platform {} encoding {}", encoding.platform_id, encoding.encoding_id);
+        let mapping = cmap.load_subtable(&encoding, loader.get_stream())?;
+
+        let kern: Option<Arc<KerningTable>> = match loader.has_table("kern") {
+            true => loader.load_shared_table("kern", ()).map_or_else(log_and_none, Some),
+            false => None
+        };
+        let gdef: Option<Arc<GlyphDefinitionTable>> = match loader.has_table("GDEF") {
+            true => loader.load_shared_table("GDEF", ()).map_or_else(log_and_none, Some),
+            false => None
+        };
+        let gsub: Option<Arc<GlyphSubstitutionTable>> = match loader.has_table("GSUB") {
+            true => loader.load_shared_table("GSUB", ()).map_or_else(log_and_none, Some),
+            false => None
+        };
+        let gpos: Option<Arc<GlyphPositioningTable>> = match loader.has_table("GPOS") {
+            true => loader.load_shared_table("GPOS", ()).map_or_else(log_and_none, Some),
+            false => None
+        };
 
         debug!("Done loading OpenType tables.");
 
@@ -37,10 +111,398 @@ impl OpenTypeFont {
 
         let outlines = Outlines::load(&mut loader, config)?;
 
+        let num_glyphs = profile.map_or_else(|| outlines.num_glyphs(), |profile| profile.num_glyphs());
+        let hmetrics: Arc<HorizontalMetrics> = loader.load_shared_table("hmtx", (hheader.number_of_h_metrics, num_glyphs))?;
+
+        // Vertical metrics are optional, and vmtx can't be read without vhea
+        let vheader: Option<Arc<VerticalHeader>> = match loader.has_table("vhea") {
+            true => loader.load_shared_table("vhea", ()).map_or_else(log_and_none, Some),
+            false => None
+        };
+        let vmetrics: Option<Arc<VerticalMetrics>> = match (&vheader, loader.has_table("vmtx")) {
+            (Some(vheader), true) => loader.load_shared_table("vmtx", (vheader.number_of_v_metrics, num_glyphs)).map_or_else(log_and_none, Some),
+            _ => None
+        };
+        let vorg: Option<Arc<VerticalOrigin>> = match loader.has_table("VORG") {
+            true => loader.load_shared_table("VORG", ()).map_or_else(log_and_none, Some),
+            false => None
+        };
+
         Ok(OpenTypeFont {
             file: String::from(filepath),
+            loader,
+
+            header,
+            hheader,
+            hmetrics,
+            vheader,
+            vmetrics,
+            vorg,
+            kern,
+            gdef,
+            gsub,
+            gpos,
+            names,
+            os2,
+            post,
+            cmap,
+            encoding,
+            mapping,
+            variations,
+            profile,
+
             outlines
         })
     }
-}
 
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Returns the index of the face within its font collection, 0 for single fonts
+    pub fn face_index(&self) -> u32 {
+        self.loader.face_index()
+    }
+
+    /// Returns the number of faces in the font's file, 1 for fonts that aren't collections
+    pub fn num_faces(&self) -> u32 {
+        self.loader.num_faces()
+    }
+
+    /// Returns the extended metadata of WOFF fonts, an XML document
+    pub fn metadata(&mut self) -> Result<Option<String>> {
+        self.loader.get_stream().metadata()
+    }
+
+    /// Returns the private data block of WOFF fonts
+    pub fn private_data(&mut self) -> Result<Option<Vec<u8>>> {
+        self.loader.get_stream().private_data()
+    }
+
+    /// Verifies the checksums of all tables and the checksum adjustment in head.
+    /// WOFF2 fonts don't store checksums, so their report is marked as unverifiable.
+    pub fn verify_checksums(&mut self) -> Result<ChecksumReport> {
+        let mut report = ChecksumReport::verify(&mut self.loader)?;
+
+        if matches!(self.loader.get_stream(), FontStream::Woff2(_)) {
+            info!("checksums of WOFF2 fonts are calculated by the decoder and can't be verified");
+            report.unverifiable = true;
+        }
+
+        Ok(report)
+    }
+
+    /// Checks the table directory and the invariants between tables
+    pub fn validate(&mut self) -> Result<ValidationReport> {
+        ValidationReport::validate(&mut self.loader)
+    }
+
+    pub fn header(&self) -> &FontHeader {
+        &self.header
+    }
+
+    pub fn horizontal_header(&self) -> &HorizontalHeader {
+        &self.hheader
+    }
+
+    pub fn horizontal_metrics(&self) -> &HorizontalMetrics {
+        &self.hmetrics
+    }
+
+    pub fn vertical_header(&self) -> Option<&VerticalHeader> {
+        self.vheader.as_deref()
+    }
+
+    pub fn vertical_metrics_table(&self) -> Option<&VerticalMetrics> {
+        self.vmetrics.as_deref()
+    }
+
+    pub fn vertical_origin(&self) -> Option<&VerticalOrigin> {
+        self.vorg.as_deref()
+    }
+
+    pub fn kerning_table(&self) -> Option<&KerningTable> {
+        self.kern.as_deref()
+    }
+
+    /// Returns the horizontal kerning between two glyphs from the legacy kern table
+    pub fn kerning(&self, left: u16, right: u16) -> i16 {
+        self.kern.as_deref().map_or(0, |kern| kern.kerning(left, right))
+    }
+
+    pub fn glyph_definitions(&self) -> Option<&GlyphDefinitionTable> {
+        self.gdef.as_deref()
+    }
+
+    pub fn glyph_substitutions(&self) -> Option<&GlyphSubstitutionTable> {
+        self.gsub.as_deref()
+    }
+
+    pub fn glyph_positioning(&self) -> Option<&GlyphPositioningTable> {
+        self.gpos.as_deref()
+    }
+
+    pub fn naming_table(&self) -> &NamingTable {
+        &self.names
+    }
+
+    /// Returns the font's family name, preferring the typographic family
+    pub fn family_name(&self) -> Option<&str> {
+        self.names.preferred_family()
+    }
+
+    pub fn os2(&self) -> &OS2 {
+        &self.os2
+    }
+
+    pub fn postscript(&self) -> &PostScriptTable {
+        &self.post
+    }
+
+    pub fn character_map(&self) -> &CharacterMap {
+        &self.mapping
+    }
+
+    pub fn cmap_header(&self) -> &CmapHeader {
+        &self.cmap
+    }
+
+    /// Returns the encoding record the character map was loaded from
+    pub fn encoding_record(&self) -> &EncodingRecord {
+        &self.encoding
+    }
+
+    /// Loads the character map of any encoding record in `cmap_header().encoding_records`
+    pub fn load_character_map(&mut self, record: &EncodingRecord) -> Result<CharacterMap> {
+        self.cmap.load_subtable(record, self.loader.get_stream())
+    }
+
+    pub fn variation_sequences(&self) -> &VariationSequences {
+        &self.variations
+    }
+
+    pub fn maximum_profile(&self) -> Option<&MaximumProfile> {
+        self.profile.as_ref()
+    }
+
+    pub fn outlines(&self) -> &Outlines {
+        &self.outlines
+    }
+
+    pub fn outlines_mut(&mut self) -> &mut Outlines {
+        &mut self.outlines
+    }
+
+    pub fn units_per_em(&self) -> u16 {
+        self.header.units_per_em
+    }
+
+    pub fn num_glyphs(&self) -> u16 {
+        self.profile
+            .map(|profile| profile.num_glyphs())
+            .unwrap_or_else(|| self.outlines.num_glyphs())
+    }
+
+    /// Returns the advance width of a glyph in font units
+    pub fn advance_width(&self, glyph_index: u16) -> Option<u16> {
+        self.hmetrics.advance_width(glyph_index)
+    }
+
+    /// Returns the left side bearing of a glyph in font units
+    pub fn left_side_bearing(&self, glyph_index: u16) -> Option<i16> {
+        self.hmetrics.left_side_bearing(glyph_index)
+    }
+
+    /// Returns the advance height and top side bearing of a glyph for vertical layout, as
+    /// stored in vmtx. Without vmtx, the advance spans the OS/2 typographic ascender to
+    /// descender and glyphs hang from the ascender, or from their VORG origin if present.
+    pub fn vertical_metrics(&mut self, glyph_index: u16) -> Result<Option<LongVerMetric>> {
+        if glyph_index >= self.num_glyphs() {
+            return Ok(None);
+        }
+
+        if let Some(vmetrics) = &self.vmetrics {
+            return Ok(vmetrics.metrics(glyph_index));
+        }
+
+        let (ascender, descender) = match self.os2.typo_metrics() {
+            Some(metrics) => (metrics.typo_ascender, metrics.typo_descender),
+            None => (self.hheader.ascender, self.hheader.descender)
+        };
+        let vert_origin_y = self.vorg.as_deref().map_or(ascender, |vorg| vorg.vert_origin_y(glyph_index));
+
+        Ok(Some(LongVerMetric {
+            advance_height: (ascender as i32 - descender as i32).clamp(0, u16::MAX as i32) as u16,
+            top_side_bearing: self.glyph_top(glyph_index)?.map_or(0, |y_max| vert_origin_y.saturating_sub(y_max))
+        }))
+    }
+
+    /// Returns the top of a glyph, from its glyf header or the tight bounds of its CFF outline
+    fn glyph_top(&mut self, glyph_index: u16) -> Result<Option<i16>> {
+        let commands = match &self.outlines {
+            Outlines::TrueType(truetype) => {
+                let header = truetype.glyphs().header(glyph_index, self.loader.get_stream())?;
+                return Ok(header.map(|header| header.bounding_box.top));
+            },
+            Outlines::Cff(cff) => cff.outline(glyph_index)?,
+            Outlines::Cff2(cff2) => cff2.outline(glyph_index)?
+        };
+
+        Ok(commands
+            .and_then(|commands| Outline::Cff(commands).bounding_box())
+            .map(|bounding_box| bounding_box.top))
+    }
+
+    /// Returns the glyph index a character is mapped to, if the font maps it at all
+    pub fn glyph_for_char(&self, character: char) -> Option<u16> {
+        self.mapping.map(character)
+    }
+
+    /// Returns the glyph index for a base character followed by a variation selector.
+    /// Falls back to the base character's glyph if the sequence isn't supported.
+    pub fn glyph_for_variation(&self, base: char, selector: char) -> Option<u16> {
+        match self.variations.lookup(base, selector) {
+            Some(VariationGlyph::Glyph(glyph_index)) => Some(glyph_index),
+            Some(VariationGlyph::Default) | None => self.glyph_for_char(base)
+        }
+    }
+
+    /// Maps a string to glyphs. Variation selectors are combined with the preceding
+    /// character, and every glyph's cluster is the byte index of its character in `text`.
+    pub fn glyph_buffer(&self, text: &str) -> Vec<GlyphInfo> {
+        let mut buffer: Vec<GlyphInfo> = Vec::with_capacity(text.len());
+        let mut previous: Option<char> = None;
+
+        for (cluster, character) in text.char_indices() {
+            if let (Some(base), true) = (previous, is_variation_selector(character)) {
+                if let Some(glyph) = buffer.last_mut() {
+                    glyph.glyph_index = self.glyph_for_variation(base, character).unwrap_or(0);
+                }
+
+                previous = None;
+                continue;
+            }
+
+            buffer.push(GlyphInfo::new(self.glyph_for_char(character).unwrap_or(0), cluster));
+            previous = Some(character);
+        }
+
+        buffer
+    }
+
+    /// Maps a string to glyphs and applies the GSUB lookups of `features` for a script and
+    /// language system, given as tags like "latn" and "DEU"
+    pub fn substitute(&self, text: &str, script: &str, language: Option<&str>, features: &[FeatureSetting]) -> Vec<GlyphInfo> {
+        let mut buffer = self.glyph_buffer(text);
+
+        if let Some(gsub) = &self.gsub {
+            gsub.apply(&mut buffer, script, language, features, self.gdef.as_deref());
+        }
+
+        buffer
+    }
+
+    fn default_positions(&self, buffer: &[GlyphInfo]) -> Vec<GlyphPosition> {
+        buffer.iter()
+            .map(|glyph| GlyphPosition {
+                x_advance: self.advance_width(glyph.glyph_index).unwrap_or(0) as i32,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Positions a glyph buffer, starting from the advance widths in hmtx and applying the
+    /// GPOS lookups of `features` for a script and language system
+    pub fn position(&self, buffer: &[GlyphInfo], script: &str, language: Option<&str>, features: &[FeatureSetting], direction: Direction) -> Vec<GlyphPosition> {
+        let mut positions = self.default_positions(buffer);
+
+        if let Some(gpos) = &self.gpos {
+            // Marks don't move the pen, GPOS only places them relative to the glyph they attach to
+            if let Some(gdef) = &self.gdef {
+                for (glyph, position) in buffer.iter().zip(&mut positions) {
+                    if gdef.glyph_class(glyph.glyph_index) == Some(GlyphClass::Mark) {
+                        position.x_advance = 0;
+                    }
+                }
+            }
+
+            gpos.apply(buffer, &mut positions, script, language, features, self.gdef.as_deref(), direction);
+        }
+
+        positions
+    }
+
+    /// Positions a glyph buffer using only the advance widths in hmtx and the kerning
+    /// pairs of the legacy kern table
+    fn position_with_kerning(&self, buffer: &[GlyphInfo], direction: Direction) -> Vec<GlyphPosition> {
+        let mut positions = self.default_positions(buffer);
+
+        let Some(kern) = &self.kern else {
+            return positions;
+        };
+
+        // Kerning pairs are in visual order, so right-to-left text kerns the glyph
+        // following in the buffer against the current one
+        for index in 1..buffer.len() {
+            let (left, right) = match direction {
+                Direction::LeftToRight => (index - 1, index),
+                Direction::RightToLeft => (index, index - 1)
+            };
+
+            positions[left].x_advance += kern.kerning(buffer[left].glyph_index, buffer[right].glyph_index) as i32;
+            positions[right].y_offset += kern.cross_stream_kerning(buffer[left].glyph_index, buffer[right].glyph_index) as i32;
+        }
+
+        positions
+    }
+
+    /// Shapes a string into positioned glyphs. The characters are mapped through the
+    /// character map, substituted with GSUB and positioned with GPOS. Fonts without GPOS
+    /// fall back to hmtx advances and the kern table.
+    ///
+    /// `features` are applied on top of `DEFAULT_FEATURES`, a feature with value 0 disables
+    /// it. Scripts and languages are given as tags like "latn" and "DEU". Glyphs are returned
+    /// in visual order, i.e. reversed for right-to-left text.
+    pub fn shape(&self, text: &str, features: &[FeatureSetting], script: &str, language: Option<&str>, direction: Direction) -> Vec<ShapedGlyph> {
+        let features: Vec<FeatureSetting> = DEFAULT_FEATURES.iter()
+            .map(|&name| FeatureSetting::from(name))
+            .chain(features.iter().copied())
+            .collect();
+
+        let buffer = self.substitute(text, script, language, &features);
+        let kerning = features.iter().rev()
+            .find(|setting| setting.tag == FeatureSetting::from("kern").tag)
+            .is_some_and(|setting| setting.value != 0);
+
+        let positions = match (&self.gpos, kerning) {
+            (Some(_), _) => self.position(&buffer, script, language, &features, direction),
+            (None, true) => self.position_with_kerning(&buffer, direction),
+            (None, false) => self.default_positions(&buffer)
+        };
+
+        let glyphs = buffer.into_iter().zip(positions).map(|(glyph, position)| ShapedGlyph::new(glyph, position));
+        match direction {
+            Direction::LeftToRight => glyphs.collect(),
+            Direction::RightToLeft => glyphs.rev().collect()
+        }
+    }
+
+    /// Returns the name of a glyph from the post table, or from the CFF charset for fonts
+    /// whose post table has no names
+    pub fn glyph_name(&self, glyph_index: u16) -> Option<&str> {
+        self.post.glyph_name(glyph_index).or_else(|| match &self.outlines {
+            Outlines::Cff(cff) => cff.table().glyph_name(glyph_index),
+            _ => None
+        })
+    }
+
+    /// Builds the mapping from glyph indices back to the characters reaching them
+    pub fn reverse_character_map(&self) -> ReverseCharacterMap {
+        ReverseCharacterMap::new(&self.mapping, &self.variations, self.num_glyphs())
+    }
+
+    /// Loads the outline of the glyph with the given index. Returns `None` for glyphs without an outline
+    pub fn outline(&mut self, glyph_index: u16) -> Result<Option<Outline>> {
+        self.outlines.outline(glyph_index, self.loader.get_stream())
+    }
+}
@@ -1,8 +1,8 @@
-use std::{collections::HashMap, fs::File, io::{BufReader, Read, Seek}};
+use std::{any::{Any, TypeId}, collections::HashMap, fmt, io::{Read, Seek, SeekFrom}, sync::{Arc, Mutex}};
 use log::{debug, info};
 use serde::Deserialize;
 
-use crate::file::{self, table::Table, error::{Result, FontError}};
+use crate::file::{self, stream::FontStream, table::Table, error::{Result, FontError}};
 
 macro_rules! tag_to_str {
     ($tag: expr) => ($tag.to_be_bytes().iter().map(|&byte| char::from(byte)).collect::<String>());
@@ -12,50 +12,194 @@ macro_rules! tag_to_int {
     ($tag: expr) => ($tag.bytes().fold(0u32, |left, right| (left << 8) | right as u32));
 }
 
+/// Tag at the start of font collections
+pub const COLLECTION_TAG: u32 = 0x74746366;
+
+/// Tag, offset, length and type of a loaded table
+type TableKey = (u32, u32, u32, TypeId);
+
+/// A loaded table and the arguments it was loaded with
+type CachedTable = (Box<dyn Any + Send + Sync>, Arc<dyn Any + Send + Sync>);
+
+/// Tables loaded from a font file. The faces of a collection share a cache, so tables
+/// several faces point to are only loaded once.
+#[derive(Default)]
+pub struct TableCache {
+    tables: Mutex<HashMap<TableKey, Vec<CachedTable>>>
+}
+
+impl TableCache {
+    fn get<T>(&self, key: TableKey, user_data: &T::UserArgsType) -> Option<Arc<T>>
+        where T: Table + Send + Sync + 'static,
+              T::UserArgsType: PartialEq + Send + Sync + 'static
+    {
+        let tables = self.tables.lock().ok()?;
+
+        tables.get(&key)?.iter()
+            .find(|(cached_data, _)| cached_data.downcast_ref::<T::UserArgsType>() == Some(user_data))
+            .and_then(|(_, table)| table.clone().downcast().ok())
+    }
+
+    fn insert<T>(&self, key: TableKey, user_data: T::UserArgsType, table: Arc<T>)
+        where T: Table + Send + Sync + 'static,
+              T::UserArgsType: PartialEq + Send + Sync + 'static
+    {
+        if let Ok(mut tables) = self.tables.lock() {
+            tables.entry(key).or_default().push((Box::new(user_data), table));
+        }
+    }
+
+    /// Returns the number of tables in the cache
+    pub fn len(&self) -> usize {
+        self.tables.lock().map_or(0, |tables| tables.values().map(Vec::len).sum())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Debug for TableCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TableCache").field("tables", &self.len()).finish()
+    }
+}
+
 #[derive(Debug)]
 pub struct FontLoader<S> where
     S: Read + Seek
 {
+    collection: Option<CollectionHeader>,
+    face_index: u32,
     table_dir: TableDirectory,
-    stream: S
+    stream: S,
+    cache: Arc<TableCache>
 }
-impl FontLoader<BufReader<File>> {
+impl FontLoader<FontStream> {
     pub fn from_file(filepath: &str) -> Result<Self> {
-        info!("loading font from file '{}'", filepath);
+        Self::from_file_with_face(filepath, 0)
+    }
 
-        let file = File::open(filepath)?;
-        let mut stream = BufReader::new(file);
+    /// Opens a face of a font collection. Single fonts only have face 0.
+    pub fn from_file_with_face(filepath: &str, face_index: u32) -> Result<Self> {
+        info!("loading font from file '{}'", filepath);
 
-        Self::new(stream)
+        let stream = FontStream::open(filepath)?;
+        Self::with_face(stream, face_index)
     }
 }
 
 impl<S> FontLoader<S>
     where S: Read + Seek
 {
-    pub fn new(mut stream: S) -> Result<Self> {
-        let mut table_dir = TableDirectory::load(&mut stream)?;
+    pub fn new(stream: S) -> Result<Self> {
+        Self::with_face(stream, 0)
+    }
+
+    /// Loads the table directory of a face of a font collection. Single fonts only have face 0.
+    pub fn with_face(mut stream: S, face_index: u32) -> Result<Self> {
+        let collection = CollectionHeader::load(&mut stream)?;
+
+        match &collection {
+            Some(header) => {
+                let Some(&offset) = header.table_directory_offsets.get(face_index as usize) else {
+                    return Err(FontError::FontFormatError(None, format!("Face {} requested, but the collection only has {} faces", face_index, header.num_fonts)));
+                };
+
+                info!("loading face {} of {} from font collection", face_index, header.num_fonts);
+                stream.seek(std::io::SeekFrom::Start(offset as u64))?;
+            },
+            None if face_index != 0 => return Err(FontError::FontFormatError(None, format!("Face {} requested, but the font isn't a collection", face_index))),
+            None => {}
+        }
+
+        let table_dir = TableDirectory::load(&mut stream)?;
 
         Ok(FontLoader {
+            collection,
+            face_index,
             table_dir,
-            stream
+            stream,
+            cache: Arc::default()
         })
     }
 
+    /// Shares the tables loaded with `load_shared_table` through `cache`, e.g. with the other
+    /// faces of a font collection
+    pub fn with_table_cache(mut self, cache: Arc<TableCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Returns the header of the font collection the font was loaded from
+    pub fn collection(&self) -> Option<&CollectionHeader> {
+        self.collection.as_ref()
+    }
+
+    /// Returns the number of faces in the file, 1 for fonts that aren't collections
+    pub fn num_faces(&self) -> u32 {
+        self.collection.as_ref().map_or(1, |header| header.num_fonts)
+    }
+
+    pub fn face_index(&self) -> u32 {
+        self.face_index
+    }
+
+    /// Returns the offset of the face's table directory from the start of the file
+    pub fn table_directory_offset(&self) -> u32 {
+        self.collection.as_ref()
+            .and_then(|header| header.table_directory_offsets.get(self.face_index as usize).copied())
+            .unwrap_or(0)
+    }
+
     pub fn load_table<T>(&mut self, tag: &str, user_data: T::UserArgsType) -> Result<T>
     where T: Table
     {
         let tag_id = tag_to_int!(tag);
-        let entry = self.table_dir.tables.remove(&tag_id)
+        let entry = self.table_dir.tables.get(&tag_id).copied()
             .ok_or_else(move || FontError::FontFormatError(None, format!("Missing table 0x{:08}", tag_id)))?;
 
         T::load(entry, &mut self.stream, user_data)
     }
 
+    /// Loads a table through the loader's table cache. Tables loaded before from the same
+    /// data with the same arguments are reused instead of being parsed again.
+    pub fn load_shared_table<T>(&mut self, tag: &str, user_data: T::UserArgsType) -> Result<Arc<T>>
+        where T: Table + Send + Sync + 'static,
+              T::UserArgsType: Clone + PartialEq + Send + Sync + 'static
+    {
+        let tag_id = tag_to_int!(tag);
+        let entry = self.table_dir.tables.get(&tag_id).copied()
+            .ok_or_else(move || FontError::FontFormatError(None, format!("Missing table 0x{:08}", tag_id)))?;
+
+        let key = (entry.tag, entry.offset, entry.length, TypeId::of::<T>());
+        if let Some(table) = self.cache.get(key, &user_data) {
+            debug!("reusing table '{}' at {:#08x}", T::get_table_name(), entry.offset);
+            return Ok(table);
+        }
+
+        let table = Arc::new(T::load(entry, &mut self.stream, user_data.clone())?);
+        self.cache.insert(key, user_data, table.clone());
+
+        Ok(table)
+    }
+
+    pub fn get_stream(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
     pub fn get_table_dir(&self) -> &TableDirectory {
         &self.table_dir
     }
 
+    pub fn table_entry(&self, tag: &str) -> Option<TableDirectoryEntry> {
+        self.table_dir.tables.get(&tag_to_int!(tag)).copied()
+    }
+
+    pub fn has_table(&self, tag: &str) -> bool {
+        self.table_dir.tables.contains_key(&tag_to_int!(tag))
+    }
+
     pub fn check_tables_present<'a, I>(&self, tables: I) -> Option<String>
         where I: Iterator<Item = &'a&'a str>
     {
@@ -77,25 +221,96 @@ impl<S> FontLoader<S>
     }
 }
 
-#[derive(Deserialize, Debug, Default)]
+/// The digital signature of a version 2 font collection
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+pub struct CollectionSignature {
+    pub dsig_tag: u32,
+    pub dsig_length: u32,
+    pub dsig_offset: u32
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct CollectionHeader {
+    pub ttc_tag: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub num_fonts: u32,
+
+    /// Offsets of the table directories of every face from the start of the file
+    #[serde(skip)]
+    pub table_directory_offsets: Vec<u32>,
+
+    /// Version 2 only, `None` if the collection isn't signed
+    #[serde(skip)]
+    pub signature: Option<CollectionSignature>
+}
+
+impl CollectionHeader {
+    /// Loads the collection header at the start of the stream. Returns `None` and rewinds
+    /// the stream if the file is a single font.
+    pub fn load<S>(stream: &mut S) -> Result<Option<CollectionHeader>>
+        where S: Read + Seek
+    {
+        let start = stream.stream_position()?;
+        let tag: u32 = file::deserialize_from(stream)?;
+        stream.seek(std::io::SeekFrom::Start(start))?;
+
+        if tag != COLLECTION_TAG {
+            return Ok(None);
+        }
+
+        let mut header: CollectionHeader = file::deserialize_from(stream)?;
+        debug!("{:?}", header);
+
+        if header.major_version != 1 && header.major_version != 2 {
+            return Err(FontError::FontFormatError(
+                Some(start as u32),
+                format!("font collection has unknown version: {}.{}", header.major_version, header.minor_version)
+            ));
+        }
+
+        // Every face needs a 4 byte offset, so the count can't exceed what's left of the file
+        let position = stream.stream_position()?;
+        let length = stream.seek(SeekFrom::End(0))?;
+        stream.seek(SeekFrom::Start(position))?;
+
+        if header.num_fonts as u64 * 4 > length.saturating_sub(position) {
+            return Err(FontError::FontFormatError(
+                Some(start as u32),
+                format!("font collection claims {} faces, but the file is too short for their offsets", header.num_fonts)
+            ));
+        }
+
+        header.table_directory_offsets = file::deserialize_vec_from(header.num_fonts as usize, stream)?;
+
+        if header.major_version == 2 {
+            let signature: CollectionSignature = file::deserialize_from(stream)?;
+            header.signature = (signature.dsig_tag == tag_to_int!("DSIG")).then_some(signature);
+        }
+
+        Ok(Some(header))
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct TableDirectory {
     pub sfnt_version: u32,
-    num_tables: u16,
+    pub num_tables: u16,
 
-    search_range: u16,
-    entry_selector: u16,
-    range_shift: u16,
+    pub search_range: u16,
+    pub entry_selector: u16,
+    pub range_shift: u16,
 
     #[serde(skip)]
     tables: HashMap<u32, TableDirectoryEntry>
 }
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
 pub struct TableDirectoryEntry {
-    tag: u32,
+    pub tag: u32,
     pub checksum: u32,
     pub offset: u32,
-    length: u32
+    pub length: u32
 }
 
 impl TableDirectory {
@@ -111,7 +326,7 @@ impl TableDirectory {
             let table: TableDirectoryEntry = file::deserialize_from(stream)?;
             debug!("found table {}", tag_to_str!(table.tag));
 
-            if let Some(_) = table_dir.tables.insert(table.tag, table) {
+            if table_dir.tables.insert(table.tag, table).is_some() {
                 return Err(FontError::new(Some((stream.stream_position()? as u32) - 16), "duplicate tag"))
             };
         }
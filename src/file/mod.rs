@@ -1,15 +1,24 @@
 mod font;
-mod error;
-mod loader;
+mod collection;
+mod checksum;
+mod validation;
+pub mod error;
+pub mod loader;
+pub mod stream;
+pub mod woff;
+pub mod woff2;
 
 use std::io::Read;
 use bincode::Options;
 
 pub use font::OpenTypeFont;
+pub use collection::{FontCollection, SharedTable};
+pub use checksum::{ChecksumAdjustment, ChecksumReport, TableChecksum, CHECKSUM_MAGIC};
+pub use validation::{Severity, ValidationIssue, ValidationReport};
 use serde::Deserialize;
-mod table;
+pub mod table;
 
-mod outlines;
+pub mod outlines;
 
 #[derive(Debug, Copy, Clone, Deserialize)]
 pub struct BoundingBox {
@@ -51,3 +60,11 @@ pub fn deserialize_vec_from<T, S>(elems: usize, stream: &mut S) -> bincode::Resu
 
     Ok(result)
 }
+
+/// Calculates the checksum of a table, the sum of its big-endian 32 bit words. Data
+/// that doesn't end on a word boundary is padded with zeros.
+pub fn table_checksum(data: &[u8]) -> u32 {
+    data.chunks(4)
+        .map(|chunk| chunk.iter().enumerate().fold(0u32, |word, (index, &byte)| word | (byte as u32) << (24 - 8 * index)))
+        .fold(0u32, |sum, word| sum.wrapping_add(word))
+}
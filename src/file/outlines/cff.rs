@@ -0,0 +1,38 @@
+use std::{io::{Read, Seek}, sync::Arc};
+
+use log::debug;
+
+use crate::file::{error::Result, loader::FontLoader, table::{CompactFontFormat, PathCommand}};
+
+#[derive(Debug, Clone)]
+pub struct Cff {
+    table: Arc<CompactFontFormat>
+}
+
+impl Cff {
+    pub fn load<S>(loader: &mut FontLoader<S>) -> Result<Cff>
+        where S: Read + Seek
+    {
+        debug!("Loading CFF outlines");
+
+        let table: Arc<CompactFontFormat> = loader.load_shared_table("CFF ", ())?;
+
+        Ok(Cff {
+            table
+        })
+    }
+
+    pub fn table(&self) -> &CompactFontFormat {
+        &self.table
+    }
+
+    pub fn num_glyphs(&self) -> u16 {
+        self.table.num_glyphs()
+    }
+
+    pub fn outline(&self, glyph_index: u16) -> Result<Option<Vec<PathCommand>>> {
+        let char_string = self.table.char_string(glyph_index)?;
+
+        Ok((!char_string.commands.is_empty()).then_some(char_string.commands))
+    }
+}
@@ -0,0 +1,44 @@
+use std::{io::{Read, Seek}, sync::Arc};
+
+use log::debug;
+
+use crate::file::{error::Result, loader::FontLoader, table::{CompactFontFormat2, PathCommand}};
+
+#[derive(Debug, Clone)]
+pub struct Cff2 {
+    table: Arc<CompactFontFormat2>
+}
+
+impl Cff2 {
+    pub fn load<S>(loader: &mut FontLoader<S>) -> Result<Cff2>
+        where S: Read + Seek
+    {
+        debug!("Loading CFF2 outlines");
+
+        let table: Arc<CompactFontFormat2> = loader.load_shared_table("CFF2", ())?;
+
+        Ok(Cff2 {
+            table
+        })
+    }
+
+    pub fn table(&self) -> &CompactFontFormat2 {
+        &self.table
+    }
+
+    pub fn num_glyphs(&self) -> u16 {
+        self.table.num_glyphs()
+    }
+
+    /// Selects the instance of the variable font that outlines are produced for. The table
+    /// is copied first if other faces share it.
+    pub fn set_coordinates(&mut self, coordinates: &[f32]) {
+        Arc::make_mut(&mut self.table).set_coordinates(coordinates);
+    }
+
+    pub fn outline(&self, glyph_index: u16) -> Result<Option<Vec<PathCommand>>> {
+        let char_string = self.table.char_string(glyph_index)?;
+
+        Ok((!char_string.commands.is_empty()).then_some(char_string.commands))
+    }
+}
@@ -3,19 +3,65 @@ use std::io::{Read, Seek};
 use log::debug;
 
 pub use self::truetype::TrueType;
+pub use self::cff::Cff;
+pub use self::cff2::Cff2;
 
-use super::{error::{FontError, Result}, loader::FontLoader, table::{FontHeader, MaximumProfile}};
+use super::{error::{FontError, Result}, loader::FontLoader, BoundingBox, table::{Contour, FontHeader, MaximumProfile, PathCommand}};
 
 mod truetype;
+mod cff;
+mod cff2;
 
 pub struct OutlineLoadConfig<'a> {
     pub head: &'a FontHeader,
     pub maxp: Option<&'a MaximumProfile>
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum Outlines {
-    TrueType(TrueType)
+    TrueType(TrueType),
+    Cff(Box<Cff>),
+    Cff2(Box<Cff2>)
+}
+
+/// The outline of a single glyph, in the representation of the font's outline format
+#[derive(Debug, Clone)]
+pub enum Outline {
+    /// Contours made of on-curve and off-curve points describing quadratic curves
+    TrueType(Vec<Contour>),
+
+    /// Drawing commands describing cubic Bézier curves (used by both CFF and CFF2)
+    Cff(Vec<PathCommand>)
+}
+
+impl Outline {
+    /// Returns the box enclosing all points of the outline, including off-curve control points
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        let points: Vec<(f32, f32)> = match self {
+            Outline::TrueType(contours) => contours.iter().flatten()
+                .map(|point| (point.point.x as f32, point.point.y as f32))
+                .collect(),
+            Outline::Cff(commands) => commands.iter()
+                .flat_map(|command| match *command {
+                    PathCommand::MoveTo { x, y } | PathCommand::LineTo { x, y } => vec![(x, y)],
+                    PathCommand::CurveTo { x1, y1, x2, y2, x, y } => vec![(x1, y1), (x2, y2), (x, y)],
+                    PathCommand::ClosePath => vec![]
+                })
+                .collect()
+        };
+
+        let (&(x, y), rest) = points.split_first()?;
+        let (left, bottom, right, top) = rest.iter().fold((x, y, x, y), |(left, bottom, right, top), &(x, y)| {
+            (left.min(x), bottom.min(y), right.max(x), top.max(y))
+        });
+
+        Some(BoundingBox {
+            left: left.floor() as i16,
+            bottom: bottom.floor() as i16,
+            right: right.ceil() as i16,
+            top: top.ceil() as i16
+        })
+    }
 }
 
 impl Outlines {
@@ -35,7 +81,8 @@ impl Outlines {
 
                 Outlines::TrueType(TrueType::load(loader, config.head, config.maxp.unwrap())?)
             },
-            0x4F54544F => todo!(),
+            0x4F54544F if loader.has_table("CFF2") => Outlines::Cff2(Box::new(Cff2::load(loader)?)),
+            0x4F54544F => Outlines::Cff(Box::new(Cff::load(loader)?)),
             _ => {
                 return Err(
                     FontError::FontFormatError(
@@ -46,4 +93,22 @@ impl Outlines {
             }
         })
     }
+
+    pub fn num_glyphs(&self) -> u16 {
+        match self {
+            Outlines::TrueType(truetype) => truetype.num_glyphs(),
+            Outlines::Cff(cff) => cff.num_glyphs(),
+            Outlines::Cff2(cff2) => cff2.num_glyphs()
+        }
+    }
+
+    pub fn outline<S>(&self, glyph_index: u16, stream: &mut S) -> Result<Option<Outline>>
+        where S: Read + Seek
+    {
+        Ok(match self {
+            Outlines::TrueType(truetype) => truetype.outline(glyph_index, stream)?.map(Outline::TrueType),
+            Outlines::Cff(cff) => cff.outline(glyph_index)?.map(Outline::Cff),
+            Outlines::Cff2(cff2) => cff2.outline(glyph_index)?.map(Outline::Cff)
+        })
+    }
 }
@@ -1,12 +1,13 @@
-use std::io::{Read, Seek};
+use std::{io::{Read, Seek}, sync::Arc};
 
 use log::debug;
 
-use crate::file::{error::{FontError, Result}, loader::FontLoader, table::{FontHeader, Glyphs, Locations, MaximumProfile, MaxpV05, Table}};
+use crate::file::{error::{FontError, Result}, loader::FontLoader, table::{Contour, FontHeader, Glyphs, Locations, MaximumProfile}};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct TrueType {
-    
+    glyphs: Arc<Glyphs>,
+    max_component_depth: u16
 }
 
 impl TrueType {
@@ -21,14 +22,32 @@ impl TrueType {
             return Err(FontError::FontFormatError(None, format!("The following tables are required, but were missing from the table directory: {}", missing_tags)));
         }
 
-        let num_glyphs = match maxp {
-            MaximumProfile::Version05(table) => table.num_glyphs,
-            MaximumProfile::Version10(table) => table.num_glyphs
+        // Composite glyphs need at least one level of nesting, even if maxp claims otherwise
+        let max_component_depth = match maxp {
+            MaximumProfile::Version05(_) => 1,
+            MaximumProfile::Version10(table) => table.component_depth.max(1)
         };
 
-        let locations: Locations = loader.load_table("loca", (header.index_to_loc_format, num_glyphs))?;
-        let glyphs: Glyphs = loader.load_table("glyf", num_glyphs)?;
+        let locations: Arc<Locations> = loader.load_shared_table("loca", (header.index_to_loc_format, maxp.num_glyphs()))?;
+        let glyphs: Arc<Glyphs> = loader.load_shared_table("glyf", locations)?;
 
-        Ok(TrueType {})
+        Ok(TrueType {
+            glyphs,
+            max_component_depth
+        })
+    }
+
+    pub fn glyphs(&self) -> &Glyphs {
+        &self.glyphs
+    }
+
+    pub fn num_glyphs(&self) -> u16 {
+        self.glyphs.num_glyphs()
+    }
+
+    pub fn outline<S>(&self, glyph_index: u16, stream: &mut S) -> Result<Option<Vec<Contour>>>
+        where S: Read + Seek
+    {
+        self.glyphs.outline(glyph_index, self.max_component_depth, stream)
     }
 }
@@ -0,0 +1,75 @@
+use std::{fs::File, io::{self, BufReader, Read, Seek, SeekFrom}};
+
+use log::info;
+
+use crate::file::{error::Result, woff::{WoffStream, WOFF_SIGNATURE}, woff2::{Woff2Stream, WOFF2_SIGNATURE}};
+
+/// The data of a font file. Fonts in web font containers are presented as regular
+/// sfnt fonts, so tables are loaded the same way for all of them.
+#[derive(Debug)]
+pub enum FontStream {
+    File(BufReader<File>),
+    Woff(Box<WoffStream<BufReader<File>>>),
+    Woff2(Box<Woff2Stream>)
+}
+
+impl FontStream {
+    /// Opens a font file and detects its container format
+    pub fn open(filepath: &str) -> Result<FontStream> {
+        let mut stream = BufReader::new(File::open(filepath)?);
+
+        let mut signature = [0u8; 4];
+        let read = stream.read(&mut signature)?;
+        stream.seek(SeekFrom::Start(0))?;
+
+        Ok(match (read, u32::from_be_bytes(signature)) {
+            (4, WOFF_SIGNATURE) => {
+                info!("font is a WOFF file");
+                FontStream::Woff(Box::new(WoffStream::new(stream)?))
+            },
+            (4, WOFF2_SIGNATURE) => {
+                info!("font is a WOFF2 file");
+                FontStream::Woff2(Box::new(Woff2Stream::new(stream)?))
+            },
+            _ => FontStream::File(stream)
+        })
+    }
+
+    /// Returns the extended metadata of web fonts, an XML document
+    pub fn metadata(&mut self) -> Result<Option<String>> {
+        match self {
+            FontStream::File(_) => Ok(None),
+            FontStream::Woff(woff) => woff.metadata(),
+            FontStream::Woff2(woff) => woff.metadata()
+        }
+    }
+
+    /// Returns the private data block of web fonts
+    pub fn private_data(&mut self) -> Result<Option<Vec<u8>>> {
+        match self {
+            FontStream::File(_) => Ok(None),
+            FontStream::Woff(woff) => woff.private_data(),
+            FontStream::Woff2(woff) => Ok(woff.private_data().map(<[u8]>::to_vec))
+        }
+    }
+}
+
+impl Read for FontStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            FontStream::File(stream) => stream.read(buf),
+            FontStream::Woff(stream) => stream.read(buf),
+            FontStream::Woff2(stream) => stream.read(buf)
+        }
+    }
+}
+
+impl Seek for FontStream {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            FontStream::File(stream) => stream.seek(pos),
+            FontStream::Woff(stream) => stream.seek(pos),
+            FontStream::Woff2(stream) => stream.seek(pos)
+        }
+    }
+}
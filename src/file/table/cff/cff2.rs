@@ -0,0 +1,248 @@
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use log::debug;
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, error::{FontError, Result}, loader::TableDirectoryEntry, table::{ItemVariationStore, Table}};
+
+use super::{charstring::{self, BlendContext, CharString, CharStringContext}, dict, Dict, FdSelect, Index, PrivateData};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Cff2Header {
+    pub major_version:      u8,
+    pub minor_version:      u8,
+    pub header_size:        u8,
+    pub top_dict_length:    u16
+}
+
+/// The Compact Font Format 2 table, containing PostScript outlines that may vary
+#[derive(Debug, Clone)]
+pub struct CompactFontFormat2 {
+    pub header: Cff2Header,
+    pub top_dict: Dict,
+    pub global_subrs: Index,
+    pub char_strings: Index,
+    pub variation_store: ItemVariationStore,
+
+    pub font_dicts: Vec<PrivateData>,
+    pub fd_select: Option<FdSelect>,
+
+    /// Region scalars of every item variation data subtable at the selected instance
+    scalars: Vec<Vec<f32>>
+}
+
+impl Table for CompactFontFormat2 {
+    type UserArgsType = ();
+
+    fn get_table_name() -> &'static str {
+        "Compact Font Format 2"
+    }
+
+    fn load_impl<S>(entry: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
+        where S: Read + Seek
+    {
+        let mut data = vec![0u8; entry.length as usize];
+        stream.read_exact(&mut data)?;
+
+        let mut cursor = Cursor::new(data.as_slice());
+        let header: Cff2Header = deserialize_from(&mut cursor)?;
+        debug!("{:?}", header);
+
+        if header.major_version != 2 {
+            return Err(FontError::FontFormatError(
+                Some(entry.offset),
+                format!("Unsupported CFF2 version {}.{}", header.major_version, header.minor_version)
+            ));
+        }
+
+        let top_dict_start = header.header_size as usize;
+        let top_dict_data = data.get(top_dict_start..top_dict_start + header.top_dict_length as usize).ok_or_else(|| {
+            FontError::FontFormatError(Some(entry.offset), "Top DICT lies outside of the CFF2 table".into())
+        })?;
+
+        let top_dict = Dict::parse(top_dict_data)?;
+        debug!("Top DICT: {:?}", top_dict);
+
+        cursor.seek(SeekFrom::Start((top_dict_start + top_dict_data.len()) as u64))?;
+        let global_subrs = Index::load(&mut cursor, 4)?;
+
+        let Some(char_strings_offset) = top_dict.get_number(dict::CHAR_STRINGS) else {
+            return Err(FontError::FontFormatError(Some(entry.offset), "CFF2 font has no CharStrings".into()));
+        };
+        cursor.seek(SeekFrom::Start(char_strings_offset as u64))?;
+        let char_strings = Index::load(&mut cursor, 4)?;
+
+        // The variation store is preceded by its length
+        let variation_store = match top_dict.get_number(dict::VARIATION_STORE) {
+            Some(offset) => {
+                cursor.seek(SeekFrom::Start(offset as u64 + 2))?;
+                ItemVariationStore::load(&mut cursor)?
+            },
+            None => ItemVariationStore::default()
+        };
+
+        let region_counts: Vec<usize> = variation_store.data.iter()
+            .map(|data| data.region_indices.len())
+            .collect();
+
+        let Some(fd_array_offset) = top_dict.get_number(dict::FD_ARRAY) else {
+            return Err(FontError::FontFormatError(Some(entry.offset), "CFF2 font has no FDArray".into()));
+        };
+        let font_dicts = PrivateData::load_font_dicts(&data, fd_array_offset as u64, Some(&region_counts))?;
+
+        let fd_select = match top_dict.get_number(dict::FD_SELECT) {
+            Some(offset) => {
+                cursor.seek(SeekFrom::Start(offset as u64))?;
+                Some(FdSelect::load(char_strings.len() as u16, &mut cursor)?)
+            },
+            None => None
+        };
+
+        let mut table = CompactFontFormat2 {
+            header,
+            top_dict,
+            global_subrs,
+            char_strings,
+            variation_store,
+
+            font_dicts,
+            fd_select,
+
+            scalars: vec![]
+        };
+
+        table.set_coordinates(&[]);
+        Ok(table)
+    }
+}
+
+impl CompactFontFormat2 {
+    pub fn num_glyphs(&self) -> u16 {
+        self.char_strings.len() as u16
+    }
+
+    /// Returns the matrix that maps charstring coordinates to text space
+    pub fn font_matrix(&self) -> [f64; 6] {
+        match self.top_dict.get(dict::FONT_MATRIX) {
+            Some(&[a, b, c, d, e, f]) => [a, b, c, d, e, f],
+            _ => [0.001, 0.0, 0.0, 0.001, 0.0, 0.0]
+        }
+    }
+
+    /// Selects the instance outlines are produced for, given as normalized coordinates
+    /// (-1 to 1) for each variation axis. Axes without a coordinate use their default.
+    pub fn set_coordinates(&mut self, coordinates: &[f32]) {
+        self.scalars = (0..self.variation_store.data.len())
+            .map(|index| self.variation_store.region_scalars(index, coordinates))
+            .collect();
+    }
+
+    /// Returns the Private DICT that applies to a glyph
+    pub fn private_data(&self, glyph_index: u16) -> Result<&PrivateData> {
+        PrivateData::select(&self.font_dicts, self.fd_select.as_ref(), glyph_index)
+    }
+
+    /// Interprets the charstring of a glyph at the selected instance
+    pub fn char_string(&self, glyph_index: u16) -> Result<CharString> {
+        let data = self.char_strings.get(glyph_index as usize).ok_or_else(|| {
+            FontError::FontFormatError(
+                None,
+                format!("Glyph index {} is out of range (font has {} glyphs)", glyph_index, self.num_glyphs())
+            )
+        })?;
+
+        let private = self.private_data(glyph_index)?;
+        let context = CharStringContext {
+            global_subrs: &self.global_subrs,
+            local_subrs: &private.subrs,
+            default_width_x: 0.0,
+            nominal_width_x: 0.0,
+            variations: Some(BlendContext {
+                scalars: &self.scalars,
+                vsindex: private.vsindex
+            })
+        };
+
+        charstring::interpret(data, &context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::file::table::{cff::{Dict, FdSelect, Index, PathCommand, PrivateData}, ItemVariationData, ItemVariationStore, RegionAxisCoordinates, VariationRegion};
+
+    use super::{Cff2Header, CompactFontFormat2};
+
+    /// Encodes small integers as single byte operands
+    fn num(value: i32) -> u8 {
+        assert!((-107..=107).contains(&value));
+        (value + 139) as u8
+    }
+
+    /// A font with one axis and two glyphs that move to (100, 100) at the default instance.
+    /// Glyph 0 uses Font DICT 0 (vsindex 0, one region peaking at 1), glyph 1 Font DICT 1
+    /// (vsindex 1, that region and one peaking at -1).
+    fn font() -> CompactFontFormat2 {
+        let char_string_0 = [num(100), num(100), num(10), num(20), num(2), 16, 21];
+        let char_string_1 = [num(100), num(100), num(10), num(20), num(40), num(80), num(2), 16, 21];
+
+        let mut data = vec![0, 0, 0, 2, 1, 1, 1 + char_string_0.len() as u8, 1 + (char_string_0.len() + char_string_1.len()) as u8];
+        data.extend(char_string_0);
+        data.extend(char_string_1);
+
+        let region = |peak: f32| VariationRegion { axes: vec![RegionAxisCoordinates { start: peak.min(0.0), peak, end: peak.max(0.0) }] };
+
+        let mut table = CompactFontFormat2 {
+            header: Cff2Header { major_version: 2, minor_version: 0, header_size: 5, top_dict_length: 0 },
+            top_dict: Dict::default(),
+            global_subrs: Index::default(),
+            char_strings: Index::load(&mut Cursor::new(&data), 4).unwrap(),
+            variation_store: ItemVariationStore {
+                format: 1,
+                regions: vec![region(1.0), region(-1.0)],
+                data: vec![
+                    ItemVariationData { item_count: 0, region_indices: vec![0] },
+                    ItemVariationData { item_count: 0, region_indices: vec![0, 1] }
+                ]
+            },
+
+            font_dicts: vec![PrivateData::default(), PrivateData { vsindex: 1, ..PrivateData::default() }],
+            fd_select: Some(FdSelect::Array(vec![0, 1])),
+
+            scalars: vec![]
+        };
+
+        table.set_coordinates(&[]);
+        table
+    }
+
+    fn move_to(table: &CompactFontFormat2, glyph_index: u16) -> PathCommand {
+        table.char_string(glyph_index).unwrap().commands[0]
+    }
+
+    #[test]
+    fn glyphs_use_the_private_dict_of_their_font_dict() {
+        let table = font();
+
+        assert_eq!(table.private_data(0).unwrap().vsindex, 0);
+        assert_eq!(table.private_data(1).unwrap().vsindex, 1);
+        assert!(table.private_data(2).is_err());
+    }
+
+    #[test]
+    fn coordinates_select_the_instance() {
+        let mut table = font();
+        assert_eq!(move_to(&table, 0), PathCommand::MoveTo { x: 100.0, y: 100.0 });
+        assert_eq!(move_to(&table, 1), PathCommand::MoveTo { x: 100.0, y: 100.0 });
+
+        table.set_coordinates(&[0.5]);
+        assert_eq!(move_to(&table, 0), PathCommand::MoveTo { x: 105.0, y: 110.0 });
+        assert_eq!(move_to(&table, 1), PathCommand::MoveTo { x: 105.0, y: 120.0 });
+
+        table.set_coordinates(&[-1.0]);
+        assert_eq!(move_to(&table, 0), PathCommand::MoveTo { x: 100.0, y: 100.0 });
+        assert_eq!(move_to(&table, 1), PathCommand::MoveTo { x: 120.0, y: 180.0 });
+    }
+}
@@ -0,0 +1,69 @@
+use std::io::{Read, Seek};
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::{FontError, Result}};
+
+use super::strings::{EXPERT_CHARSET, EXPERT_SUBSET_CHARSET};
+
+/// Maps glyph indices to SIDs (or CIDs in CID-keyed fonts)
+#[derive(Debug, Clone)]
+pub enum Charset {
+    IsoAdobe,
+    Expert,
+    ExpertSubset,
+    Custom(Vec<u16>)
+}
+
+impl Charset {
+    /// Loads a custom charset located at the current stream position
+    pub fn load<S>(num_glyphs: u16, stream: &mut S) -> Result<Charset>
+        where S: Read + Seek
+    {
+        let position = stream.stream_position()?;
+        let format: u8 = deserialize_from(stream)?;
+
+        let mut sids: Vec<u16> = Vec::with_capacity(num_glyphs as usize);
+        sids.push(0);
+
+        match format {
+            0 => sids.extend(deserialize_vec_from::<u16, _>(num_glyphs.saturating_sub(1) as usize, stream)?),
+            1 | 2 => {
+                while sids.len() < num_glyphs as usize {
+                    let first: u16 = deserialize_from(stream)?;
+                    let num_left: u16 = match format {
+                        1 => deserialize_from::<u8, _>(stream)? as u16,
+                        _ => deserialize_from(stream)?
+                    };
+
+                    sids.extend((0..=num_left).map(|offset| first.wrapping_add(offset)));
+                }
+
+                sids.truncate(num_glyphs as usize);
+            },
+            _ => return Err(FontError::FontFormatError(Some(position as u32), format!("Unknown charset format {}", format)))
+        }
+
+        Ok(Charset::Custom(sids))
+    }
+
+    /// Returns the SID (or CID) of a glyph
+    pub fn sid(&self, glyph_index: u16) -> Option<u16> {
+        match self {
+            Charset::IsoAdobe       => (glyph_index <= 228).then_some(glyph_index),
+            Charset::Expert         => EXPERT_CHARSET.get(glyph_index as usize).copied(),
+            Charset::ExpertSubset   => EXPERT_SUBSET_CHARSET.get(glyph_index as usize).copied(),
+            Charset::Custom(sids)   => sids.get(glyph_index as usize).copied()
+        }
+    }
+
+    /// Returns the glyph with the given SID (or CID)
+    pub fn glyph_index(&self, sid: u16) -> Option<u16> {
+        let position = match self {
+            Charset::IsoAdobe       => return (sid <= 228).then_some(sid),
+            Charset::Expert         => EXPERT_CHARSET.iter().position(|&item| item == sid),
+            Charset::ExpertSubset   => EXPERT_SUBSET_CHARSET.iter().position(|&item| item == sid),
+            Charset::Custom(sids)   => sids.iter().position(|&item| item == sid)
+        };
+
+        position.map(|index| index as u16)
+    }
+}
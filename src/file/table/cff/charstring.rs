@@ -0,0 +1,743 @@
+use crate::file::error::{FontError, Result};
+
+use super::index::Index;
+
+const MAX_STACK_SIZE:       usize = 48;
+const MAX_CFF2_STACK_SIZE:  usize = 513;
+const MAX_SUBR_DEPTH:       usize = 10;
+const TRANSIENT_ARRAY_SIZE: usize = 32;
+
+/// A single drawing command of a glyph outline made of cubic Bézier curves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathCommand {
+    MoveTo { x: f32, y: f32 },
+    LineTo { x: f32, y: f32 },
+    CurveTo { x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32 },
+    ClosePath
+}
+
+/// Parameters that accented characters built with `endchar` refer to
+#[derive(Debug, Clone, Copy)]
+pub struct Seac {
+    pub adx: f32,
+    pub ady: f32,
+    pub base_code: u8,
+    pub accent_code: u8
+}
+
+/// The result of running a Type 2 charstring
+#[derive(Debug, Clone, Default)]
+pub struct CharString {
+    pub commands: Vec<PathCommand>,
+    pub width: f32,
+    pub seac: Option<Seac>
+}
+
+/// Everything a charstring may refer to while it is being interpreted
+pub struct CharStringContext<'a> {
+    pub global_subrs: &'a Index,
+    pub local_subrs: &'a Index,
+    pub default_width_x: f32,
+    pub nominal_width_x: f32,
+
+    /// Only present for CFF2 charstrings
+    pub variations: Option<BlendContext<'a>>
+}
+
+/// Data needed to evaluate the `blend` operator of CFF2 charstrings
+pub struct BlendContext<'a> {
+    /// Region scalars for every item variation data subtable, at the current instance
+    pub scalars: &'a [Vec<f32>],
+
+    /// The item variation data used until a charstring selects another one with `vsindex`
+    pub vsindex: usize
+}
+
+/// Returns the number that is added to subroutine indices in charstrings
+pub fn subr_bias(subrs: &Index) -> i32 {
+    match subrs.len() {
+        0..=1239 => 107,
+        1240..=33899 => 1131,
+        _ => 32768
+    }
+}
+
+struct Interpreter<'a, 'b> {
+    context: &'b CharStringContext<'a>,
+
+    stack: Vec<f32>,
+    max_stack_size: usize,
+    vsindex: usize,
+    transient: [f32; TRANSIENT_ARRAY_SIZE],
+    num_stems: usize,
+    width_parsed: bool,
+    path_open: bool,
+    finished: bool,
+
+    x: f32,
+    y: f32,
+    result: CharString
+}
+
+fn error(msg: &str) -> FontError {
+    FontError::FontFormatError(None, format!("Invalid charstring: {}", msg))
+}
+
+/// Interprets a Type 2 charstring and returns the outline it describes
+pub fn interpret(data: &[u8], context: &CharStringContext) -> Result<CharString> {
+    let max_stack_size = match context.variations {
+        Some(_) => MAX_CFF2_STACK_SIZE,
+        None => MAX_STACK_SIZE
+    };
+
+    let mut interpreter = Interpreter {
+        context,
+
+        stack: Vec::with_capacity(max_stack_size),
+        max_stack_size,
+        vsindex: context.variations.as_ref().map_or(0, |variations| variations.vsindex),
+        transient: [0.0; TRANSIENT_ARRAY_SIZE],
+        num_stems: 0,
+        // CFF2 charstrings never contain an advance width
+        width_parsed: context.variations.is_some(),
+        path_open: false,
+        finished: false,
+
+        x: 0.0,
+        y: 0.0,
+        result: CharString {
+            width: context.default_width_x,
+            ..Default::default()
+        }
+    };
+
+    interpreter.execute(data, 0)?;
+    interpreter.close_path();
+
+    Ok(interpreter.result)
+}
+
+impl<'a, 'b> Interpreter<'a, 'b> {
+    fn push(&mut self, value: f32) -> Result<()> {
+        if self.stack.len() >= self.max_stack_size {
+            return Err(error("argument stack overflow"));
+        }
+
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<f32> {
+        self.stack.pop().ok_or_else(|| error("argument stack underflow"))
+    }
+
+    /// Removes the advance width from the stack if this is the first stack-clearing
+    /// operator and it has more arguments than it expects
+    fn parse_width(&mut self, has_extra_argument: bool) {
+        if !self.width_parsed {
+            self.width_parsed = true;
+            if has_extra_argument && !self.stack.is_empty() {
+                self.result.width = self.context.nominal_width_x + self.stack.remove(0);
+            }
+        }
+    }
+
+    fn close_path(&mut self) {
+        if self.path_open {
+            self.result.commands.push(PathCommand::ClosePath);
+            self.path_open = false;
+        }
+    }
+
+    fn move_to(&mut self, dx: f32, dy: f32) {
+        self.close_path();
+
+        self.x += dx;
+        self.y += dy;
+        self.result.commands.push(PathCommand::MoveTo { x: self.x, y: self.y });
+        self.path_open = true;
+    }
+
+    fn line_to(&mut self, dx: f32, dy: f32) {
+        self.x += dx;
+        self.y += dy;
+        self.result.commands.push(PathCommand::LineTo { x: self.x, y: self.y });
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn curve_to(&mut self, dx1: f32, dy1: f32, dx2: f32, dy2: f32, dx3: f32, dy3: f32) {
+        let (x1, y1) = (self.x + dx1, self.y + dy1);
+        let (x2, y2) = (x1 + dx2, y1 + dy2);
+        self.x = x2 + dx3;
+        self.y = y2 + dy3;
+
+        self.result.commands.push(PathCommand::CurveTo { x1, y1, x2, y2, x: self.x, y: self.y });
+    }
+
+    fn blend(&mut self) -> Result<()> {
+        let Some(variations) = &self.context.variations else {
+            return Err(error("blend is only allowed in CFF2 charstrings"));
+        };
+
+        let num_values = self.pop()? as usize;
+        let scalars = variations.scalars.get(self.vsindex)
+            .ok_or_else(|| error(&format!("item variation data {} does not exist", self.vsindex)))?;
+
+        let num_operands = num_values * (scalars.len() + 1);
+        if num_operands > self.stack.len() {
+            return Err(error("too few arguments for blend"));
+        }
+
+        // The default values come first, followed by the deltas for each of them
+        let base = self.stack.len() - num_operands;
+        for value in 0..num_values {
+            let deltas_start = base + num_values + value * scalars.len();
+            let adjustment: f32 = self.stack[deltas_start..deltas_start + scalars.len()].iter()
+                .zip(scalars)
+                .map(|(delta, scalar)| delta * scalar)
+                .sum();
+
+            self.stack[base + value] += adjustment;
+        }
+
+        self.stack.truncate(base + num_values);
+        Ok(())
+    }
+
+    fn call_subr(&mut self, subrs: &Index, depth: usize) -> Result<()> {
+        let index = self.pop()? as i32 + subr_bias(subrs);
+        let subr = usize::try_from(index).ok()
+            .and_then(|index| subrs.get(index))
+            .ok_or_else(|| error(&format!("subroutine {} does not exist", index)))?;
+
+        self.execute(subr, depth + 1)
+    }
+
+    fn execute(&mut self, data: &[u8], depth: usize) -> Result<()> {
+        if depth > MAX_SUBR_DEPTH {
+            return Err(error("subroutines are nested too deeply"));
+        }
+
+        let mut position = 0;
+        let byte_at = |position: usize| data.get(position).copied().ok_or_else(|| error("unexpected end of data"));
+
+        while position < data.len() && !self.finished {
+            let b0 = data[position];
+            position += 1;
+
+            match b0 {
+                // hstem, vstem, hstemhm, vstemhm
+                1 | 3 | 18 | 23 => {
+                    self.parse_width(self.stack.len() % 2 == 1);
+                    self.num_stems += self.stack.len() / 2;
+                    self.stack.clear();
+                },
+                // vmoveto
+                4 => {
+                    self.parse_width(self.stack.len() > 1);
+                    let dy = self.pop()?;
+                    self.move_to(0.0, dy);
+                    self.stack.clear();
+                },
+                // rlineto
+                5 => {
+                    for pair in self.stack.clone().chunks_exact(2) {
+                        self.line_to(pair[0], pair[1]);
+                    }
+                    self.stack.clear();
+                },
+                // hlineto, vlineto
+                6 | 7 => {
+                    let mut horizontal = b0 == 6;
+                    for delta in self.stack.clone() {
+                        if horizontal {
+                            self.line_to(delta, 0.0);
+                        } else {
+                            self.line_to(0.0, delta);
+                        }
+                        horizontal = !horizontal;
+                    }
+                    self.stack.clear();
+                },
+                // rrcurveto
+                8 => {
+                    for args in self.stack.clone().chunks_exact(6) {
+                        self.curve_to(args[0], args[1], args[2], args[3], args[4], args[5]);
+                    }
+                    self.stack.clear();
+                },
+                // callsubr
+                10 => {
+                    let subrs = self.context.local_subrs;
+                    self.call_subr(subrs, depth)?;
+                },
+                // return
+                11 => return Ok(()),
+                12 => {
+                    let b1 = byte_at(position)?;
+                    position += 1;
+                    self.execute_escaped(b1)?;
+                },
+                // vsindex
+                15 => {
+                    self.vsindex = self.pop()? as usize;
+                    self.stack.clear();
+                },
+                // blend
+                16 => self.blend()?,
+                // endchar
+                14 => {
+                    self.parse_width(self.stack.len() == 1 || self.stack.len() == 5);
+                    if let [adx, ady, base_code, accent_code] = self.stack[..] {
+                        self.result.seac = Some(Seac {
+                            adx, ady,
+                            base_code: base_code as u8,
+                            accent_code: accent_code as u8
+                        });
+                    }
+
+                    self.stack.clear();
+                    self.close_path();
+                    self.finished = true;
+                },
+                // hintmask, cntrmask
+                19 | 20 => {
+                    self.parse_width(self.stack.len() % 2 == 1);
+                    self.num_stems += self.stack.len() / 2;
+                    self.stack.clear();
+
+                    position += self.num_stems.div_ceil(8);
+                },
+                // rmoveto
+                21 => {
+                    self.parse_width(self.stack.len() > 2);
+                    let dy = self.pop()?;
+                    let dx = self.pop()?;
+                    self.move_to(dx, dy);
+                    self.stack.clear();
+                },
+                // hmoveto
+                22 => {
+                    self.parse_width(self.stack.len() > 1);
+                    let dx = self.pop()?;
+                    self.move_to(dx, 0.0);
+                    self.stack.clear();
+                },
+                // rcurveline
+                24 => {
+                    let args = std::mem::take(&mut self.stack);
+                    if args.len() < 8 {
+                        return Err(error("rcurveline needs at least 8 arguments"));
+                    }
+
+                    let (curves, line) = args.split_at(args.len() - 2);
+                    for curve in curves.chunks_exact(6) {
+                        self.curve_to(curve[0], curve[1], curve[2], curve[3], curve[4], curve[5]);
+                    }
+                    self.line_to(line[0], line[1]);
+                },
+                // rlinecurve
+                25 => {
+                    let args = std::mem::take(&mut self.stack);
+                    if args.len() < 8 {
+                        return Err(error("rlinecurve needs at least 8 arguments"));
+                    }
+
+                    let (lines, curve) = args.split_at(args.len() - 6);
+                    for line in lines.chunks_exact(2) {
+                        self.line_to(line[0], line[1]);
+                    }
+                    self.curve_to(curve[0], curve[1], curve[2], curve[3], curve[4], curve[5]);
+                },
+                // vvcurveto
+                26 => {
+                    let args = std::mem::take(&mut self.stack);
+                    let (mut dx1, rest) = match args.len() % 2 {
+                        1 => (args[0], &args[1..]),
+                        _ => (0.0, &args[..])
+                    };
+
+                    for curve in rest.chunks_exact(4) {
+                        self.curve_to(dx1, curve[0], curve[1], curve[2], 0.0, curve[3]);
+                        dx1 = 0.0;
+                    }
+                },
+                // hhcurveto
+                27 => {
+                    let args = std::mem::take(&mut self.stack);
+                    let (mut dy1, rest) = match args.len() % 2 {
+                        1 => (args[0], &args[1..]),
+                        _ => (0.0, &args[..])
+                    };
+
+                    for curve in rest.chunks_exact(4) {
+                        self.curve_to(curve[0], dy1, curve[1], curve[2], curve[3], 0.0);
+                        dy1 = 0.0;
+                    }
+                },
+                // shortint
+                28 => {
+                    let value = i16::from_be_bytes([byte_at(position)?, byte_at(position + 1)?]);
+                    position += 2;
+                    self.push(value as f32)?;
+                },
+                // callgsubr
+                29 => {
+                    let subrs = self.context.global_subrs;
+                    self.call_subr(subrs, depth)?;
+                },
+                // vhcurveto, hvcurveto
+                30 | 31 => {
+                    let args = std::mem::take(&mut self.stack);
+                    let mut horizontal = b0 == 31;
+                    let mut index = 0;
+
+                    while index + 4 <= args.len() {
+                        let last = if args.len() - index == 5 { args[index + 4] } else { 0.0 };
+                        let curve = &args[index..index + 4];
+
+                        if horizontal {
+                            self.curve_to(curve[0], 0.0, curve[1], curve[2], last, curve[3]);
+                        } else {
+                            self.curve_to(0.0, curve[0], curve[1], curve[2], curve[3], last);
+                        }
+
+                        horizontal = !horizontal;
+                        index += 4;
+                    }
+                },
+                32..=246 => self.push(b0 as f32 - 139.0)?,
+                247..=250 => {
+                    let b1 = byte_at(position)?;
+                    position += 1;
+                    self.push((b0 as f32 - 247.0) * 256.0 + b1 as f32 + 108.0)?;
+                },
+                251..=254 => {
+                    let b1 = byte_at(position)?;
+                    position += 1;
+                    self.push(-(b0 as f32 - 251.0) * 256.0 - b1 as f32 - 108.0)?;
+                },
+                255 => {
+                    let value = i32::from_be_bytes([
+                        byte_at(position)?, byte_at(position + 1)?, byte_at(position + 2)?, byte_at(position + 3)?
+                    ]);
+                    position += 4;
+                    self.push(value as f32 / 65536.0)?;
+                },
+                _ => return Err(error(&format!("reserved operator {}", b0)))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn execute_escaped(&mut self, operator: u8) -> Result<()> {
+        match operator {
+            // and, or
+            3 | 4 => {
+                let b = self.pop()? != 0.0;
+                let a = self.pop()? != 0.0;
+                let value = if operator == 3 { a && b } else { a || b };
+                self.push(value as u8 as f32)?;
+            },
+            // not
+            5 => {
+                let a = self.pop()?;
+                self.push((a == 0.0) as u8 as f32)?;
+            },
+            // abs
+            9 => {
+                let a = self.pop()?;
+                self.push(a.abs())?;
+            },
+            // add, sub, div, mul
+            10 | 11 | 12 | 24 => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push(match operator {
+                    10 => a + b,
+                    11 => a - b,
+                    12 => a / b,
+                    _  => a * b
+                })?;
+            },
+            // neg
+            14 => {
+                let a = self.pop()?;
+                self.push(-a)?;
+            },
+            // eq
+            15 => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push((a == b) as u8 as f32)?;
+            },
+            // drop
+            18 => {
+                self.pop()?;
+            },
+            // put
+            20 => {
+                let index = self.pop()? as usize;
+                let value = self.pop()?;
+                *self.transient.get_mut(index).ok_or_else(|| error("transient array index out of range"))? = value;
+            },
+            // get
+            21 => {
+                let index = self.pop()? as usize;
+                let value = *self.transient.get(index).ok_or_else(|| error("transient array index out of range"))?;
+                self.push(value)?;
+            },
+            // ifelse
+            22 => {
+                let v2 = self.pop()?;
+                let v1 = self.pop()?;
+                let s2 = self.pop()?;
+                let s1 = self.pop()?;
+                self.push(if v1 <= v2 { s1 } else { s2 })?;
+            },
+            // random; charstrings using it cannot be rendered deterministically anyway
+            23 => self.push(0.5)?,
+            // sqrt
+            26 => {
+                let a = self.pop()?;
+                self.push(a.sqrt())?;
+            },
+            // dup
+            27 => {
+                let a = *self.stack.last().ok_or_else(|| error("argument stack underflow"))?;
+                self.push(a)?;
+            },
+            // exch
+            28 => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push(b)?;
+                self.push(a)?;
+            },
+            // index
+            29 => {
+                let index = self.pop()?.max(0.0) as usize;
+                let value = *self.stack.iter().rev().nth(index).ok_or_else(|| error("index out of range"))?;
+                self.push(value)?;
+            },
+            // roll
+            30 => {
+                let shift = self.pop()? as i32;
+                let count = self.pop()? as usize;
+                if count > self.stack.len() {
+                    return Err(error("roll exceeds the argument stack"));
+                }
+
+                if count > 0 {
+                    let start = self.stack.len() - count;
+                    let shift = shift.rem_euclid(count as i32) as usize;
+                    self.stack[start..].rotate_right(shift);
+                }
+            },
+            // hflex
+            34 => {
+                let args = self.take_args(7)?;
+                self.curve_to(args[0], 0.0, args[1], args[2], args[3], 0.0);
+                self.curve_to(args[4], 0.0, args[5], -args[2], args[6], 0.0);
+            },
+            // flex
+            35 => {
+                let args = self.take_args(13)?;
+                self.curve_to(args[0], args[1], args[2], args[3], args[4], args[5]);
+                self.curve_to(args[6], args[7], args[8], args[9], args[10], args[11]);
+            },
+            // hflex1
+            36 => {
+                let args = self.take_args(9)?;
+                self.curve_to(args[0], args[1], args[2], args[3], args[4], 0.0);
+                self.curve_to(args[5], 0.0, args[6], args[7], args[8], -(args[1] + args[3] + args[7]));
+            },
+            // flex1
+            37 => {
+                let args = self.take_args(11)?;
+                let dx: f32 = args[0..10].iter().step_by(2).sum();
+                let dy: f32 = args[1..10].iter().step_by(2).sum();
+                let (dx6, dy6) = if dx.abs() > dy.abs() { (args[10], -dy) } else { (-dx, args[10]) };
+
+                self.curve_to(args[0], args[1], args[2], args[3], args[4], args[5]);
+                self.curve_to(args[6], args[7], args[8], args[9], dx6, dy6);
+            },
+            _ => return Err(error(&format!("reserved operator 12 {}", operator)))
+        }
+
+        Ok(())
+    }
+
+    fn take_args(&mut self, count: usize) -> Result<Vec<f32>> {
+        if self.stack.len() < count {
+            return Err(error("too few arguments for flex operator"));
+        }
+
+        let args = self.stack.split_off(self.stack.len() - count);
+        self.stack.clear();
+        Ok(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{interpret, BlendContext, CharString, CharStringContext, Index, PathCommand};
+
+    /// Encodes small integers as single byte operands
+    fn num(value: i32) -> u8 {
+        assert!((-107..=107).contains(&value));
+        (value + 139) as u8
+    }
+
+    fn run(data: &[u8]) -> CharString {
+        let subrs = Index::default();
+        let context = CharStringContext {
+            global_subrs: &subrs,
+            local_subrs: &subrs,
+            default_width_x: 500.0,
+            nominal_width_x: 100.0,
+            variations: None
+        };
+
+        interpret(data, &context).unwrap()
+    }
+
+    /// Runs a CFF2 charstring with the given region scalars for each item variation data subtable
+    fn run_cff2(data: &[u8], scalars: &[Vec<f32>], vsindex: usize) -> Option<CharString> {
+        let subrs = Index::default();
+        let context = CharStringContext {
+            global_subrs: &subrs,
+            local_subrs: &subrs,
+            default_width_x: 0.0,
+            nominal_width_x: 0.0,
+            variations: Some(BlendContext { scalars, vsindex })
+        };
+
+        interpret(data, &context).ok()
+    }
+
+    fn end_point(charstring: &CharString) -> (f32, f32) {
+        match charstring.commands.iter().rev().find(|command| **command != PathCommand::ClosePath) {
+            Some(PathCommand::CurveTo { x, y, .. } | PathCommand::LineTo { x, y } | PathCommand::MoveTo { x, y }) => (*x, *y),
+            _ => panic!("charstring has no path")
+        }
+    }
+
+    #[test]
+    fn hintmask_skips_one_byte_per_eight_stems() {
+        // Two hstemhm stems and one implicit vstem: one mask byte, which would be
+        // read as the start of a 32 bit number if it wasn't skipped
+        let data = [num(10), num(20), num(30), num(40), 18, num(50), num(60), 19, 0xFF, num(1), num(2), 21, 14];
+        assert_eq!(run(&data).commands, vec![PathCommand::MoveTo { x: 1.0, y: 2.0 }, PathCommand::ClosePath]);
+
+        // Nine stems need two mask bytes, the second one would otherwise end the charstring
+        let mut data: Vec<u8> = (0..18).map(|_| num(1)).collect();
+        data.extend([18, 19, 0x0E, 0x0E, num(1), num(2), 21, 14]);
+        assert_eq!(run(&data).commands, vec![PathCommand::MoveTo { x: 1.0, y: 2.0 }, PathCommand::ClosePath]);
+    }
+
+    #[test]
+    fn hintmask_counts_stems_of_later_masks() {
+        // Every mask of a charstring has the same length
+        let data = [num(1), num(1), 1, 19, 0x0E, 19, 0x0E, num(3), num(4), 21, 14];
+        assert_eq!(run(&data).commands, vec![PathCommand::MoveTo { x: 3.0, y: 4.0 }, PathCommand::ClosePath]);
+    }
+
+    #[test]
+    fn width_is_taken_from_the_first_stack_clearing_operator() {
+        assert_eq!(run(&[num(20), num(1), num(2), 21, 14]).width, 120.0);
+        assert_eq!(run(&[num(1), num(2), 21, 14]).width, 500.0);
+        assert_eq!(run(&[num(7), 14]).width, 107.0);
+    }
+
+    #[test]
+    fn flex_draws_two_curves() {
+        let mut data: Vec<u8> = (1..=12).map(num).collect();
+        data.extend([num(50), 12, 35, 14]);
+
+        let charstring = run(&data);
+        assert_eq!(charstring.commands, vec![
+            PathCommand::CurveTo { x1: 1.0, y1: 2.0, x2: 4.0, y2: 6.0, x: 9.0, y: 12.0 },
+            PathCommand::CurveTo { x1: 16.0, y1: 20.0, x2: 25.0, y2: 30.0, x: 36.0, y: 42.0 }
+        ]);
+    }
+
+    #[test]
+    fn hflex_returns_to_the_start_height() {
+        let mut data: Vec<u8> = (1..=7).map(num).collect();
+        data.extend([12, 34, 14]);
+        assert_eq!(end_point(&run(&data)), (25.0, 0.0));
+
+        let mut data: Vec<u8> = (1..=9).map(num).collect();
+        data.extend([12, 36, 14]);
+        assert_eq!(end_point(&run(&data)), (31.0, 0.0));
+    }
+
+    #[test]
+    fn flex1_picks_the_last_coordinate_by_direction() {
+        // Mostly horizontal, the last point returns to the start height
+        let data = [num(10), num(1), num(10), num(1), num(10), num(1), num(10), num(-1), num(10), num(-1), num(5), 12, 37, 14];
+        assert_eq!(end_point(&run(&data)), (55.0, 0.0));
+
+        // Mostly vertical, the last point returns to the start x
+        let data = [num(1), num(10), num(1), num(10), num(1), num(10), num(-1), num(10), num(-1), num(10), num(5), 12, 37, 14];
+        assert_eq!(end_point(&run(&data)), (0.0, 55.0));
+    }
+
+    #[test]
+    fn endchar_with_four_arguments_is_seac() {
+        let charstring = run(&[num(10), num(20), num(65), num(97), 14]);
+        let seac = charstring.seac.unwrap();
+        assert_eq!((seac.adx, seac.ady, seac.base_code, seac.accent_code), (10.0, 20.0, 65, 97));
+        assert_eq!(charstring.width, 500.0);
+
+        let charstring = run(&[num(50), num(10), num(20), num(65), num(97), 14]);
+        assert_eq!(charstring.seac.map(|seac| seac.base_code), Some(65));
+        assert_eq!(charstring.width, 150.0);
+
+        assert!(run(&[num(10), num(20), 21, 14]).seac.is_none());
+    }
+
+    #[test]
+    fn blend_adds_the_scaled_deltas() {
+        // Two values with one delta each, scaled by 0.5
+        let data = [num(100), num(100), num(10), num(20), num(2), 16, 21];
+        let charstring = run_cff2(&data, &[vec![0.5]], 0).unwrap();
+
+        assert_eq!(charstring.commands, vec![PathCommand::MoveTo { x: 105.0, y: 110.0 }, PathCommand::ClosePath]);
+        assert_eq!(charstring.width, 0.0);
+    }
+
+    #[test]
+    fn vsindex_selects_the_regions_of_blend() {
+        let scalars = [vec![0.5], vec![0.5, 0.25]];
+
+        // Subtable 1 has two regions, so every value has two deltas
+        let data = [num(1), 15, num(100), num(100), num(10), num(20), num(40), num(80), num(2), 16, 21];
+        let expected = vec![PathCommand::MoveTo { x: 110.0, y: 140.0 }, PathCommand::ClosePath];
+        assert_eq!(run_cff2(&data, &scalars, 0).unwrap().commands, expected);
+
+        // The Private DICT's vsindex applies until the charstring selects another subtable
+        assert_eq!(run_cff2(&data[2..], &scalars, 1).unwrap().commands, expected);
+        assert!(run_cff2(&data[2..], &scalars, 0).is_some_and(|charstring| charstring.commands != expected));
+    }
+
+    #[test]
+    fn invalid_blends_are_errors() {
+        // Missing item variation data, too few deltas
+        assert!(run_cff2(&[num(5), num(1), 16, num(0), 21], &[vec![1.0]], 1).is_none());
+        assert!(run_cff2(&[num(5), num(1), 16, num(0), 21], &[vec![1.0]], 0).is_none());
+
+        // blend is a CFF2 operator
+        let subrs = Index::default();
+        let context = CharStringContext {
+            global_subrs: &subrs,
+            local_subrs: &subrs,
+            default_width_x: 0.0,
+            nominal_width_x: 0.0,
+            variations: None
+        };
+        assert!(interpret(&[num(5), num(1), num(1), 16, 14], &context).is_err());
+    }
+}
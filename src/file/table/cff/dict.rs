@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use crate::file::error::{FontError, Result};
+
+/// Two-byte operators are stored as `(12 << 8) | second_byte`
+pub const fn escaped(operator: u8) -> u16 {
+    (12 << 8) | operator as u16
+}
+
+// Top DICT operators
+pub const NOTICE:               u16 = 1;
+pub const FULL_NAME:            u16 = 2;
+pub const FAMILY_NAME:          u16 = 3;
+pub const FONT_BBOX:            u16 = 5;
+pub const CHARSET:              u16 = 15;
+pub const ENCODING:             u16 = 16;
+pub const CHAR_STRINGS:         u16 = 17;
+pub const PRIVATE:              u16 = 18;
+pub const COPYRIGHT:            u16 = escaped(0);
+pub const CHARSTRING_TYPE:      u16 = escaped(6);
+pub const FONT_MATRIX:          u16 = escaped(7);
+pub const ROS:                  u16 = escaped(30);
+pub const FD_ARRAY:             u16 = escaped(36);
+pub const FD_SELECT:            u16 = escaped(37);
+pub const VARIATION_STORE:      u16 = 24;
+
+// Private DICT operators
+pub const SUBRS:                u16 = 19;
+pub const DEFAULT_WIDTH_X:      u16 = 20;
+pub const NOMINAL_WIDTH_X:      u16 = 21;
+pub const VSINDEX:              u16 = 22;
+
+/// A DICT structure, mapping operators to their operands
+#[derive(Debug, Clone, Default)]
+pub struct Dict {
+    entries: HashMap<u16, Vec<f64>>
+}
+
+impl Dict {
+    pub fn parse(data: &[u8]) -> Result<Dict> {
+        Dict::parse_impl(data, None)
+    }
+
+    /// Parses a CFF2 DICT, which may contain `blend` operators. `region_counts` holds the
+    /// number of regions of every item variation data subtable in the variation store.
+    /// Blended values are resolved to their default.
+    pub fn parse_cff2(data: &[u8], region_counts: &[usize]) -> Result<Dict> {
+        Dict::parse_impl(data, Some(region_counts))
+    }
+
+    fn parse_impl(data: &[u8], region_counts: Option<&[usize]>) -> Result<Dict> {
+        let mut entries: HashMap<u16, Vec<f64>> = HashMap::new();
+        let mut operands: Vec<f64> = vec![];
+        let mut position = 0;
+        let mut vsindex = 0;
+
+        let byte_at = |position: usize| data.get(position).copied().ok_or_else(|| {
+            FontError::FontFormatError(None, "Unexpected end of DICT data".into())
+        });
+
+        while position < data.len() {
+            let b0 = data[position];
+            position += 1;
+
+            match b0 {
+                // blend
+                23 if region_counts.is_some() => {
+                    let num_values = operands.pop().unwrap_or(0.0) as usize;
+                    let num_regions = region_counts.and_then(|counts| counts.get(vsindex)).copied().unwrap_or(0);
+
+                    let num_operands = num_regions.checked_add(1).and_then(|count| num_values.checked_mul(count));
+                    let Some(num_operands) = num_operands.filter(|&count| count <= operands.len()) else {
+                        return Err(FontError::FontFormatError(None, "Too few operands for blend in DICT data".into()));
+                    };
+
+                    // Keep the default values and drop their deltas
+                    operands.truncate(operands.len() - num_operands + num_values);
+                },
+                0..=11 | 13..=27 => {
+                    if b0 as u16 == VSINDEX {
+                        vsindex = operands.first().copied().unwrap_or(0.0) as usize;
+                    }
+
+                    entries.insert(b0 as u16, std::mem::take(&mut operands));
+                },
+                12 => {
+                    let b1 = byte_at(position)?;
+                    position += 1;
+                    entries.insert(escaped(b1), std::mem::take(&mut operands));
+                },
+                28 => {
+                    let value = i16::from_be_bytes([byte_at(position)?, byte_at(position + 1)?]);
+                    position += 2;
+                    operands.push(value as f64);
+                },
+                29 => {
+                    let value = i32::from_be_bytes([
+                        byte_at(position)?, byte_at(position + 1)?, byte_at(position + 2)?, byte_at(position + 3)?
+                    ]);
+                    position += 4;
+                    operands.push(value as f64);
+                },
+                30 => {
+                    let (value, length) = parse_real(&data[position..])?;
+                    position += length;
+                    operands.push(value);
+                },
+                32..=246 => operands.push(b0 as f64 - 139.0),
+                247..=250 => {
+                    let b1 = byte_at(position)?;
+                    position += 1;
+                    operands.push((b0 as f64 - 247.0) * 256.0 + b1 as f64 + 108.0);
+                },
+                251..=254 => {
+                    let b1 = byte_at(position)?;
+                    position += 1;
+                    operands.push(-(b0 as f64 - 251.0) * 256.0 - b1 as f64 - 108.0);
+                },
+                _ => return Err(FontError::FontFormatError(None, format!("Invalid byte {} in DICT data", b0)))
+            }
+        }
+
+        Ok(Dict {
+            entries
+        })
+    }
+
+    pub fn get(&self, operator: u16) -> Option<&[f64]> {
+        self.entries.get(&operator).map(|operands| operands.as_slice())
+    }
+
+    pub fn get_number(&self, operator: u16) -> Option<f64> {
+        self.get(operator)?.first().copied()
+    }
+
+    pub fn get_pair(&self, operator: u16) -> Option<(f64, f64)> {
+        match self.get(operator)? {
+            [first, second, ..] => Some((*first, *second)),
+            _ => None
+        }
+    }
+
+    pub fn contains(&self, operator: u16) -> bool {
+        self.entries.contains_key(&operator)
+    }
+}
+
+/// Parses a real number operand, which is stored as a sequence of nibbles.
+/// Returns the value and the number of bytes consumed.
+fn parse_real(data: &[u8]) -> Result<(f64, usize)> {
+    let mut text = String::new();
+
+    for (index, byte) in data.iter().enumerate() {
+        for nibble in [byte >> 4, byte & 0x0F] {
+            match nibble {
+                0..=9 => text.push(char::from(b'0' + nibble)),
+                0xA => text.push('.'),
+                0xB => text.push('E'),
+                0xC => text.push_str("E-"),
+                0xE => text.push('-'),
+                0xF => {
+                    let value = text.parse::<f64>().map_err(|_| {
+                        FontError::FontFormatError(None, format!("Invalid real number '{}' in DICT data", text))
+                    })?;
+
+                    return Ok((value, index + 1));
+                },
+                _ => return Err(FontError::FontFormatError(None, "Reserved nibble in real number".into()))
+            }
+        }
+    }
+
+    Err(FontError::FontFormatError(None, "Unterminated real number in DICT data".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dict, NOMINAL_WIDTH_X, VSINDEX};
+
+    /// Encodes small integers as single byte operands
+    fn num(value: i32) -> u8 {
+        assert!((-107..=107).contains(&value));
+        (value + 139) as u8
+    }
+
+    #[test]
+    fn blend_keeps_the_default_values() {
+        let data = [num(100), num(50), num(10), num(-10), num(2), 23, NOMINAL_WIDTH_X as u8];
+        let dict = Dict::parse_cff2(&data, &[1]).unwrap();
+
+        assert_eq!(dict.get(NOMINAL_WIDTH_X), Some(&[100.0, 50.0][..]));
+    }
+
+    #[test]
+    fn blend_uses_the_regions_selected_by_vsindex() {
+        let data = [num(1), VSINDEX as u8, num(100), num(10), num(20), num(1), 23, NOMINAL_WIDTH_X as u8];
+        let dict = Dict::parse_cff2(&data, &[1, 2]).unwrap();
+
+        assert_eq!(dict.get_number(VSINDEX), Some(1.0));
+        assert_eq!(dict.get(NOMINAL_WIDTH_X), Some(&[100.0][..]));
+
+        // Without vsindex, the first subtable has a single region, so 10 is the default value
+        // and 100 an operand before the blend
+        let data = [num(100), num(10), num(20), num(1), 23, NOMINAL_WIDTH_X as u8];
+        assert_eq!(Dict::parse_cff2(&data, &[1, 2]).unwrap().get(NOMINAL_WIDTH_X), Some(&[100.0, 10.0][..]));
+    }
+
+    #[test]
+    fn blend_with_too_few_operands_is_an_error() {
+        assert!(Dict::parse_cff2(&[num(100), num(1), 23], &[1]).is_err());
+
+        // 1E30 values, whose operand count overflows
+        assert!(Dict::parse_cff2(&[30, 0x1B, 0x30, 0xFF, 23], &[1]).is_err());
+    }
+
+    #[test]
+    fn blend_is_an_operator_only_in_cff2() {
+        let data = [num(100), 23];
+        assert!(Dict::parse(&data).unwrap().contains(23));
+    }
+}
@@ -0,0 +1,85 @@
+use std::{collections::HashMap, io::{Read, Seek}};
+
+use log::warn;
+
+use crate::file::{deserialize_from, error::{FontError, Result}};
+
+use super::{charset::Charset, strings::STANDARD_ENCODING};
+
+/// Maps character codes to glyphs. Only relevant for fonts used without a cmap table,
+/// but needed to resolve the accented characters built by the `endchar` operator.
+#[derive(Debug, Clone)]
+pub enum Encoding {
+    Standard,
+    Expert,
+    Custom {
+        /// Maps codes directly to glyph indices
+        codes: HashMap<u8, u16>,
+        /// Maps additional codes to SIDs
+        supplements: HashMap<u8, u16>
+    }
+}
+
+impl Encoding {
+    /// Loads a custom encoding located at the current stream position
+    pub fn load<S>(stream: &mut S) -> Result<Encoding>
+        where S: Read + Seek
+    {
+        let position = stream.stream_position()?;
+        let format: u8 = deserialize_from(stream)?;
+
+        let mut codes: HashMap<u8, u16> = HashMap::new();
+        match format & 0x7F {
+            0 => {
+                let num_codes: u8 = deserialize_from(stream)?;
+                for glyph_index in 1..=num_codes as u16 {
+                    codes.insert(deserialize_from(stream)?, glyph_index);
+                }
+            },
+            1 => {
+                let num_ranges: u8 = deserialize_from(stream)?;
+                let mut glyph_index: u16 = 1;
+
+                for _ in 0..num_ranges {
+                    let first: u8 = deserialize_from(stream)?;
+                    let num_left: u8 = deserialize_from(stream)?;
+
+                    for code in first as u16..=first as u16 + num_left as u16 {
+                        codes.insert(code as u8, glyph_index);
+                        glyph_index += 1;
+                    }
+                }
+            },
+            _ => return Err(FontError::FontFormatError(Some(position as u32), format!("Unknown encoding format {}", format)))
+        }
+
+        let mut supplements: HashMap<u8, u16> = HashMap::new();
+        if format & 0x80 != 0 {
+            let num_supplements: u8 = deserialize_from(stream)?;
+            for _ in 0..num_supplements {
+                let code: u8 = deserialize_from(stream)?;
+                supplements.insert(code, deserialize_from(stream)?);
+            }
+        }
+
+        Ok(Encoding::Custom {
+            codes,
+            supplements
+        })
+    }
+
+    pub fn glyph_index(&self, code: u8, charset: &Charset) -> Option<u16> {
+        match self {
+            Encoding::Standard => match STANDARD_ENCODING[code as usize] {
+                0 => None,
+                sid => charset.glyph_index(sid)
+            },
+            Encoding::Expert => {
+                warn!("The predefined expert encoding is not supported");
+                None
+            },
+            Encoding::Custom { codes, supplements } => codes.get(&code).copied()
+                .or_else(|| supplements.get(&code).and_then(|&sid| charset.glyph_index(sid)))
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::io::{Read, Seek};
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::{FontError, Result}};
+
+/// Assigns every glyph of a CID-keyed font to one of its Font DICTs
+#[derive(Debug, Clone)]
+pub enum FdSelect {
+    /// One Font DICT index per glyph
+    Array(Vec<u8>),
+
+    /// Ranges of glyphs, given as (first glyph, Font DICT index), followed by a sentinel glyph
+    Ranges(Vec<(u32, u16)>, u32)
+}
+
+impl FdSelect {
+    /// Loads the FDSelect structure located at the current stream position
+    pub fn load<S>(num_glyphs: u16, stream: &mut S) -> Result<FdSelect>
+        where S: Read + Seek
+    {
+        let position = stream.stream_position()?;
+        let format: u8 = deserialize_from(stream)?;
+
+        Ok(match format {
+            0 => FdSelect::Array(deserialize_vec_from(num_glyphs as usize, stream)?),
+            3 => {
+                let num_ranges: u16 = deserialize_from(stream)?;
+                let mut ranges: Vec<(u32, u16)> = Vec::with_capacity(num_ranges as usize);
+                for _ in 0..num_ranges {
+                    let first: u16 = deserialize_from(stream)?;
+                    let fd: u8 = deserialize_from(stream)?;
+                    ranges.push((first as u32, fd as u16));
+                }
+
+                let sentinel: u16 = deserialize_from(stream)?;
+                FdSelect::Ranges(ranges, sentinel as u32)
+            },
+            4 => {
+                let num_ranges: u32 = deserialize_from(stream)?;
+                let ranges: Vec<(u32, u16)> = deserialize_vec_from(num_ranges as usize, stream)?;
+                let sentinel: u32 = deserialize_from(stream)?;
+
+                FdSelect::Ranges(ranges, sentinel)
+            },
+            _ => return Err(FontError::FontFormatError(Some(position as u32), format!("Unknown FDSelect format {}", format)))
+        })
+    }
+
+    pub fn font_dict_index(&self, glyph_index: u16) -> Option<usize> {
+        match self {
+            FdSelect::Array(fds) => fds.get(glyph_index as usize).map(|&fd| fd as usize),
+            FdSelect::Ranges(ranges, sentinel) => {
+                if glyph_index as u32 >= *sentinel {
+                    return None;
+                }
+
+                // Index of the last range that starts at or before the glyph
+                let range = ranges.partition_point(|&(first, _)| first <= glyph_index as u32).checked_sub(1)?;
+                Some(ranges[range].1 as usize)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::FdSelect;
+
+    fn load(num_glyphs: u16, data: &[u8]) -> FdSelect {
+        FdSelect::load(num_glyphs, &mut Cursor::new(data)).unwrap()
+    }
+
+    fn lookup(fd_select: &FdSelect, num_glyphs: u16) -> Vec<Option<usize>> {
+        (0..num_glyphs).map(|glyph_index| fd_select.font_dict_index(glyph_index)).collect()
+    }
+
+    #[test]
+    fn format_0() {
+        let fd_select = load(4, &[0, 0, 1, 1, 2]);
+        assert_eq!(lookup(&fd_select, 5), [Some(0), Some(1), Some(1), Some(2), None]);
+    }
+
+    #[test]
+    fn format_3() {
+        // Glyphs 1-2 use Font DICT 3, glyphs 3-4 Font DICT 1, glyph 0 and 5 no range
+        let fd_select = load(6, &[3, 0x00, 0x02, 0x00, 0x01, 0x03, 0x00, 0x03, 0x01, 0x00, 0x05]);
+        assert_eq!(lookup(&fd_select, 6), [None, Some(3), Some(3), Some(1), Some(1), None]);
+    }
+
+    #[test]
+    fn format_4() {
+        let fd_select = load(4, &[
+            4, 0x00, 0x00, 0x00, 0x02,
+            0x00, 0x00, 0x00, 0x00, 0x01, 0x00,     // glyph 0: Font DICT 256
+            0x00, 0x00, 0x00, 0x02, 0x00, 0x02,     // glyph 2: Font DICT 2
+            0x00, 0x00, 0x00, 0x03
+        ]);
+        assert_eq!(lookup(&fd_select, 4), [Some(256), Some(256), Some(2), None]);
+    }
+
+    #[test]
+    fn unknown_formats_are_errors() {
+        assert!(FdSelect::load(1, &mut Cursor::new(&[1, 0])).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::file::{deserialize_from, error::{FontError, Result}};
+
+/// An INDEX structure, i.e. an array of variable-sized objects
+#[derive(Debug, Clone, Default)]
+pub struct Index {
+    offsets: Vec<u32>,
+    data: Vec<u8>
+}
+
+fn read_offset<S>(offset_size: u8, stream: &mut S) -> Result<u32>
+    where S: Read + Seek
+{
+    let mut bytes = [0u8; 4];
+    stream.read_exact(&mut bytes[4 - offset_size as usize..])?;
+
+    Ok(u32::from_be_bytes(bytes))
+}
+
+/// Returns the number of bytes between the stream position and the end of the stream
+fn remaining_length<S>(stream: &mut S) -> Result<u64>
+    where S: Read + Seek
+{
+    let position = stream.stream_position()?;
+    let length = stream.seek(SeekFrom::End(0))?;
+    stream.seek(SeekFrom::Start(position))?;
+
+    Ok(length.saturating_sub(position))
+}
+
+impl Index {
+    /// Reads an INDEX whose count is stored in `count_size` bytes (2 for CFF, 4 for CFF2).
+    /// The stream is left positioned at the first byte after the INDEX, and is expected to
+    /// end with the table.
+    pub fn load<S>(stream: &mut S, count_size: u8) -> Result<Index>
+        where S: Read + Seek
+    {
+        let start = stream.stream_position()?;
+        let count = read_offset(count_size, stream)?;
+        if count == 0 {
+            return Ok(Index::default());
+        }
+
+        let offset_size: u8 = deserialize_from(stream)?;
+        if !(1..=4).contains(&offset_size) {
+            return Err(FontError::FontFormatError(Some(start as u32), format!("Invalid INDEX offset size {}", offset_size)));
+        }
+
+        // The count comes from the file, so it's checked against the data left in the table
+        // before anything is allocated for it
+        if (count as u64 + 1) * offset_size as u64 > remaining_length(stream)? {
+            return Err(FontError::FontFormatError(Some(start as u32), format!("INDEX with {} objects doesn't fit into the table", count)));
+        }
+
+        let mut offsets: Vec<u32> = Vec::with_capacity(count as usize + 1);
+        for _ in 0..=count {
+            offsets.push(read_offset(offset_size, stream)?);
+        }
+
+        // Offsets are 1-based, relative to the byte preceding the object data
+        let (first, last) = (offsets[0], offsets[count as usize]);
+        if first != 1 || offsets.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(FontError::FontFormatError(Some(start as u32), "INDEX offsets are not increasing".into()));
+        }
+
+        if (last - first) as u64 > remaining_length(stream)? {
+            return Err(FontError::FontFormatError(Some(start as u32), format!("INDEX data of {} bytes doesn't fit into the table", last - first)));
+        }
+
+        let mut data = vec![0u8; (last - first) as usize];
+        stream.read_exact(&mut data)?;
+
+        Ok(Index {
+            offsets,
+            data
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        let start = *self.offsets.get(index)? as usize - 1;
+        let end = *self.offsets.get(index + 1)? as usize - 1;
+
+        self.data.get(start..end)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        (0..self.len()).filter_map(|index| self.get(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::Index;
+
+    #[test]
+    fn objects_are_read() {
+        let data = [0, 2, 1, 1, 3, 4, b'a', b'b', b'c'];
+        let index = Index::load(&mut Cursor::new(&data), 2).unwrap();
+
+        assert_eq!(index.iter().collect::<Vec<_>>(), [&b"ab"[..], &b"c"[..]]);
+    }
+
+    #[test]
+    fn count_past_the_end_of_the_table_is_an_error() {
+        let data = [0xFF, 0xFF, 0xFF, 0xFF, 4, 0, 0, 0, 1];
+        assert!(Index::load(&mut Cursor::new(&data), 4).is_err());
+    }
+
+    #[test]
+    fn data_past_the_end_of_the_table_is_an_error() {
+        let data = [0, 0, 0, 1, 4, 0, 0, 0, 1, 0xFF, 0xFF, 0xFF, 0xFF];
+        assert!(Index::load(&mut Cursor::new(&data), 4).is_err());
+    }
+}
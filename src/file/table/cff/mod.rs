@@ -0,0 +1,378 @@
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use log::{debug, warn};
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, error::{FontError, Result}, loader::TableDirectoryEntry, table::Table};
+
+mod charset;
+pub use charset::Charset;
+
+mod charstring;
+pub use charstring::{CharString, PathCommand, Seac};
+
+mod dict;
+pub use dict::Dict;
+
+mod encoding;
+pub use encoding::Encoding;
+
+mod fdselect;
+pub use fdselect::FdSelect;
+
+mod cff2;
+pub use cff2::{Cff2Header, CompactFontFormat2};
+
+mod index;
+pub use index::Index;
+
+mod strings;
+pub use strings::{STANDARD_ENCODING, STANDARD_STRINGS};
+
+use charstring::CharStringContext;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct CffHeader {
+    pub major_version:  u8,
+    pub minor_version:  u8,
+    pub header_size:    u8,
+    pub offset_size:    u8
+}
+
+/// A Private DICT together with the local subroutines it points to
+#[derive(Debug, Clone, Default)]
+pub struct PrivateData {
+    pub dict: Dict,
+    pub subrs: Index,
+    pub default_width_x: f32,
+    pub nominal_width_x: f32,
+    pub vsindex: usize
+}
+
+impl PrivateData {
+    /// Loads the Private DICT located at `offset` into `data`, and its local subroutines.
+    /// `region_counts` must be given for CFF2 tables (see `Dict::parse_cff2`).
+    fn load(data: &[u8], size: usize, offset: usize, region_counts: Option<&[usize]>) -> Result<PrivateData> {
+        // Both values are DICT operands, so their sum may overflow
+        let dict_data = offset.checked_add(size).and_then(|end| data.get(offset..end)).ok_or_else(|| {
+            FontError::FontFormatError(Some(offset as u32), "Private DICT lies outside of the CFF table".into())
+        })?;
+
+        let (dict, count_size) = match region_counts {
+            Some(region_counts) => (Dict::parse_cff2(dict_data, region_counts)?, 4),
+            None => (Dict::parse(dict_data)?, 2)
+        };
+        debug!("Private DICT: {:?}", dict);
+
+        // The subroutine offset is relative to the start of the Private DICT
+        let subrs = match dict.get_number(dict::SUBRS) {
+            Some(subrs_offset) => {
+                let mut cursor = Cursor::new(data);
+                cursor.seek(SeekFrom::Start((offset as f64 + subrs_offset) as u64))?;
+                Index::load(&mut cursor, count_size)?
+            },
+            None => Index::default()
+        };
+
+        Ok(PrivateData {
+            default_width_x: dict.get_number(dict::DEFAULT_WIDTH_X).unwrap_or(0.0) as f32,
+            nominal_width_x: dict.get_number(dict::NOMINAL_WIDTH_X).unwrap_or(0.0) as f32,
+            vsindex: dict.get_number(dict::VSINDEX).unwrap_or(0.0) as usize,
+            dict,
+            subrs
+        })
+    }
+
+    /// Picks the Private DICT of the Font DICT a glyph belongs to
+    fn select<'a>(font_dicts: &'a [PrivateData], fd_select: Option<&FdSelect>, glyph_index: u16) -> Result<&'a PrivateData> {
+        let font_dict = match fd_select {
+            Some(fd_select) => fd_select.font_dict_index(glyph_index),
+            None => Some(0)
+        };
+
+        font_dict.and_then(|index| font_dicts.get(index)).ok_or_else(|| {
+            FontError::FontFormatError(None, format!("Glyph {} is not assigned to a valid Font DICT", glyph_index))
+        })
+    }
+
+    /// Loads the Font DICT INDEX located at `offset` into `data`, and the Private DICT of every font in it
+    fn load_font_dicts(data: &[u8], offset: u64, region_counts: Option<&[usize]>) -> Result<Vec<PrivateData>> {
+        let mut cursor = Cursor::new(data);
+        cursor.seek(SeekFrom::Start(offset))?;
+
+        let count_size = if region_counts.is_some() { 4 } else { 2 };
+        let font_dicts = Index::load(&mut cursor, count_size)?;
+
+        font_dicts.iter()
+            .map(|font_dict| {
+                let font_dict = Dict::parse(font_dict)?;
+                match font_dict.get_pair(dict::PRIVATE) {
+                    Some((size, offset)) => PrivateData::load(data, size as usize, offset as usize, region_counts),
+                    None => Ok(PrivateData::default())
+                }
+            })
+            .collect()
+    }
+}
+
+/// The Compact Font Format table, containing PostScript outlines
+#[derive(Debug, Clone)]
+pub struct CompactFontFormat {
+    pub header: CffHeader,
+    pub name: String,
+    pub top_dict: Dict,
+    pub strings: Index,
+    pub global_subrs: Index,
+    pub char_strings: Index,
+    pub charset: Charset,
+    pub encoding: Encoding,
+
+    /// The Private DICTs of all Font DICTs. Fonts that are not CID-keyed only have one
+    pub font_dicts: Vec<PrivateData>,
+    pub fd_select: Option<FdSelect>
+}
+
+impl Table for CompactFontFormat {
+    type UserArgsType = ();
+
+    fn get_table_name() -> &'static str {
+        "Compact Font Format"
+    }
+
+    fn load_impl<S>(entry: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
+        where S: Read + Seek
+    {
+        let mut data = vec![0u8; entry.length as usize];
+        stream.read_exact(&mut data)?;
+
+        let mut cursor = Cursor::new(data.as_slice());
+        let header: CffHeader = deserialize_from(&mut cursor)?;
+        debug!("{:?}", header);
+
+        if header.major_version != 1 {
+            return Err(FontError::FontFormatError(
+                Some(entry.offset),
+                format!("Unsupported CFF version {}.{}", header.major_version, header.minor_version)
+            ));
+        }
+
+        cursor.seek(SeekFrom::Start(header.header_size as u64))?;
+
+        let names = Index::load(&mut cursor, 2)?;
+        let top_dicts = Index::load(&mut cursor, 2)?;
+        let strings = Index::load(&mut cursor, 2)?;
+        let global_subrs = Index::load(&mut cursor, 2)?;
+
+        if names.len() != 1 {
+            warn!("CFF table contains {} fonts, only the first one will be used", names.len());
+        }
+
+        let name = String::from_utf8_lossy(names.get(0).unwrap_or_default()).into_owned();
+        let top_dict = Dict::parse(top_dicts.get(0).ok_or_else(|| {
+            FontError::FontFormatError(Some(entry.offset), "CFF table does not contain a Top DICT".into())
+        })?)?;
+        debug!("Top DICT of '{}': {:?}", name, top_dict);
+
+        let charstring_type = top_dict.get_number(dict::CHARSTRING_TYPE).unwrap_or(2.0);
+        if charstring_type != 2.0 {
+            return Err(FontError::FontFormatError(
+                Some(entry.offset),
+                format!("Unsupported charstring type {}", charstring_type)
+            ));
+        }
+
+        let Some(char_strings_offset) = top_dict.get_number(dict::CHAR_STRINGS) else {
+            return Err(FontError::FontFormatError(Some(entry.offset), "CFF font has no CharStrings".into()));
+        };
+        cursor.seek(SeekFrom::Start(char_strings_offset as u64))?;
+        let char_strings = Index::load(&mut cursor, 2)?;
+        let num_glyphs = char_strings.len() as u16;
+
+        let charset = match top_dict.get_number(dict::CHARSET).unwrap_or(0.0) as u64 {
+            0 => Charset::IsoAdobe,
+            1 => Charset::Expert,
+            2 => Charset::ExpertSubset,
+            offset => {
+                cursor.seek(SeekFrom::Start(offset))?;
+                Charset::load(num_glyphs, &mut cursor)?
+            }
+        };
+
+        let encoding = match top_dict.get_number(dict::ENCODING).unwrap_or(0.0) as u64 {
+            0 => Encoding::Standard,
+            1 => Encoding::Expert,
+            offset => {
+                cursor.seek(SeekFrom::Start(offset))?;
+                Encoding::load(&mut cursor)?
+            }
+        };
+
+        let (font_dicts, fd_select) = if top_dict.contains(dict::ROS) {
+            debug!("'{}' is a CID-keyed font", name);
+
+            let (Some(fd_array_offset), Some(fd_select_offset)) = (top_dict.get_number(dict::FD_ARRAY), top_dict.get_number(dict::FD_SELECT)) else {
+                return Err(FontError::FontFormatError(Some(entry.offset), "CID-keyed font is missing FDArray or FDSelect".into()));
+            };
+
+            cursor.seek(SeekFrom::Start(fd_select_offset as u64))?;
+            let fd_select = FdSelect::load(num_glyphs, &mut cursor)?;
+
+            (PrivateData::load_font_dicts(&data, fd_array_offset as u64, None)?, Some(fd_select))
+        } else {
+            let private = match top_dict.get_pair(dict::PRIVATE) {
+                Some((size, offset)) => PrivateData::load(&data, size as usize, offset as usize, None)?,
+                None => PrivateData::default()
+            };
+
+            (vec![private], None)
+        };
+
+        Ok(CompactFontFormat {
+            header,
+            name,
+            top_dict,
+            strings,
+            global_subrs,
+            char_strings,
+            charset,
+            encoding,
+            font_dicts,
+            fd_select
+        })
+    }
+}
+
+impl CompactFontFormat {
+    pub fn num_glyphs(&self) -> u16 {
+        self.char_strings.len() as u16
+    }
+
+    pub fn is_cid_keyed(&self) -> bool {
+        self.top_dict.contains(dict::ROS)
+    }
+
+    /// Returns the CID of a glyph in a CID-keyed font
+    pub fn cid(&self, glyph_index: u16) -> Option<u16> {
+        self.is_cid_keyed().then(|| self.charset.sid(glyph_index)).flatten()
+    }
+
+    /// Looks up a string by its SID
+    pub fn string(&self, sid: u16) -> Option<&str> {
+        match STANDARD_STRINGS.get(sid as usize) {
+            Some(&string) => Some(string),
+            None => std::str::from_utf8(self.strings.get(sid as usize - STANDARD_STRINGS.len())?).ok()
+        }
+    }
+
+    fn top_dict_string(&self, operator: u16) -> Option<&str> {
+        self.string(self.top_dict.get_number(operator)? as u16)
+    }
+
+    pub fn notice(&self) -> Option<&str> {
+        self.top_dict_string(dict::NOTICE)
+    }
+
+    pub fn copyright(&self) -> Option<&str> {
+        self.top_dict_string(dict::COPYRIGHT)
+    }
+
+    pub fn full_name(&self) -> Option<&str> {
+        self.top_dict_string(dict::FULL_NAME)
+    }
+
+    pub fn family_name(&self) -> Option<&str> {
+        self.top_dict_string(dict::FAMILY_NAME)
+    }
+
+    pub fn font_bbox(&self) -> [f64; 4] {
+        match self.top_dict.get(dict::FONT_BBOX) {
+            Some(&[left, bottom, right, top]) => [left, bottom, right, top],
+            _ => [0.0; 4]
+        }
+    }
+
+    /// Returns the matrix that maps charstring coordinates to text space
+    pub fn font_matrix(&self) -> [f64; 6] {
+        match self.top_dict.get(dict::FONT_MATRIX) {
+            Some(&[a, b, c, d, e, f]) => [a, b, c, d, e, f],
+            _ => [0.001, 0.0, 0.0, 0.001, 0.0, 0.0]
+        }
+    }
+
+    /// Returns the name of a glyph. CID-keyed fonts don't have glyph names
+    pub fn glyph_name(&self, glyph_index: u16) -> Option<&str> {
+        if self.is_cid_keyed() {
+            return None;
+        }
+
+        self.string(self.charset.sid(glyph_index)?)
+    }
+
+    /// Returns the Private DICT that applies to a glyph
+    pub fn private_data(&self, glyph_index: u16) -> Result<&PrivateData> {
+        PrivateData::select(&self.font_dicts, self.fd_select.as_ref(), glyph_index)
+    }
+
+    /// Interprets the charstring of a glyph. Accented characters built with `endchar`
+    /// are merged into a single outline.
+    pub fn char_string(&self, glyph_index: u16) -> Result<CharString> {
+        let mut result = self.interpret(glyph_index)?;
+
+        if let Some(seac) = result.seac {
+            let component = |code: u8| self.charset.glyph_index(STANDARD_ENCODING[code as usize]).ok_or_else(|| {
+                FontError::FontFormatError(None, format!("Glyph {} refers to character code {}, which the font doesn't contain", glyph_index, code))
+            });
+
+            let base = self.interpret(component(seac.base_code)?)?;
+            let accent = self.interpret(component(seac.accent_code)?)?;
+
+            result.commands = base.commands;
+            result.commands.extend(accent.commands.into_iter().map(|command| match command {
+                PathCommand::MoveTo { x, y } => PathCommand::MoveTo { x: x + seac.adx, y: y + seac.ady },
+                PathCommand::LineTo { x, y } => PathCommand::LineTo { x: x + seac.adx, y: y + seac.ady },
+                PathCommand::CurveTo { x1, y1, x2, y2, x, y } => PathCommand::CurveTo {
+                    x1: x1 + seac.adx, y1: y1 + seac.ady,
+                    x2: x2 + seac.adx, y2: y2 + seac.ady,
+                    x: x + seac.adx, y: y + seac.ady
+                },
+                PathCommand::ClosePath => PathCommand::ClosePath
+            }));
+        }
+
+        Ok(result)
+    }
+
+    fn interpret(&self, glyph_index: u16) -> Result<CharString> {
+        let data = self.char_strings.get(glyph_index as usize).ok_or_else(|| {
+            FontError::FontFormatError(
+                None,
+                format!("Glyph index {} is out of range (font has {} glyphs)", glyph_index, self.num_glyphs())
+            )
+        })?;
+
+        let private = self.private_data(glyph_index)?;
+        let context = CharStringContext {
+            global_subrs: &self.global_subrs,
+            local_subrs: &private.subrs,
+            default_width_x: private.default_width_x,
+            nominal_width_x: private.nominal_width_x,
+            variations: None
+        };
+
+        charstring::interpret(data, &context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrivateData;
+
+    #[test]
+    fn private_dict_bounds_do_not_overflow() {
+        let data = [0u8; 16];
+
+        assert!(PrivateData::load(&data, usize::MAX, 8, None).is_err());
+        assert!(PrivateData::load(&data, 8, usize::MAX, None).is_err());
+        assert!(PrivateData::load(&data, 0, 16, None).is_ok());
+    }
+}
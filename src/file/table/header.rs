@@ -33,7 +33,7 @@ impl Table for FontHeader {
         "Font Header"
     }
 
-    fn load_impl<S>(dict_entry: TableDirectoryEntry, stream: &mut S, user_data: Self::UserArgsType) -> Result<Self>
+    fn load_impl<S>(_: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
         where S: Read + Seek
     {
         let header: FontHeader = file::deserialize_from(stream)?;
@@ -9,30 +9,30 @@ use super::table::Table;
 
 #[derive(Debug, Deserialize)]
 pub struct MinSideBearing {
-    left: i16,
-    right: i16
+    pub left: i16,
+    pub right: i16
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Caret {
-    rise: i16,
-    run: i16,
-    offset: i16
+    pub rise: i16,
+    pub run: i16,
+    pub offset: i16
 }
 
 #[derive(Debug, Deserialize)]
 pub struct HorizontalHeader {
-    version:                (u16, u16),
-    ascender:               i16,
-    descender:              i16,
-    line_gap:               i16,
-    advance_width_max:      u16,
-    min_side_bearing:       MinSideBearing,
-    x_max_extent:           i16,
-    caret:                  Caret,
-    _reserved:              (i16, i16, i16, i16),
-    metric_data_format:     i16,
-    number_of_h_metrics:    u16
+    pub version:                (u16, u16),
+    pub ascender:               i16,
+    pub descender:              i16,
+    pub line_gap:               i16,
+    pub advance_width_max:      u16,
+    pub min_side_bearing:       MinSideBearing,
+    pub x_max_extent:           i16,
+    pub caret:                  Caret,
+    pub _reserved:              (i16, i16, i16, i16),
+    pub metric_data_format:     i16,
+    pub number_of_h_metrics:    u16
 }
 
 impl Table for HorizontalHeader {
@@ -42,7 +42,7 @@ impl Table for HorizontalHeader {
         "Horizontal Header"
     }
 
-    fn load_impl<S>(entry: TableDirectoryEntry, stream: &mut S, user_data: Self::UserArgsType) -> Result<Self>
+    fn load_impl<S>(_: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
             where S: Read + Seek
     {
         let hheader: Self = deserialize_from(stream)?;
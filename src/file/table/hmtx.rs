@@ -0,0 +1,147 @@
+use std::io::{Read, Seek};
+
+use log::{debug, warn};
+use serde::Deserialize;
+
+use crate::file::{deserialize_vec_from, error::{FontError, Result}, loader::TableDirectoryEntry};
+
+use super::table::Table;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct LongHorMetric {
+    pub advance_width:      u16,
+    pub left_side_bearing:  i16
+}
+
+#[derive(Debug, Clone)]
+pub struct HorizontalMetrics {
+    /// Metrics of the first `number_of_h_metrics` glyphs
+    pub h_metrics: Vec<LongHorMetric>,
+
+    /// Left side bearings of the remaining glyphs, which share the last advance width
+    pub left_side_bearings: Vec<i16>
+}
+
+impl HorizontalMetrics {
+    pub fn num_glyphs(&self) -> usize {
+        self.h_metrics.len() + self.left_side_bearings.len()
+    }
+
+    /// Returns the advance width and left side bearing of a glyph
+    pub fn metrics(&self, glyph_index: u16) -> Option<LongHorMetric> {
+        let glyph_index = glyph_index as usize;
+        if let Some(&metric) = self.h_metrics.get(glyph_index) {
+            return Some(metric);
+        }
+
+        let left_side_bearing = *self.left_side_bearings.get(glyph_index - self.h_metrics.len())?;
+        Some(LongHorMetric {
+            advance_width: self.h_metrics.last()?.advance_width,
+            left_side_bearing
+        })
+    }
+
+    pub fn advance_width(&self, glyph_index: u16) -> Option<u16> {
+        self.metrics(glyph_index).map(|metric| metric.advance_width)
+    }
+
+    pub fn left_side_bearing(&self, glyph_index: u16) -> Option<i16> {
+        self.metrics(glyph_index).map(|metric| metric.left_side_bearing)
+    }
+}
+
+impl Table for HorizontalMetrics {
+    /// (number_of_h_metrics from hhea, number of glyphs from maxp)
+    type UserArgsType = (u16, u16);
+
+    fn get_table_name() -> &'static str {
+        "Horizontal Metrics"
+    }
+
+    fn load_impl<S>(entry: TableDirectoryEntry, stream: &mut S, (number_of_h_metrics, num_glyphs): Self::UserArgsType) -> Result<Self>
+        where S: Read + Seek
+    {
+        if number_of_h_metrics == 0 {
+            return Err(FontError::FontFormatError(Some(entry.offset), "Horizontal metrics table has no long metrics".into()));
+        }
+
+        let num_metrics = if number_of_h_metrics > num_glyphs {
+            warn!("Horizontal header lists {} metrics, but the font only has {} glyphs", number_of_h_metrics, num_glyphs);
+            num_glyphs.max(1) as usize
+        } else {
+            number_of_h_metrics as usize
+        };
+
+        let metrics_size = num_metrics * 4;
+        if metrics_size > entry.length as usize {
+            return Err(FontError::FontFormatError(
+                Some(entry.offset),
+                format!("Horizontal metrics table is too short for {} metrics ({} bytes)", num_metrics, entry.length)
+            ));
+        }
+
+        let mut num_bearings = (num_glyphs as usize).saturating_sub(num_metrics);
+        let available = (entry.length as usize - metrics_size) / 2;
+        if num_bearings > available {
+            warn!("Horizontal metrics table is missing {} left side bearings", num_bearings - available);
+            num_bearings = available;
+        }
+
+        let table = HorizontalMetrics {
+            h_metrics: deserialize_vec_from(num_metrics, stream)?,
+            left_side_bearings: deserialize_vec_from(num_bearings, stream)?
+        };
+        debug!("loaded metrics for {} glyphs", table.num_glyphs());
+
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::file::{error::Result, loader::TableDirectoryEntry, table::Table};
+
+    use super::HorizontalMetrics;
+
+    fn load(values: &[i16], number_of_metrics: u16, num_glyphs: u16) -> Result<HorizontalMetrics> {
+        let data: Vec<u8> = values.iter().flat_map(|value| value.to_be_bytes()).collect();
+        let entry = TableDirectoryEntry { tag: 0x686D7478, checksum: 0, offset: 0, length: data.len() as u32 };
+
+        HorizontalMetrics::load(entry, &mut Cursor::new(data), (number_of_metrics, num_glyphs))
+    }
+
+    #[test]
+    fn trailing_bearings_share_the_last_advance() {
+        let metrics = load(&[500, 10, 600, 20, 30, -40], 2, 4).unwrap();
+
+        assert_eq!(metrics.num_glyphs(), 4);
+        assert_eq!(metrics.advance_width(0), Some(500));
+        assert_eq!(metrics.left_side_bearing(1), Some(20));
+        assert_eq!([2, 3].map(|glyph| metrics.advance_width(glyph)), [Some(600); 2]);
+        assert_eq!([2, 3].map(|glyph| metrics.left_side_bearing(glyph)), [Some(30), Some(-40)]);
+        assert!(metrics.metrics(4).is_none());
+    }
+
+    #[test]
+    fn missing_bearings_are_dropped() {
+        let metrics = load(&[500, 10, 30], 1, 5).unwrap();
+
+        assert_eq!(metrics.num_glyphs(), 2);
+        assert_eq!(metrics.left_side_bearing(1), Some(30));
+        assert!(metrics.metrics(2).is_none());
+    }
+
+    #[test]
+    fn metrics_count_is_limited_to_the_glyph_count() {
+        let metrics = load(&[500, 10, 600, 20], 2, 1).unwrap();
+        assert_eq!(metrics.num_glyphs(), 1);
+    }
+
+    #[test]
+    fn invalid_metrics_counts_are_errors() {
+        assert!(load(&[500, 10], 0, 1).is_err());
+        assert!(load(&[500, 10], 2, 2).is_err());
+    }
+}
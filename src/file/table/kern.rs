@@ -0,0 +1,407 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use log::{debug, warn};
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::{FontError, Result}, loader::TableDirectoryEntry};
+
+use super::table::Table;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct MicrosoftSubtableHeader {
+    _version:       u16,
+    length:         u16,
+    coverage:       u16
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct AppleSubtableHeader {
+    length:         u32,
+    coverage:       u16,
+    tuple_index:    u16
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct PairsHeader {
+    num_pairs:      u16,
+    _search_range:  u16,
+    _entry_selector: u16,
+    _range_shift:   u16
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct ClassArrayHeader {
+    _row_width:         u16,
+    left_class_offset:  u16,
+    right_class_offset: u16,
+    array_offset:       u16
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct KerningPair {
+    pub left:   u16,
+    pub right:  u16,
+    pub value:  i16
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KerningCoverage {
+    /// Set for horizontal text, unset for vertical text
+    pub horizontal: bool,
+    /// The values are minimum values rather than kerning values
+    pub minimum: bool,
+    /// The values move glyphs perpendicular to the text direction
+    pub cross_stream: bool,
+    /// The values replace the accumulated value instead of being added to it
+    pub override_value: bool
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassTable {
+    pub first_glyph: u16,
+    pub values: Vec<u16>
+}
+
+impl ClassTable {
+    fn load<S>(stream: &mut S) -> Result<ClassTable>
+        where S: Read + Seek
+    {
+        let first_glyph: u16 = deserialize_from(stream)?;
+        let num_glyphs: u16 = deserialize_from(stream)?;
+
+        Ok(ClassTable {
+            first_glyph,
+            values: deserialize_vec_from(num_glyphs as usize, stream)?
+        })
+    }
+
+    fn get(&self, glyph_index: u16) -> u16 {
+        glyph_index.checked_sub(self.first_glyph)
+            .and_then(|index| self.values.get(index as usize))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum KerningData {
+    /// Format 0, pairs sorted by left and right glyph
+    Pairs(Vec<KerningPair>),
+
+    /// Format 2, a two-dimensional array indexed by glyph classes. The class values are
+    /// byte offsets that, added together, point into `subtable`.
+    ClassArray {
+        left_classes: ClassTable,
+        right_classes: ClassTable,
+        array_offset: u16,
+        subtable: Vec<u8>
+    },
+
+    /// Subtable formats that aren't supported
+    Unsupported(u8)
+}
+
+#[derive(Debug, Clone)]
+pub struct KerningSubtable {
+    pub coverage: KerningCoverage,
+    pub data: KerningData
+}
+
+impl KerningSubtable {
+    /// Returns the value this subtable stores for a pair of glyphs
+    pub fn kerning(&self, left: u16, right: u16) -> Option<i16> {
+        match &self.data {
+            KerningData::Pairs(pairs) => pairs
+                .binary_search_by_key(&(left, right), |pair| (pair.left, pair.right))
+                .ok()
+                .map(|index| pairs[index].value),
+            KerningData::ClassArray { left_classes, right_classes, array_offset, subtable } => {
+                let offset = left_classes.get(left) as usize + right_classes.get(right) as usize;
+                if offset < *array_offset as usize {
+                    return None;
+                }
+
+                let bytes = subtable.get(offset..offset + 2)?;
+                Some(i16::from_be_bytes([bytes[0], bytes[1]]))
+            },
+            KerningData::Unsupported(_) => None
+        }
+    }
+
+    fn load_data<S>(format: u8, start: u64, length: u64, stream: &mut S) -> Result<KerningData>
+        where S: Read + Seek
+    {
+        Ok(match format {
+            0 => {
+                let header: PairsHeader = deserialize_from(stream)?;
+                let mut pairs: Vec<KerningPair> = deserialize_vec_from(header.num_pairs as usize, stream)?;
+
+                if !pairs.is_sorted_by_key(|pair| (pair.left, pair.right)) {
+                    warn!("Kerning pairs are not sorted");
+                    pairs.sort_by_key(|pair| (pair.left, pair.right));
+                }
+
+                KerningData::Pairs(pairs)
+            },
+            2 => {
+                let header: ClassArrayHeader = deserialize_from(stream)?;
+
+                stream.seek(SeekFrom::Start(start + header.left_class_offset as u64))?;
+                let left_classes = ClassTable::load(stream)?;
+                stream.seek(SeekFrom::Start(start + header.right_class_offset as u64))?;
+                let right_classes = ClassTable::load(stream)?;
+
+                stream.seek(SeekFrom::Start(start))?;
+                KerningData::ClassArray {
+                    left_classes,
+                    right_classes,
+                    array_offset: header.array_offset,
+                    subtable: deserialize_vec_from(length as usize, stream)?
+                }
+            },
+            _ => {
+                warn!("Kerning subtable format {} is not supported", format);
+                KerningData::Unsupported(format)
+            }
+        })
+    }
+}
+
+/// The legacy kerning table, in either its Microsoft or Apple flavour
+#[derive(Debug, Clone)]
+pub struct KerningTable {
+    pub subtables: Vec<KerningSubtable>
+}
+
+impl KerningTable {
+    fn accumulate(&self, left: u16, right: u16, cross_stream: bool) -> i16 {
+        self.subtables.iter()
+            .filter(|subtable| subtable.coverage.horizontal && !subtable.coverage.minimum)
+            .filter(|subtable| subtable.coverage.cross_stream == cross_stream)
+            .fold(0, |total, subtable| match subtable.kerning(left, right) {
+                Some(value) if subtable.coverage.override_value => value,
+                Some(value) => total.saturating_add(value),
+                None => total
+            })
+    }
+
+    /// Returns the horizontal kerning between two glyphs in font units
+    pub fn kerning(&self, left: u16, right: u16) -> i16 {
+        self.accumulate(left, right, false)
+    }
+
+    /// Returns the vertical shift of the right glyph in horizontal text
+    pub fn cross_stream_kerning(&self, left: u16, right: u16) -> i16 {
+        self.accumulate(left, right, true)
+    }
+
+    fn load_microsoft<S>(entry: &TableDirectoryEntry, stream: &mut S) -> Result<Vec<KerningSubtable>>
+        where S: Read + Seek
+    {
+        let num_tables: u16 = deserialize_from(stream)?;
+        let mut subtables = Vec::with_capacity(num_tables as usize);
+
+        for _ in 0..num_tables {
+            let start = stream.stream_position()?;
+            let header: MicrosoftSubtableHeader = deserialize_from(stream)?;
+
+            let coverage = KerningCoverage {
+                horizontal: header.coverage & 0x0001 != 0,
+                minimum: header.coverage & 0x0002 != 0,
+                cross_stream: header.coverage & 0x0004 != 0,
+                override_value: header.coverage & 0x0008 != 0
+            };
+
+            let table_end = entry.offset as u64 + entry.length as u64;
+            let data = KerningSubtable::load_data((header.coverage >> 8) as u8, start, (header.length as u64).min(table_end.saturating_sub(start)), stream)?;
+
+            // Large format 0 subtables overflow the 16 bit length, so the end of the pairs is used instead
+            let end = match data {
+                KerningData::Pairs(ref pairs) => start + 14 + pairs.len() as u64 * 6,
+                _ => start + header.length as u64
+            };
+            stream.seek(SeekFrom::Start(end))?;
+
+            subtables.push(KerningSubtable {
+                coverage,
+                data
+            });
+        }
+
+        Ok(subtables)
+    }
+
+    fn load_apple<S>(stream: &mut S) -> Result<Vec<KerningSubtable>>
+        where S: Read + Seek
+    {
+        let num_tables: u32 = deserialize_from(stream)?;
+        let mut subtables = Vec::with_capacity(num_tables as usize);
+
+        for _ in 0..num_tables {
+            let start = stream.stream_position()?;
+            let header: AppleSubtableHeader = deserialize_from(stream)?;
+
+            if header.coverage & 0x2000 != 0 {
+                debug!("skipping variation kerning subtable (tuple {})", header.tuple_index);
+                stream.seek(SeekFrom::Start(start + header.length as u64))?;
+                continue;
+            }
+
+            let coverage = KerningCoverage {
+                horizontal: header.coverage & 0x8000 == 0,
+                minimum: false,
+                cross_stream: header.coverage & 0x4000 != 0,
+                override_value: false
+            };
+
+            let data = KerningSubtable::load_data(header.coverage as u8, start, header.length as u64, stream)?;
+            stream.seek(SeekFrom::Start(start + header.length as u64))?;
+
+            subtables.push(KerningSubtable {
+                coverage,
+                data
+            });
+        }
+
+        Ok(subtables)
+    }
+}
+
+impl Table for KerningTable {
+    type UserArgsType = ();
+
+    fn get_table_name() -> &'static str {
+        "Kerning"
+    }
+
+    fn load_impl<S>(entry: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
+        where S: Read + Seek
+    {
+        // Microsoft tables start with a 16 bit version of 0, Apple tables with a 32 bit version of 1.0
+        let version: u16 = deserialize_from(stream)?;
+        let subtables = match version {
+            0 => KerningTable::load_microsoft(&entry, stream)?,
+            1 => {
+                let _: u16 = deserialize_from(stream)?;
+                KerningTable::load_apple(stream)?
+            },
+            _ => return Err(
+                FontError::FontFormatError(
+                    Some(entry.offset),
+                    format!("kerning table has unknown version: {}", version)
+                )
+            )
+        };
+
+        debug!("loaded {} kerning subtables", subtables.len());
+        Ok(KerningTable {
+            subtables
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::file::{error::Result, loader::TableDirectoryEntry, table::Table};
+
+    use super::{KerningData, KerningTable};
+
+    fn load(values: &[i32]) -> Result<KerningTable> {
+        let data: Vec<u8> = values.iter().flat_map(|&value| (value as u16).to_be_bytes()).collect();
+        let entry = TableDirectoryEntry { tag: 0x6B65726E, checksum: 0, offset: 0, length: data.len() as u32 };
+
+        KerningTable::load(entry, &mut Cursor::new(data), ())
+    }
+
+    #[test]
+    fn microsoft_format_0() {
+        let kern = load(&[
+            0, 4,
+            // unsorted pairs
+            0, 32, 0x0001, 3, 0, 0, 0, 5, 6, -50, 1, 2, -20, 1, 3, 30,
+            // cross-stream
+            0, 20, 0x0005, 1, 0, 0, 0, 1, 2, 15,
+            // override
+            0, 20, 0x0009, 1, 0, 0, 0, 5, 6, -80,
+            // minimum
+            0, 20, 0x0003, 1, 0, 0, 0, 1, 2, 100
+        ]).unwrap();
+
+        assert_eq!(kern.subtables.len(), 4);
+        assert!(matches!(&kern.subtables[0].data, KerningData::Pairs(pairs) if pairs[0].left == 1 && pairs[2].left == 5));
+        assert_eq!(kern.kerning(1, 2), -20);
+        assert_eq!(kern.kerning(1, 3), 30);
+        assert_eq!(kern.kerning(5, 6), -80);
+        assert_eq!(kern.kerning(2, 1), 0);
+        assert_eq!(kern.cross_stream_kerning(1, 2), 15);
+        assert_eq!(kern.cross_stream_kerning(1, 3), 0);
+    }
+
+    #[test]
+    fn format_0_length_overflowing_16_bits() {
+        // The length of large subtables wraps around, the next subtable follows the pairs
+        let kern = load(&[
+            0, 2,
+            0, 4, 0x0001, 1, 0, 0, 0, 1, 2, -20,
+            0, 20, 0x0001, 1, 0, 0, 0, 3, 4, -30
+        ]).unwrap();
+
+        assert_eq!(kern.kerning(1, 2), -20);
+        assert_eq!(kern.kerning(3, 4), -30);
+    }
+
+    #[test]
+    fn microsoft_format_2_class_offsets() {
+        let kern = load(&[
+            0, 1,
+            0, 38, 0x0201,
+            4, 14, 22, 30,
+            // left classes of glyphs 10 and 11 are row offsets
+            10, 2, 30, 34,
+            // right classes of glyphs 20 and 21 are column offsets
+            20, 2, 0, 2,
+            -10, -20, -30, -40
+        ]).unwrap();
+
+        assert_eq!(kern.kerning(10, 20), -10);
+        assert_eq!(kern.kerning(10, 21), -20);
+        assert_eq!(kern.kerning(11, 20), -30);
+        assert_eq!(kern.kerning(11, 21), -40);
+
+        // Glyphs without a left class point before the array
+        assert_eq!(kern.kerning(9, 21), 0);
+        assert_eq!(kern.subtables[0].kerning(9, 21), None);
+    }
+
+    #[test]
+    fn apple_header() {
+        let kern = load(&[
+            1, 0, 0, 4,
+            0, 22, 0x0000, 0, 1, 0, 0, 0, 1, 2, -25,
+            // variation subtables are skipped
+            0, 22, 0x2000, 0, 1, 0, 0, 0, 1, 2, -1000,
+            0, 22, 0x4000, 0, 1, 0, 0, 0, 1, 2, 12,
+            // vertical
+            0, 22, 0x8000, 0, 1, 0, 0, 0, 1, 2, 99
+        ]).unwrap();
+
+        assert_eq!(kern.subtables.len(), 3);
+        assert!(!kern.subtables[2].coverage.horizontal);
+        assert_eq!(kern.kerning(1, 2), -25);
+        assert_eq!(kern.cross_stream_kerning(1, 2), 12);
+    }
+
+    #[test]
+    fn unknown_version_is_an_error() {
+        assert!(load(&[2, 0]).is_err());
+    }
+
+    #[test]
+    fn unsupported_formats_are_kept() {
+        let kern = load(&[0, 1, 0, 6, 0x0101]).unwrap();
+        assert!(matches!(kern.subtables[0].data, KerningData::Unsupported(1)));
+        assert_eq!(kern.kerning(1, 2), 0);
+    }
+}
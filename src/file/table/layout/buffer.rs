@@ -0,0 +1,85 @@
+/// A glyph in a buffer that layout lookups are applied to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlyphInfo {
+    pub glyph_index: u16,
+
+    /// Index of the first byte of the text the glyph was created from. Glyphs merged
+    /// into a ligature take the smallest cluster of their components.
+    pub cluster: usize,
+
+    /// Identifies the ligature a glyph was formed into or whose components it was
+    /// skipped between, 0 if it isn't part of a ligature
+    pub ligature_id: u16,
+
+    /// For glyphs skipped between the components of a ligature, the number of the component
+    /// they follow, starting at 1. 0 for the ligature glyph itself.
+    pub ligature_component: u16
+}
+
+impl GlyphInfo {
+    pub fn new(glyph_index: u16, cluster: usize) -> GlyphInfo {
+        GlyphInfo {
+            glyph_index,
+            cluster,
+            ligature_id: 0,
+            ligature_component: 0
+        }
+    }
+}
+
+/// The placement of a glyph in font units, produced by positioning a glyph buffer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GlyphPosition {
+    pub x_advance: i32,
+    pub y_advance: i32,
+    pub x_offset: i32,
+    pub y_offset: i32
+}
+
+/// A glyph of shaped text with its cluster and placement in font units
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShapedGlyph {
+    pub glyph_index: u16,
+    pub cluster: usize,
+
+    pub x_advance: i32,
+    pub y_advance: i32,
+    pub x_offset: i32,
+    pub y_offset: i32
+}
+
+impl ShapedGlyph {
+    pub fn new(glyph: GlyphInfo, position: GlyphPosition) -> ShapedGlyph {
+        ShapedGlyph {
+            glyph_index: glyph.glyph_index,
+            cluster: glyph.cluster,
+
+            x_advance: position.x_advance,
+            y_advance: position.y_advance,
+            x_offset: position.x_offset,
+            y_offset: position.y_offset
+        }
+    }
+}
+
+/// The direction text is laid out in. Buffers are always kept in logical order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Direction {
+    #[default]
+    LeftToRight,
+    RightToLeft
+}
+
+/// Returns the index of the next glyph after `position` that isn't skipped
+pub fn next_position<F>(buffer: &[GlyphInfo], position: usize, skips: F) -> Option<usize>
+    where F: Fn(u16) -> bool
+{
+    (position + 1..buffer.len()).find(|&index| !skips(buffer[index].glyph_index))
+}
+
+/// Returns the index of the previous glyph before `position` that isn't skipped
+pub fn previous_position<F>(buffer: &[GlyphInfo], position: usize, skips: F) -> Option<usize>
+    where F: Fn(u16) -> bool
+{
+    (0..position).rev().find(|&index| !skips(buffer[index].glyph_index))
+}
@@ -0,0 +1,533 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use log::{debug, warn};
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::{FontError, Result}, loader::TableDirectoryEntry};
+
+use super::{GlyphClass, GlyphDefinitionTable};
+
+/// Seeks to `base + offset` and loads a subtable there. Null offsets yield `None`.
+pub fn load_at<S, T, F>(stream: &mut S, base: u64, offset: u32, load: F) -> Result<Option<T>>
+    where S: Read + Seek,
+          F: FnOnce(&mut S) -> Result<T>
+{
+    if offset == 0 {
+        return Ok(None);
+    }
+
+    stream.seek(SeekFrom::Start(base + offset as u64))?;
+    load(stream).map(Some)
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RangeRecord {
+    pub start_glyph: u16,
+    pub end_glyph: u16,
+    pub start_coverage_index: u16
+}
+
+/// A set of glyphs, each assigned an index in the order they are listed
+#[derive(Debug, Clone)]
+pub enum Coverage {
+    /// Format 1, a sorted list of glyphs
+    Glyphs(Vec<u16>),
+
+    /// Format 2, sorted ranges of consecutive glyphs
+    Ranges(Vec<RangeRecord>)
+}
+
+impl Coverage {
+    /// Loads a coverage table starting at the current stream position
+    pub fn load<S>(stream: &mut S) -> Result<Coverage>
+        where S: Read + Seek
+    {
+        let start = stream.stream_position()?;
+        let format: u16 = deserialize_from(stream)?;
+        let count: u16 = deserialize_from(stream)?;
+
+        Ok(match format {
+            1 => Coverage::Glyphs(deserialize_vec_from(count as usize, stream)?),
+            2 => Coverage::Ranges(deserialize_vec_from(count as usize, stream)?),
+            _ => return Err(FontError::FontFormatError(Some(start as u32), format!("Unknown coverage format {}", format)))
+        })
+    }
+
+    /// Loads a coverage table at `base + offset`, failing if the offset is null
+    pub fn load_required<S>(stream: &mut S, base: u64, offset: u16) -> Result<Coverage>
+        where S: Read + Seek
+    {
+        load_at(stream, base, offset as u32, Coverage::load)?
+            .ok_or_else(|| FontError::FontFormatError(Some(base as u32), "Missing coverage table".into()))
+    }
+
+    /// Returns the coverage index of a glyph, or `None` if the glyph isn't covered
+    pub fn index(&self, glyph_index: u16) -> Option<u16> {
+        match self {
+            Coverage::Glyphs(glyphs) => glyphs.binary_search(&glyph_index).ok().map(|index| index as u16),
+            Coverage::Ranges(ranges) => {
+                let range = ranges.get(ranges.partition_point(|range| range.end_glyph < glyph_index))?;
+                (range.start_glyph <= glyph_index).then(|| range.start_coverage_index + (glyph_index - range.start_glyph))
+            }
+        }
+    }
+
+    pub fn contains(&self, glyph_index: u16) -> bool {
+        self.index(glyph_index).is_some()
+    }
+
+    /// Iterates over all covered glyphs in coverage index order
+    pub fn glyphs(&self) -> Box<dyn Iterator<Item = u16> + '_> {
+        match self {
+            Coverage::Glyphs(glyphs) => Box::new(glyphs.iter().copied()),
+            Coverage::Ranges(ranges) => Box::new(ranges.iter().flat_map(|range| range.start_glyph..=range.end_glyph))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ClassRangeRecord {
+    pub start_glyph: u16,
+    pub end_glyph: u16,
+    pub class: u16
+}
+
+/// Assigns glyphs to classes. Glyphs that aren't listed belong to class 0.
+#[derive(Debug, Clone)]
+pub enum ClassDef {
+    /// Format 1, the classes of a consecutive run of glyphs
+    Array { start_glyph: u16, classes: Vec<u16> },
+
+    /// Format 2, sorted ranges of glyphs sharing a class
+    Ranges(Vec<ClassRangeRecord>)
+}
+
+impl ClassDef {
+    /// Loads a class definition table starting at the current stream position
+    pub fn load<S>(stream: &mut S) -> Result<ClassDef>
+        where S: Read + Seek
+    {
+        let start = stream.stream_position()?;
+        let format: u16 = deserialize_from(stream)?;
+
+        Ok(match format {
+            1 => {
+                let start_glyph: u16 = deserialize_from(stream)?;
+                let count: u16 = deserialize_from(stream)?;
+
+                ClassDef::Array {
+                    start_glyph,
+                    classes: deserialize_vec_from(count as usize, stream)?
+                }
+            },
+            2 => {
+                let count: u16 = deserialize_from(stream)?;
+                ClassDef::Ranges(deserialize_vec_from(count as usize, stream)?)
+            },
+            _ => return Err(FontError::FontFormatError(Some(start as u32), format!("Unknown class definition format {}", format)))
+        })
+    }
+
+    pub fn class(&self, glyph_index: u16) -> u16 {
+        match self {
+            ClassDef::Array { start_glyph, classes } => glyph_index.checked_sub(*start_glyph)
+                .and_then(|index| classes.get(index as usize))
+                .copied()
+                .unwrap_or(0),
+            ClassDef::Ranges(ranges) => ranges.get(ranges.partition_point(|range| range.end_glyph < glyph_index))
+                .filter(|range| range.start_glyph <= glyph_index)
+                .map_or(0, |range| range.class)
+        }
+    }
+}
+
+/// Converts a tag like "liga" or "DEU" to its numeric form, padding short tags with spaces
+pub fn tag(name: &str) -> u32 {
+    name.bytes()
+        .chain(std::iter::repeat(b' '))
+        .take(4)
+        .fold(0u32, |tag, byte| (tag << 8) | byte as u32)
+}
+
+/// The features applied when shaping text unless they are explicitly disabled
+pub const DEFAULT_FEATURES: &[&str] = &[
+    "rvrn", "ccmp", "locl", "rlig", "liga", "clig", "calt", "kern", "mark", "mkmk", "curs"
+];
+
+/// Enables a feature. For alternate substitutions the value selects the alternate
+/// (starting at 1), every other lookup only checks that it isn't 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureSetting {
+    pub tag: u32,
+    pub value: u32
+}
+
+impl FeatureSetting {
+    pub fn new(name: &str, value: u32) -> FeatureSetting {
+        FeatureSetting {
+            tag: tag(name),
+            value
+        }
+    }
+}
+
+impl From<&str> for FeatureSetting {
+    fn from(name: &str) -> FeatureSetting {
+        FeatureSetting::new(name, 1)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LangSys {
+    /// Index of the feature that is always applied for this language system
+    pub required_feature_index: Option<u16>,
+    pub feature_indices: Vec<u16>
+}
+
+impl LangSys {
+    fn load<S>(stream: &mut S) -> Result<LangSys>
+        where S: Read + Seek
+    {
+        let _lookup_order_offset: u16 = deserialize_from(stream)?;
+        let required_feature_index: u16 = deserialize_from(stream)?;
+        let feature_index_count: u16 = deserialize_from(stream)?;
+
+        Ok(LangSys {
+            required_feature_index: (required_feature_index != 0xFFFF).then_some(required_feature_index),
+            feature_indices: deserialize_vec_from(feature_index_count as usize, stream)?
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    pub default_lang_sys: Option<LangSys>,
+    pub lang_sys_records: Vec<(u32, LangSys)>
+}
+
+impl Script {
+    fn load<S>(stream: &mut S) -> Result<Script>
+        where S: Read + Seek
+    {
+        let start = stream.stream_position()?;
+        let default_lang_sys_offset: u16 = deserialize_from(stream)?;
+        let count: u16 = deserialize_from(stream)?;
+        let records: Vec<(u32, u16)> = deserialize_vec_from(count as usize, stream)?;
+
+        let mut lang_sys_records = Vec::with_capacity(records.len());
+        for (tag, offset) in records {
+            if let Some(lang_sys) = load_at(stream, start, offset as u32, LangSys::load)? {
+                lang_sys_records.push((tag, lang_sys));
+            }
+        }
+
+        Ok(Script {
+            default_lang_sys: load_at(stream, start, default_lang_sys_offset as u32, LangSys::load)?,
+            lang_sys_records
+        })
+    }
+
+    pub fn lang_sys(&self, language: u32) -> Option<&LangSys> {
+        self.lang_sys_records.iter()
+            .find(|(tag, _)| *tag == language)
+            .map(|(_, lang_sys)| lang_sys)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Feature {
+    pub tag: u32,
+    pub lookup_indices: Vec<u16>
+}
+
+impl Feature {
+    fn load<S>(tag: u32, stream: &mut S) -> Result<Feature>
+        where S: Read + Seek
+    {
+        let _feature_params_offset: u16 = deserialize_from(stream)?;
+        let lookup_index_count: u16 = deserialize_from(stream)?;
+
+        Ok(Feature {
+            tag,
+            lookup_indices: deserialize_vec_from(lookup_index_count as usize, stream)?
+        })
+    }
+}
+
+pub const RIGHT_TO_LEFT:            u16 = 0x0001;
+pub const IGNORE_BASE_GLYPHS:       u16 = 0x0002;
+pub const IGNORE_LIGATURES:         u16 = 0x0004;
+pub const IGNORE_MARKS:             u16 = 0x0008;
+pub const USE_MARK_FILTERING_SET:   u16 = 0x0010;
+pub const MARK_ATTACHMENT_TYPE:     u16 = 0xFF00;
+
+/// The subtables of a single lookup type, implemented by GSUB and GPOS
+pub trait LookupSubtable: Sized {
+    /// The lookup type that wraps other subtables in a 32 bit offset
+    const EXTENSION_TYPE: u16;
+
+    /// Loads a subtable starting at the current stream position. Returns `None` for
+    /// unsupported lookup types.
+    fn load<S>(lookup_type: u16, stream: &mut S) -> Result<Option<Self>>
+        where S: Read + Seek;
+}
+
+#[derive(Debug, Clone)]
+pub struct Lookup<T> {
+    /// The lookup type, with extension lookups replaced by the type they wrap
+    pub lookup_type: u16,
+    pub lookup_flag: u16,
+    pub mark_filtering_set: Option<u16>,
+    pub subtables: Vec<T>
+}
+
+impl<T> Lookup<T>
+    where T: LookupSubtable
+{
+    fn load<S>(stream: &mut S) -> Result<Lookup<T>>
+        where S: Read + Seek
+    {
+        let start = stream.stream_position()?;
+        let mut lookup_type: u16 = deserialize_from(stream)?;
+        let lookup_flag: u16 = deserialize_from(stream)?;
+        let subtable_count: u16 = deserialize_from(stream)?;
+        let subtable_offsets: Vec<u16> = deserialize_vec_from(subtable_count as usize, stream)?;
+
+        let mark_filtering_set: Option<u16> = match lookup_flag & USE_MARK_FILTERING_SET != 0 {
+            true => Some(deserialize_from(stream)?),
+            false => None
+        };
+
+        let mut subtables = Vec::with_capacity(subtable_offsets.len());
+        for offset in subtable_offsets {
+            let subtable_start = start + offset as u64;
+            stream.seek(SeekFrom::Start(subtable_start))?;
+
+            let mut subtable_type = lookup_type;
+            if lookup_type == T::EXTENSION_TYPE {
+                let _format: u16 = deserialize_from(stream)?;
+                subtable_type = deserialize_from(stream)?;
+                let extension_offset: u32 = deserialize_from(stream)?;
+
+                stream.seek(SeekFrom::Start(subtable_start + extension_offset as u64))?;
+            }
+
+            match T::load(subtable_type, stream)? {
+                Some(subtable) => subtables.push(subtable),
+                None => warn!("Skipping subtable of unsupported lookup type {}", subtable_type)
+            }
+
+            if subtable_type != T::EXTENSION_TYPE {
+                lookup_type = subtable_type;
+            }
+        }
+
+        Ok(Lookup {
+            lookup_type,
+            lookup_flag,
+            mark_filtering_set,
+            subtables
+        })
+    }
+
+    /// Returns whether a glyph is ignored by this lookup because of its lookup flags
+    pub fn skips(&self, glyph_index: u16, gdef: Option<&GlyphDefinitionTable>) -> bool {
+        let Some(gdef) = gdef else {
+            return false;
+        };
+
+        match gdef.glyph_class(glyph_index) {
+            Some(GlyphClass::Base) => self.lookup_flag & IGNORE_BASE_GLYPHS != 0,
+            Some(GlyphClass::Ligature) => self.lookup_flag & IGNORE_LIGATURES != 0,
+            Some(GlyphClass::Mark) => {
+                let attachment_type = (self.lookup_flag & MARK_ATTACHMENT_TYPE) >> 8;
+
+                self.lookup_flag & IGNORE_MARKS != 0
+                    || self.mark_filtering_set.is_some_and(|set| !gdef.is_in_mark_glyph_set(set, glyph_index))
+                    || (attachment_type != 0 && gdef.mark_attach_class(glyph_index) != attachment_type)
+            },
+            _ => false
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct LayoutHeader {
+    major_version:          u16,
+    minor_version:          u16,
+    script_list_offset:     u16,
+    feature_list_offset:    u16,
+    lookup_list_offset:     u16
+}
+
+/// The structure shared by GSUB and GPOS: scripts select features, which select lookups
+#[derive(Debug, Clone)]
+pub struct LayoutTable<T> {
+    pub major_version: u16,
+    pub minor_version: u16,
+
+    pub scripts: Vec<(u32, Script)>,
+    pub features: Vec<Feature>,
+    pub lookups: Vec<Lookup<T>>
+}
+
+impl<T> LayoutTable<T>
+    where T: LookupSubtable
+{
+    pub fn load<S>(entry: &TableDirectoryEntry, stream: &mut S) -> Result<LayoutTable<T>>
+        where S: Read + Seek
+    {
+        let start = entry.offset as u64;
+        let header: LayoutHeader = deserialize_from(stream)?;
+
+        if header.major_version != 1 {
+            return Err(FontError::FontFormatError(
+                Some(entry.offset),
+                format!("layout table has unknown version: {}.{}", header.major_version, header.minor_version)
+            ));
+        }
+
+        let scripts = load_at(stream, start, header.script_list_offset as u32, |stream| {
+            let list_start = stream.stream_position()?;
+            let count: u16 = deserialize_from(stream)?;
+            let records: Vec<(u32, u16)> = deserialize_vec_from(count as usize, stream)?;
+
+            let mut scripts = Vec::with_capacity(records.len());
+            for (tag, offset) in records {
+                scripts.push((tag, load_at(stream, list_start, offset as u32, Script::load)?.unwrap_or_default()));
+            }
+
+            Ok(scripts)
+        })?.unwrap_or_default();
+
+        let features = load_at(stream, start, header.feature_list_offset as u32, |stream| {
+            let list_start = stream.stream_position()?;
+            let count: u16 = deserialize_from(stream)?;
+            let records: Vec<(u32, u16)> = deserialize_vec_from(count as usize, stream)?;
+
+            let mut features = Vec::with_capacity(records.len());
+            for (tag, offset) in records {
+                stream.seek(SeekFrom::Start(list_start + offset as u64))?;
+                features.push(Feature::load(tag, stream)?);
+            }
+
+            Ok(features)
+        })?.unwrap_or_default();
+
+        let lookups = load_at(stream, start, header.lookup_list_offset as u32, |stream| {
+            let list_start = stream.stream_position()?;
+            let count: u16 = deserialize_from(stream)?;
+            let offsets: Vec<u16> = deserialize_vec_from(count as usize, stream)?;
+
+            let mut lookups = Vec::with_capacity(offsets.len());
+            for offset in offsets {
+                stream.seek(SeekFrom::Start(list_start + offset as u64))?;
+                lookups.push(Lookup::load(stream)?);
+            }
+
+            Ok(lookups)
+        })?.unwrap_or_default();
+
+        debug!("loaded {} scripts, {} features and {} lookups", scripts.len(), features.len(), lookups.len());
+
+        Ok(LayoutTable {
+            major_version: header.major_version,
+            minor_version: header.minor_version,
+
+            scripts,
+            features,
+            lookups
+        })
+    }
+}
+
+impl<T> LayoutTable<T> {
+    /// Returns the script with the given tag, falling back to the default script
+    pub fn script(&self, script: u32) -> Option<&Script> {
+        [script, tag("DFLT"), tag("dflt"), tag("latn")].into_iter()
+            .find_map(|script| self.scripts.iter().find(|(tag, _)| *tag == script))
+            .map(|(_, script)| script)
+    }
+
+    /// Returns the language system for a script and language, falling back to the
+    /// script's default language system
+    pub fn lang_sys(&self, script: u32, language: Option<u32>) -> Option<&LangSys> {
+        let script = self.script(script)?;
+
+        language.and_then(|language| script.lang_sys(language))
+            .or(script.default_lang_sys.as_ref())
+    }
+
+    /// Returns the indices of the lookups to apply for a set of features, in the order
+    /// they have to be applied, along with the value of the feature enabling them
+    pub fn lookup_indices(&self, script: u32, language: Option<u32>, features: &[FeatureSetting]) -> Vec<(u16, u32)> {
+        let Some(lang_sys) = self.lang_sys(script, language) else {
+            return vec![];
+        };
+
+        let required = lang_sys.required_feature_index.map(|index| (index, 1));
+        let selected = lang_sys.feature_indices.iter().filter_map(|&index| {
+            let feature = self.features.get(index as usize)?;
+            let setting = features.iter().rev().find(|setting| setting.tag == feature.tag)?;
+
+            (setting.value != 0).then_some((index, setting.value))
+        });
+
+        let mut lookups: Vec<(u16, u32)> = required.into_iter().chain(selected)
+            .filter_map(|(index, value)| self.features.get(index as usize).map(|feature| (feature, value)))
+            .flat_map(|(feature, value)| feature.lookup_indices.iter().map(move |&lookup| (lookup, value)))
+            .filter(|&(lookup, _)| (lookup as usize) < self.lookups.len())
+            .collect();
+
+        lookups.sort_by_key(|&(lookup, _)| lookup);
+        lookups.dedup_by_key(|&mut (lookup, _)| lookup);
+        lookups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{ClassDef, Coverage};
+
+    fn be(values: &[u16]) -> Vec<u8> {
+        values.iter().flat_map(|value| value.to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn coverage_format_1() {
+        let coverage = Coverage::load(&mut Cursor::new(be(&[1, 3, 4, 8, 20]))).unwrap();
+
+        assert_eq!([4, 8, 20, 0, 5, 21].map(|glyph| coverage.index(glyph)), [Some(0), Some(1), Some(2), None, None, None]);
+        assert_eq!(coverage.glyphs().collect::<Vec<_>>(), [4, 8, 20]);
+    }
+
+    #[test]
+    fn coverage_format_2() {
+        // Glyphs 10-12 start at index 0, glyphs 20-21 at index 3
+        let coverage = Coverage::load(&mut Cursor::new(be(&[2, 2, 10, 12, 0, 20, 21, 3]))).unwrap();
+
+        assert_eq!([10, 12, 20, 21].map(|glyph| coverage.index(glyph)), [Some(0), Some(2), Some(3), Some(4)]);
+        assert_eq!([9, 13, 19, 22].map(|glyph| coverage.index(glyph)), [None; 4]);
+        assert_eq!(coverage.glyphs().collect::<Vec<_>>(), [10, 11, 12, 20, 21]);
+    }
+
+    #[test]
+    fn class_def_format_1() {
+        let class_def = ClassDef::load(&mut Cursor::new(be(&[1, 5, 3, 1, 0, 2]))).unwrap();
+        assert_eq!([4, 5, 6, 7, 8].map(|glyph| class_def.class(glyph)), [0, 1, 0, 2, 0]);
+    }
+
+    #[test]
+    fn class_def_format_2() {
+        let class_def = ClassDef::load(&mut Cursor::new(be(&[2, 2, 3, 4, 1, 10, 10, 3]))).unwrap();
+        assert_eq!([2, 3, 4, 5, 9, 10, 11].map(|glyph| class_def.class(glyph)), [0, 1, 1, 0, 0, 3, 0]);
+    }
+
+    #[test]
+    fn unknown_formats_are_errors() {
+        assert!(Coverage::load(&mut Cursor::new(be(&[3, 0]))).is_err());
+        assert!(ClassDef::load(&mut Cursor::new(be(&[3, 0]))).is_err());
+    }
+}
@@ -0,0 +1,312 @@
+use std::io::{Read, Seek};
+
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::{FontError, Result}};
+
+use super::{buffer::{next_position, previous_position}, load_at, ClassDef, Coverage, GlyphInfo};
+
+/// Applies a lookup at one glyph of a matched context
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct SequenceLookupRecord {
+    /// Index into the matched input sequence
+    pub sequence_index: u16,
+    pub lookup_list_index: u16
+}
+
+/// A rule of a glyph or class based context. The values are glyph indices or classes,
+/// depending on the format, and the backtrack sequence is stored closest glyph first.
+#[derive(Debug, Clone, Default)]
+pub struct ContextRule {
+    pub backtrack: Vec<u16>,
+    /// The input sequence without its first glyph, which is matched by the coverage table
+    pub input: Vec<u16>,
+    pub lookahead: Vec<u16>,
+    pub lookups: Vec<SequenceLookupRecord>
+}
+
+impl ContextRule {
+    fn load<S>(stream: &mut S, chained: bool) -> Result<ContextRule>
+        where S: Read + Seek
+    {
+        let read_sequence = |stream: &mut S| -> Result<Vec<u16>> {
+            let count: u16 = deserialize_from(stream)?;
+            Ok(deserialize_vec_from(count as usize, stream)?)
+        };
+
+        let backtrack = match chained {
+            true => read_sequence(stream)?,
+            false => vec![]
+        };
+
+        let input_count: u16 = deserialize_from(stream)?;
+        let (input, lookahead, lookup_count) = match chained {
+            true => {
+                let input = deserialize_vec_from(input_count.saturating_sub(1) as usize, stream)?;
+                let lookahead = read_sequence(stream)?;
+                (input, lookahead, deserialize_from::<u16, _>(stream)?)
+            },
+            false => {
+                // Non-chained rules store the lookup count before the input sequence
+                let lookup_count: u16 = deserialize_from(stream)?;
+                (deserialize_vec_from(input_count.saturating_sub(1) as usize, stream)?, vec![], lookup_count)
+            }
+        };
+
+        Ok(ContextRule {
+            backtrack,
+            input,
+            lookahead,
+            lookups: deserialize_vec_from(lookup_count as usize, stream)?
+        })
+    }
+}
+
+/// Sequence context (GSUB type 5, GPOS type 7) and chained sequence context (GSUB type 6,
+/// GPOS type 8) subtables. Non-chained contexts have no backtrack or lookahead sequences.
+#[derive(Debug, Clone)]
+pub enum SequenceContext {
+    /// Format 1, rules made of glyph indices, grouped by the first input glyph
+    Glyphs {
+        coverage: Coverage,
+        rule_sets: Vec<Vec<ContextRule>>
+    },
+
+    /// Format 2, rules made of glyph classes, grouped by the class of the first input glyph
+    Classes {
+        coverage: Coverage,
+        backtrack_classes: ClassDef,
+        input_classes: ClassDef,
+        lookahead_classes: ClassDef,
+        rule_sets: Vec<Vec<ContextRule>>
+    },
+
+    /// Format 3, a single rule with a coverage table for every glyph
+    Coverages {
+        backtrack: Vec<Coverage>,
+        input: Vec<Coverage>,
+        lookahead: Vec<Coverage>,
+        lookups: Vec<SequenceLookupRecord>
+    }
+}
+
+fn load_rule_sets<S>(stream: &mut S, start: u64, chained: bool) -> Result<Vec<Vec<ContextRule>>>
+    where S: Read + Seek
+{
+    let count: u16 = deserialize_from(stream)?;
+    let set_offsets: Vec<u16> = deserialize_vec_from(count as usize, stream)?;
+
+    let mut rule_sets = Vec::with_capacity(set_offsets.len());
+    for set_offset in set_offsets {
+        let rules = load_at(stream, start, set_offset as u32, |stream| {
+            let set_start = stream.stream_position()?;
+            let rule_count: u16 = deserialize_from(stream)?;
+            let rule_offsets: Vec<u16> = deserialize_vec_from(rule_count as usize, stream)?;
+
+            let mut rules = Vec::with_capacity(rule_offsets.len());
+            for rule_offset in rule_offsets {
+                if let Some(rule) = load_at(stream, set_start, rule_offset as u32, |stream| ContextRule::load(stream, chained))? {
+                    rules.push(rule);
+                }
+            }
+
+            Ok(rules)
+        })?;
+
+        rule_sets.push(rules.unwrap_or_default());
+    }
+
+    Ok(rule_sets)
+}
+
+fn load_coverages<S>(stream: &mut S, start: u64, count: u16) -> Result<Vec<Coverage>>
+    where S: Read + Seek
+{
+    let offsets: Vec<u16> = deserialize_vec_from(count as usize, stream)?;
+    let position = stream.stream_position()?;
+
+    let coverages = offsets.into_iter()
+        .map(|offset| Coverage::load_required(stream, start, offset))
+        .collect::<Result<Vec<Coverage>>>()?;
+
+    stream.seek(std::io::SeekFrom::Start(position))?;
+    Ok(coverages)
+}
+
+fn load_class_def<S>(stream: &mut S, start: u64) -> Result<ClassDef>
+    where S: Read + Seek
+{
+    let offset: u16 = deserialize_from(stream)?;
+    let position = stream.stream_position()?;
+
+    // A missing class definition puts every glyph into class 0
+    let class_def = load_at(stream, start, offset as u32, ClassDef::load)?
+        .unwrap_or(ClassDef::Ranges(vec![]));
+
+    stream.seek(std::io::SeekFrom::Start(position))?;
+    Ok(class_def)
+}
+
+impl SequenceContext {
+    /// Loads a (chained) sequence context subtable starting at the current stream position
+    pub fn load<S>(stream: &mut S, chained: bool) -> Result<SequenceContext>
+        where S: Read + Seek
+    {
+        let start = stream.stream_position()?;
+        let format: u16 = deserialize_from(stream)?;
+
+        Ok(match format {
+            1 => {
+                let coverage_offset: u16 = deserialize_from(stream)?;
+                let rule_sets = load_rule_sets(stream, start, chained)?;
+
+                SequenceContext::Glyphs {
+                    coverage: Coverage::load_required(stream, start, coverage_offset)?,
+                    rule_sets
+                }
+            },
+            2 => {
+                let coverage_offset: u16 = deserialize_from(stream)?;
+                let (backtrack_classes, input_classes, lookahead_classes) = match chained {
+                    true => (load_class_def(stream, start)?, load_class_def(stream, start)?, load_class_def(stream, start)?),
+                    false => {
+                        let input_classes = load_class_def(stream, start)?;
+                        (input_classes.clone(), input_classes.clone(), input_classes)
+                    }
+                };
+                let rule_sets = load_rule_sets(stream, start, chained)?;
+
+                SequenceContext::Classes {
+                    coverage: Coverage::load_required(stream, start, coverage_offset)?,
+                    backtrack_classes,
+                    input_classes,
+                    lookahead_classes,
+                    rule_sets
+                }
+            },
+            3 if chained => {
+                let backtrack_count: u16 = deserialize_from(stream)?;
+                let backtrack = load_coverages(stream, start, backtrack_count)?;
+                let input_count: u16 = deserialize_from(stream)?;
+                let input = load_coverages(stream, start, input_count)?;
+                let lookahead_count: u16 = deserialize_from(stream)?;
+                let lookahead = load_coverages(stream, start, lookahead_count)?;
+                let lookup_count: u16 = deserialize_from(stream)?;
+
+                SequenceContext::Coverages {
+                    backtrack,
+                    input,
+                    lookahead,
+                    lookups: deserialize_vec_from(lookup_count as usize, stream)?
+                }
+            },
+            3 => {
+                let input_count: u16 = deserialize_from(stream)?;
+                let lookup_count: u16 = deserialize_from(stream)?;
+                let input = load_coverages(stream, start, input_count)?;
+
+                SequenceContext::Coverages {
+                    backtrack: vec![],
+                    input,
+                    lookahead: vec![],
+                    lookups: deserialize_vec_from(lookup_count as usize, stream)?
+                }
+            },
+            _ => return Err(FontError::FontFormatError(Some(start as u32), format!("Unknown sequence context format {}", format)))
+        })
+    }
+
+    /// Tries to match the context with its first input glyph at `position`. Returns the
+    /// positions of the input glyphs and the lookups to apply to them.
+    pub fn match_at<F>(&self, buffer: &[GlyphInfo], position: usize, skips: F) -> Option<(Vec<usize>, &[SequenceLookupRecord])>
+        where F: Fn(u16) -> bool
+    {
+        let glyph_index = buffer[position].glyph_index;
+
+        match self {
+            SequenceContext::Glyphs { coverage, rule_sets } => {
+                let rules = rule_sets.get(coverage.index(glyph_index)? as usize)?;
+                let matches = |value: u16, glyph_index: u16| value == glyph_index;
+
+                rules.iter().find_map(|rule| match_rule(buffer, position, rule, &skips, matches, matches, matches))
+            },
+            SequenceContext::Classes { coverage, backtrack_classes, input_classes, lookahead_classes, rule_sets } => {
+                coverage.index(glyph_index)?;
+                let rules = rule_sets.get(input_classes.class(glyph_index) as usize)?;
+
+                rules.iter().find_map(|rule| match_rule(
+                    buffer, position, rule, &skips,
+                    |class, glyph_index| backtrack_classes.class(glyph_index) == class,
+                    |class, glyph_index| input_classes.class(glyph_index) == class,
+                    |class, glyph_index| lookahead_classes.class(glyph_index) == class
+                ))
+            },
+            SequenceContext::Coverages { backtrack, input, lookahead, lookups } => {
+                let (first, rest) = input.split_first()?;
+                first.index(glyph_index)?;
+
+                let positions = match_sequence(buffer, position, rest, &skips, |coverage, glyph_index| coverage.contains(glyph_index))?;
+                let last = *positions.last()?;
+
+                match_backtrack(buffer, position, backtrack, &skips, |coverage, glyph_index| coverage.contains(glyph_index))?;
+                match_sequence(buffer, last, lookahead, &skips, |coverage, glyph_index| coverage.contains(glyph_index))?;
+
+                Some((positions, lookups.as_slice()))
+            }
+        }
+    }
+}
+
+fn match_rule<'a, F, B, I, L>(buffer: &[GlyphInfo], position: usize, rule: &'a ContextRule, skips: &F, backtrack: B, input: I, lookahead: L)
+    -> Option<(Vec<usize>, &'a [SequenceLookupRecord])>
+    where F: Fn(u16) -> bool,
+          B: Fn(u16, u16) -> bool,
+          I: Fn(u16, u16) -> bool,
+          L: Fn(u16, u16) -> bool
+{
+    let positions = match_sequence(buffer, position, &rule.input, skips, |&value, glyph_index| input(value, glyph_index))?;
+    let last = *positions.last()?;
+
+    match_backtrack(buffer, position, &rule.backtrack, skips, |&value, glyph_index| backtrack(value, glyph_index))?;
+    match_sequence(buffer, last, &rule.lookahead, skips, |&value, glyph_index| lookahead(value, glyph_index))?;
+
+    Some((positions, rule.lookups.as_slice()))
+}
+
+/// Matches `sequence` against the glyphs following `position`. Returns the positions of the
+/// matched glyphs, starting with `position` itself.
+pub fn match_sequence<T, F, M>(buffer: &[GlyphInfo], position: usize, sequence: &[T], skips: &F, matches: M) -> Option<Vec<usize>>
+    where F: Fn(u16) -> bool,
+          M: Fn(&T, u16) -> bool
+{
+    let mut positions = Vec::with_capacity(sequence.len() + 1);
+    positions.push(position);
+
+    let mut current = position;
+    for value in sequence {
+        current = next_position(buffer, current, skips)?;
+        if !matches(value, buffer[current].glyph_index) {
+            return None;
+        }
+
+        positions.push(current);
+    }
+
+    Some(positions)
+}
+
+/// Matches `sequence` against the glyphs preceding `position`, closest glyph first
+pub fn match_backtrack<T, F, M>(buffer: &[GlyphInfo], position: usize, sequence: &[T], skips: &F, matches: M) -> Option<()>
+    where F: Fn(u16) -> bool,
+          M: Fn(&T, u16) -> bool
+{
+    let mut current = position;
+    for value in sequence {
+        current = previous_position(buffer, current, skips)?;
+        if !matches(value, buffer[current].glyph_index) {
+            return None;
+        }
+    }
+
+    Some(())
+}
@@ -0,0 +1,309 @@
+use std::io::{Read, Seek};
+
+use log::debug;
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::{FontError, Result}, loader::TableDirectoryEntry, table::{ItemVariationStore, Table}};
+
+use super::{load_at, ClassDef, Coverage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphClass {
+    Base,
+    Ligature,
+    Mark,
+    Component
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct GdefHeader {
+    major_version:              u16,
+    minor_version:              u16,
+    glyph_class_def_offset:     u16,
+    attach_list_offset:         u16,
+    lig_caret_list_offset:      u16,
+    mark_attach_class_def_offset: u16
+}
+
+/// Contour points of glyphs that other glyphs attach to
+#[derive(Debug, Clone)]
+pub struct AttachList {
+    pub coverage: Coverage,
+    /// Point indices for every covered glyph, in coverage order
+    pub attach_points: Vec<Vec<u16>>
+}
+
+impl AttachList {
+    fn load<S>(stream: &mut S) -> Result<AttachList>
+        where S: Read + Seek
+    {
+        let start = stream.stream_position()?;
+        let coverage_offset: u16 = deserialize_from(stream)?;
+        let glyph_count: u16 = deserialize_from(stream)?;
+        let point_offsets: Vec<u16> = deserialize_vec_from(glyph_count as usize, stream)?;
+
+        let mut attach_points = Vec::with_capacity(point_offsets.len());
+        for offset in point_offsets {
+            let points = load_at(stream, start, offset as u32, |stream| {
+                let point_count: u16 = deserialize_from(stream)?;
+                Ok(deserialize_vec_from(point_count as usize, stream)?)
+            })?;
+
+            attach_points.push(points.unwrap_or_default());
+        }
+
+        Ok(AttachList {
+            coverage: Coverage::load_required(stream, start, coverage_offset)?,
+            attach_points
+        })
+    }
+}
+
+/// The position of a caret between two components of a ligature
+#[derive(Debug, Clone, Copy)]
+pub enum CaretValue {
+    /// Formats 1 and 3, an x or y coordinate in design units. The device table
+    /// of format 3 is ignored.
+    Coordinate(i16),
+
+    /// Format 2, the index of a contour point of the ligature glyph
+    ContourPoint(u16)
+}
+
+impl CaretValue {
+    fn load<S>(stream: &mut S) -> Result<CaretValue>
+        where S: Read + Seek
+    {
+        let start = stream.stream_position()?;
+        let format: u16 = deserialize_from(stream)?;
+
+        Ok(match format {
+            1 | 3 => CaretValue::Coordinate(deserialize_from(stream)?),
+            2 => CaretValue::ContourPoint(deserialize_from(stream)?),
+            _ => return Err(FontError::FontFormatError(Some(start as u32), format!("Unknown caret value format {}", format)))
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LigatureCaretList {
+    pub coverage: Coverage,
+    /// Carets for every covered ligature, in coverage order
+    pub carets: Vec<Vec<CaretValue>>
+}
+
+impl LigatureCaretList {
+    fn load<S>(stream: &mut S) -> Result<LigatureCaretList>
+        where S: Read + Seek
+    {
+        let start = stream.stream_position()?;
+        let coverage_offset: u16 = deserialize_from(stream)?;
+        let lig_glyph_count: u16 = deserialize_from(stream)?;
+        let lig_glyph_offsets: Vec<u16> = deserialize_vec_from(lig_glyph_count as usize, stream)?;
+
+        let mut carets = Vec::with_capacity(lig_glyph_offsets.len());
+        for offset in lig_glyph_offsets {
+            let lig_glyph = start + offset as u64;
+            let values = load_at(stream, start, offset as u32, |stream| {
+                let caret_count: u16 = deserialize_from(stream)?;
+                Ok(deserialize_vec_from::<u16, _>(caret_count as usize, stream)?)
+            })?.unwrap_or_default();
+
+            let mut glyph_carets = Vec::with_capacity(values.len());
+            for value_offset in values {
+                if let Some(caret) = load_at(stream, lig_glyph, value_offset as u32, CaretValue::load)? {
+                    glyph_carets.push(caret);
+                }
+            }
+
+            carets.push(glyph_carets);
+        }
+
+        Ok(LigatureCaretList {
+            coverage: Coverage::load_required(stream, start, coverage_offset)?,
+            carets
+        })
+    }
+}
+
+/// The glyph definition table
+#[derive(Debug, Clone)]
+pub struct GlyphDefinitionTable {
+    pub major_version: u16,
+    pub minor_version: u16,
+
+    pub glyph_class_def: Option<ClassDef>,
+    pub attach_list: Option<AttachList>,
+    pub lig_caret_list: Option<LigatureCaretList>,
+    pub mark_attach_class_def: Option<ClassDef>,
+
+    /// Version 1.2 and later
+    pub mark_glyph_sets: Vec<Coverage>,
+
+    /// Version 1.3 and later
+    pub item_variation_store: Option<ItemVariationStore>
+}
+
+impl GlyphDefinitionTable {
+    pub fn glyph_class(&self, glyph_index: u16) -> Option<GlyphClass> {
+        match self.glyph_class_def.as_ref()?.class(glyph_index) {
+            1 => Some(GlyphClass::Base),
+            2 => Some(GlyphClass::Ligature),
+            3 => Some(GlyphClass::Mark),
+            4 => Some(GlyphClass::Component),
+            _ => None
+        }
+    }
+
+    pub fn mark_attach_class(&self, glyph_index: u16) -> u16 {
+        self.mark_attach_class_def.as_ref().map_or(0, |class_def| class_def.class(glyph_index))
+    }
+
+    pub fn is_in_mark_glyph_set(&self, set: u16, glyph_index: u16) -> bool {
+        self.mark_glyph_sets.get(set as usize).is_some_and(|coverage| coverage.contains(glyph_index))
+    }
+
+    pub fn attach_points(&self, glyph_index: u16) -> Option<&[u16]> {
+        let list = self.attach_list.as_ref()?;
+        list.attach_points.get(list.coverage.index(glyph_index)? as usize).map(Vec::as_slice)
+    }
+
+    pub fn ligature_carets(&self, glyph_index: u16) -> Option<&[CaretValue]> {
+        let list = self.lig_caret_list.as_ref()?;
+        list.carets.get(list.coverage.index(glyph_index)? as usize).map(Vec::as_slice)
+    }
+}
+
+impl Table for GlyphDefinitionTable {
+    type UserArgsType = ();
+
+    fn get_table_name() -> &'static str {
+        "Glyph Definition"
+    }
+
+    fn load_impl<S>(entry: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
+        where S: Read + Seek
+    {
+        let start = entry.offset as u64;
+        let header: GdefHeader = deserialize_from(stream)?;
+
+        if header.major_version != 1 {
+            return Err(FontError::FontFormatError(
+                Some(entry.offset),
+                format!("glyph definition table has unknown version: {}.{}", header.major_version, header.minor_version)
+            ));
+        }
+
+        let mark_glyph_sets_offset: u16 = match header.minor_version >= 2 {
+            true => deserialize_from(stream)?,
+            false => 0
+        };
+        let item_var_store_offset: u32 = match header.minor_version >= 3 {
+            true => deserialize_from(stream)?,
+            false => 0
+        };
+
+        let mark_glyph_sets = load_at(stream, start, mark_glyph_sets_offset as u32, |stream| {
+            let sets_start = stream.stream_position()?;
+            let _format: u16 = deserialize_from(stream)?;
+            let count: u16 = deserialize_from(stream)?;
+            let offsets: Vec<u32> = deserialize_vec_from(count as usize, stream)?;
+
+            offsets.into_iter()
+                .map(|offset| load_at(stream, sets_start, offset, Coverage::load)?
+                    .ok_or_else(|| FontError::FontFormatError(Some(sets_start as u32), "Missing mark glyph set coverage".into())))
+                .collect::<Result<Vec<Coverage>>>()
+        })?;
+
+        let table = GlyphDefinitionTable {
+            major_version: header.major_version,
+            minor_version: header.minor_version,
+
+            glyph_class_def: load_at(stream, start, header.glyph_class_def_offset as u32, ClassDef::load)?,
+            attach_list: load_at(stream, start, header.attach_list_offset as u32, AttachList::load)?,
+            lig_caret_list: load_at(stream, start, header.lig_caret_list_offset as u32, LigatureCaretList::load)?,
+            mark_attach_class_def: load_at(stream, start, header.mark_attach_class_def_offset as u32, ClassDef::load)?,
+
+            mark_glyph_sets: mark_glyph_sets.unwrap_or_default(),
+            item_variation_store: load_at(stream, start, item_var_store_offset, ItemVariationStore::load)?
+        };
+        debug!("{:?}", table);
+
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::file::{loader::TableDirectoryEntry, table::Table};
+
+    use super::{CaretValue, GlyphClass, GlyphDefinitionTable};
+
+    fn load(values: &[u16]) -> Option<GlyphDefinitionTable> {
+        let data: Vec<u8> = values.iter().flat_map(|value| value.to_be_bytes()).collect();
+        let entry = TableDirectoryEntry { tag: 0x47444546, checksum: 0, offset: 0, length: data.len() as u32 };
+
+        GlyphDefinitionTable::load(entry, &mut Cursor::new(data), ()).ok()
+    }
+
+    #[test]
+    fn version_1_0() {
+        let gdef = load(&[
+            1, 0, 12, 28, 46, 84,
+            // glyph classes: 1-3 base, 10-11 mark
+            2, 2, 1, 3, 1, 10, 11, 3,
+            // attach list: points 3 and 7 of glyph 5
+            12, 1, 6, 2, 3, 7, 1, 1, 5,
+            // ligature carets of glyph 20: a coordinate, a contour point and a coordinate with a device table
+            28, 1, 6, 3, 8, 12, 16, 1, 300, 2, 4, 3, 500, 0, 2, 1, 20, 20, 0,
+            // mark attachment classes
+            1, 10, 2, 1, 2
+        ]).unwrap();
+
+        assert_eq!([1, 3, 4, 10].map(|glyph| gdef.glyph_class(glyph)), [Some(GlyphClass::Base), Some(GlyphClass::Base), None, Some(GlyphClass::Mark)]);
+        assert_eq!(gdef.attach_points(5), Some(&[3, 7][..]));
+        assert_eq!(gdef.attach_points(6), None);
+        assert!(matches!(
+            gdef.ligature_carets(20),
+            Some([CaretValue::Coordinate(300), CaretValue::ContourPoint(4), CaretValue::Coordinate(500)])
+        ));
+        assert_eq!([9, 10, 11].map(|glyph| gdef.mark_attach_class(glyph)), [0, 1, 2]);
+        assert!(gdef.mark_glyph_sets.is_empty() && gdef.item_variation_store.is_none());
+    }
+
+    #[test]
+    fn version_1_2_adds_mark_glyph_sets() {
+        let gdef = load(&[
+            1, 2, 0, 0, 0, 0, 14,
+            1, 2, 0, 12, 0, 20,
+            1, 2, 10, 11,
+            1, 1, 12
+        ]).unwrap();
+
+        assert!(gdef.is_in_mark_glyph_set(0, 11));
+        assert!(!gdef.is_in_mark_glyph_set(0, 12));
+        assert!(gdef.is_in_mark_glyph_set(1, 12));
+        assert!(!gdef.is_in_mark_glyph_set(2, 12));
+        assert!(gdef.glyph_class_def.is_none());
+    }
+
+    #[test]
+    fn version_1_3_adds_an_item_variation_store() {
+        let gdef = load(&[
+            1, 3, 0, 0, 0, 0, 0, 0, 18,
+            1, 0, 8, 0,
+            1, 1, 0, 0x4000, 0x4000
+        ]).unwrap();
+
+        let store = gdef.item_variation_store.unwrap();
+        assert_eq!(store.regions.len(), 1);
+        assert_eq!(store.regions[0].scalar(&[0.5]), 0.5);
+    }
+
+    #[test]
+    fn unknown_major_versions_are_errors() {
+        assert!(load(&[2, 0, 0, 0, 0, 0]).is_none());
+    }
+}
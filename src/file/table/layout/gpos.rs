@@ -0,0 +1,729 @@
+use std::io::{Read, Seek};
+
+use log::warn;
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::{FontError, Result}, loader::TableDirectoryEntry, table::Table};
+
+use super::{
+    buffer::{next_position, previous_position},
+    load_at, tag, Anchor, ClassDef, Coverage, Direction, FeatureSetting, GlyphClass, GlyphDefinitionTable, GlyphInfo, GlyphPosition,
+    LayoutTable, Lookup, LookupSubtable, SequenceContext, ValueRecord, RIGHT_TO_LEFT
+};
+
+/// How deep contextual lookups may nest before they are cut off
+const MAX_NESTING_DEPTH: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct PairValueRecord {
+    pub second_glyph: u16,
+    pub first_value: ValueRecord,
+    pub second_value: ValueRecord
+}
+
+#[derive(Debug, Clone)]
+pub struct MarkRecord {
+    pub class: u16,
+    pub anchor: Anchor
+}
+
+/// The subtables of GPOS lookups
+#[derive(Debug, Clone)]
+pub enum PositioningSubtable {
+    /// Type 1 format 1, the same adjustment for every covered glyph
+    Single {
+        coverage: Coverage,
+        value: ValueRecord
+    },
+
+    /// Type 1 format 2, an adjustment per covered glyph
+    SingleList {
+        coverage: Coverage,
+        values: Vec<ValueRecord>
+    },
+
+    /// Type 2 format 1, adjustments for specific pairs, grouped by the first glyph
+    PairGlyphs {
+        coverage: Coverage,
+        /// Pairs don't consume their second glyph if there are no values for it
+        second_value_format: u16,
+        pair_sets: Vec<Vec<PairValueRecord>>
+    },
+
+    /// Type 2 format 2, adjustments for pairs of glyph classes
+    PairClasses {
+        coverage: Coverage,
+        second_value_format: u16,
+        first_classes: ClassDef,
+        second_classes: ClassDef,
+        /// Indexed by the class of the first glyph, then by the class of the second one
+        class_values: Vec<Vec<(ValueRecord, ValueRecord)>>
+    },
+
+    /// Type 3, connects the exit anchor of a glyph to the entry anchor of the next one
+    Cursive {
+        coverage: Coverage,
+        /// Entry and exit anchors of every covered glyph
+        entry_exits: Vec<(Option<Anchor>, Option<Anchor>)>
+    },
+
+    /// Type 4
+    MarkToBase {
+        mark_coverage: Coverage,
+        base_coverage: Coverage,
+        marks: Vec<MarkRecord>,
+        /// Anchors of every base glyph, indexed by mark class
+        bases: Vec<Vec<Option<Anchor>>>
+    },
+
+    /// Type 5
+    MarkToLigature {
+        mark_coverage: Coverage,
+        ligature_coverage: Coverage,
+        marks: Vec<MarkRecord>,
+        /// Anchors of every ligature component, indexed by mark class
+        ligatures: Vec<Vec<Vec<Option<Anchor>>>>
+    },
+
+    /// Type 6
+    MarkToMark {
+        mark_coverage: Coverage,
+        base_mark_coverage: Coverage,
+        marks: Vec<MarkRecord>,
+        base_marks: Vec<Vec<Option<Anchor>>>
+    },
+
+    /// Type 7
+    Context(SequenceContext),
+
+    /// Type 8
+    ChainedContext(SequenceContext)
+}
+
+fn load_mark_array<S>(stream: &mut S) -> Result<Vec<MarkRecord>>
+    where S: Read + Seek
+{
+    let start = stream.stream_position()?;
+    let count: u16 = deserialize_from(stream)?;
+    let records: Vec<(u16, u16)> = deserialize_vec_from(count as usize, stream)?;
+
+    records.into_iter()
+        .map(|(class, offset)| Ok(MarkRecord {
+            class,
+            anchor: Anchor::load_at(stream, start, offset)?.unwrap_or_default()
+        }))
+        .collect()
+}
+
+/// Loads an array of anchor records, each holding one anchor per mark class
+fn load_anchor_matrix<S>(stream: &mut S, mark_class_count: u16) -> Result<Vec<Vec<Option<Anchor>>>>
+    where S: Read + Seek
+{
+    let start = stream.stream_position()?;
+    let count: u16 = deserialize_from(stream)?;
+
+    // Records are read one at a time, so memory only grows with the data that's really there
+    let mut records: Vec<Vec<u16>> = Vec::new();
+    for _ in 0..count {
+        records.push(deserialize_vec_from(mark_class_count as usize, stream)?);
+    }
+
+    records.into_iter()
+        .map(|record| record.into_iter()
+            .map(|offset| Anchor::load_at(stream, start, offset))
+            .collect::<Result<Vec<Option<Anchor>>>>())
+        .collect()
+}
+
+/// Loads the parts shared by all mark attachment subtables: the two coverage tables,
+/// the mark array and the offset to the array of the glyphs marks attach to
+fn load_mark_attachment<S>(stream: &mut S, start: u64) -> Result<(Coverage, Coverage, Vec<MarkRecord>, u16, u16)>
+    where S: Read + Seek
+{
+    let mark_coverage_offset: u16 = deserialize_from(stream)?;
+    let base_coverage_offset: u16 = deserialize_from(stream)?;
+    let mark_class_count: u16 = deserialize_from(stream)?;
+    let mark_array_offset: u16 = deserialize_from(stream)?;
+    let base_array_offset: u16 = deserialize_from(stream)?;
+
+    let marks = load_at(stream, start, mark_array_offset as u32, load_mark_array)?.unwrap_or_default();
+
+    Ok((
+        Coverage::load_required(stream, start, mark_coverage_offset)?,
+        Coverage::load_required(stream, start, base_coverage_offset)?,
+        marks,
+        mark_class_count,
+        base_array_offset
+    ))
+}
+
+impl LookupSubtable for PositioningSubtable {
+    const EXTENSION_TYPE: u16 = 9;
+
+    fn load<S>(lookup_type: u16, stream: &mut S) -> Result<Option<Self>>
+        where S: Read + Seek
+    {
+        let start = stream.stream_position()?;
+
+        if lookup_type == 7 || lookup_type == 8 {
+            let context = SequenceContext::load(stream, lookup_type == 8)?;
+
+            return Ok(Some(match lookup_type {
+                7 => PositioningSubtable::Context(context),
+                _ => PositioningSubtable::ChainedContext(context)
+            }));
+        }
+
+        let format: u16 = deserialize_from(stream)?;
+
+        let subtable = match (lookup_type, format) {
+            (1, 1) => {
+                let coverage_offset: u16 = deserialize_from(stream)?;
+                let value_format: u16 = deserialize_from(stream)?;
+
+                PositioningSubtable::Single {
+                    value: ValueRecord::load(stream, value_format, start)?,
+                    coverage: Coverage::load_required(stream, start, coverage_offset)?
+                }
+            },
+            (1, 2) => {
+                let coverage_offset: u16 = deserialize_from(stream)?;
+                let value_format: u16 = deserialize_from(stream)?;
+                let count: u16 = deserialize_from(stream)?;
+
+                PositioningSubtable::SingleList {
+                    values: (0..count).map(|_| ValueRecord::load(stream, value_format, start)).collect::<Result<_>>()?,
+                    coverage: Coverage::load_required(stream, start, coverage_offset)?
+                }
+            },
+            (2, 1) => {
+                let coverage_offset: u16 = deserialize_from(stream)?;
+                let first_value_format: u16 = deserialize_from(stream)?;
+                let second_value_format: u16 = deserialize_from(stream)?;
+                let count: u16 = deserialize_from(stream)?;
+                let set_offsets: Vec<u16> = deserialize_vec_from(count as usize, stream)?;
+
+                let mut pair_sets = Vec::with_capacity(set_offsets.len());
+                for offset in set_offsets {
+                    let pairs = load_at(stream, start, offset as u32, |stream| {
+                        let pair_count: u16 = deserialize_from(stream)?;
+
+                        (0..pair_count).map(|_| Ok(PairValueRecord {
+                            second_glyph: deserialize_from(stream)?,
+                            first_value: ValueRecord::load(stream, first_value_format, start)?,
+                            second_value: ValueRecord::load(stream, second_value_format, start)?
+                        })).collect::<Result<Vec<PairValueRecord>>>()
+                    })?;
+
+                    pair_sets.push(pairs.unwrap_or_default());
+                }
+
+                PositioningSubtable::PairGlyphs {
+                    coverage: Coverage::load_required(stream, start, coverage_offset)?,
+                    second_value_format,
+                    pair_sets
+                }
+            },
+            (2, 2) => {
+                let coverage_offset: u16 = deserialize_from(stream)?;
+                let first_value_format: u16 = deserialize_from(stream)?;
+                let second_value_format: u16 = deserialize_from(stream)?;
+                let first_classes_offset: u16 = deserialize_from(stream)?;
+                let second_classes_offset: u16 = deserialize_from(stream)?;
+                let first_class_count: u16 = deserialize_from(stream)?;
+                let second_class_count: u16 = deserialize_from(stream)?;
+
+                let class_values = (0..first_class_count)
+                    .map(|_| (0..second_class_count)
+                        .map(|_| Ok((
+                            ValueRecord::load(stream, first_value_format, start)?,
+                            ValueRecord::load(stream, second_value_format, start)?
+                        )))
+                        .collect::<Result<Vec<(ValueRecord, ValueRecord)>>>())
+                    .collect::<Result<_>>()?;
+
+                PositioningSubtable::PairClasses {
+                    coverage: Coverage::load_required(stream, start, coverage_offset)?,
+                    second_value_format,
+                    first_classes: load_at(stream, start, first_classes_offset as u32, ClassDef::load)?.unwrap_or(ClassDef::Ranges(vec![])),
+                    second_classes: load_at(stream, start, second_classes_offset as u32, ClassDef::load)?.unwrap_or(ClassDef::Ranges(vec![])),
+                    class_values
+                }
+            },
+            (3, 1) => {
+                let coverage_offset: u16 = deserialize_from(stream)?;
+                let count: u16 = deserialize_from(stream)?;
+                let records: Vec<(u16, u16)> = deserialize_vec_from(count as usize, stream)?;
+
+                PositioningSubtable::Cursive {
+                    entry_exits: records.into_iter()
+                        .map(|(entry, exit)| Ok((Anchor::load_at(stream, start, entry)?, Anchor::load_at(stream, start, exit)?)))
+                        .collect::<Result<_>>()?,
+                    coverage: Coverage::load_required(stream, start, coverage_offset)?
+                }
+            },
+            (4, 1) => {
+                let (mark_coverage, base_coverage, marks, mark_class_count, base_array_offset) = load_mark_attachment(stream, start)?;
+
+                PositioningSubtable::MarkToBase {
+                    mark_coverage,
+                    base_coverage,
+                    marks,
+                    bases: load_at(stream, start, base_array_offset as u32, |stream| load_anchor_matrix(stream, mark_class_count))?.unwrap_or_default()
+                }
+            },
+            (5, 1) => {
+                let (mark_coverage, ligature_coverage, marks, mark_class_count, ligature_array_offset) = load_mark_attachment(stream, start)?;
+
+                let ligatures = load_at(stream, start, ligature_array_offset as u32, |stream| {
+                    let array_start = stream.stream_position()?;
+                    let count: u16 = deserialize_from(stream)?;
+                    let offsets: Vec<u16> = deserialize_vec_from(count as usize, stream)?;
+
+                    offsets.into_iter()
+                        .map(|offset| Ok(load_at(stream, array_start, offset as u32, |stream| load_anchor_matrix(stream, mark_class_count))?.unwrap_or_default()))
+                        .collect::<Result<Vec<Vec<Vec<Option<Anchor>>>>>>()
+                })?.unwrap_or_default();
+
+                PositioningSubtable::MarkToLigature {
+                    mark_coverage,
+                    ligature_coverage,
+                    marks,
+                    ligatures
+                }
+            },
+            (6, 1) => {
+                let (mark_coverage, base_mark_coverage, marks, mark_class_count, base_mark_array_offset) = load_mark_attachment(stream, start)?;
+
+                PositioningSubtable::MarkToMark {
+                    mark_coverage,
+                    base_mark_coverage,
+                    marks,
+                    base_marks: load_at(stream, start, base_mark_array_offset as u32, |stream| load_anchor_matrix(stream, mark_class_count))?.unwrap_or_default()
+                }
+            },
+            (1..=9, _) => return Err(FontError::FontFormatError(
+                Some(start as u32),
+                format!("Unknown format {} for positioning lookup type {}", format, lookup_type)
+            )),
+            _ => return Ok(None)
+        };
+
+        Ok(Some(subtable))
+    }
+}
+
+/// The glyph positioning table
+pub type GlyphPositioningTable = LayoutTable<PositioningSubtable>;
+
+impl Table for GlyphPositioningTable {
+    type UserArgsType = ();
+
+    fn get_table_name() -> &'static str {
+        "Glyph Positioning"
+    }
+
+    fn load_impl<S>(dict_entry: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
+        where S: Read + Seek
+    {
+        LayoutTable::load(&dict_entry, stream)
+    }
+}
+
+/// A glyph whose offsets are relative to another glyph
+#[derive(Debug, Clone, Copy)]
+enum Attachment {
+    Mark(usize),
+    Cursive(usize)
+}
+
+struct PositioningState<'a> {
+    buffer: &'a [GlyphInfo],
+    positions: &'a mut [GlyphPosition],
+    attachments: Vec<Option<Attachment>>,
+    gdef: Option<&'a GlyphDefinitionTable>,
+    direction: Direction
+}
+
+impl PositioningState<'_> {
+    fn is_mark(&self, glyph_index: u16) -> bool {
+        self.gdef.is_some_and(|gdef| gdef.glyph_class(glyph_index) == Some(GlyphClass::Mark))
+    }
+
+    fn adjust(&mut self, position: usize, value: &ValueRecord) {
+        let glyph = &mut self.positions[position];
+
+        glyph.x_offset += value.x_placement as i32;
+        glyph.y_offset += value.y_placement as i32;
+        glyph.x_advance += value.x_advance as i32;
+        glyph.y_advance += value.y_advance as i32;
+    }
+
+    fn attach_mark(&mut self, mark: usize, base: usize, mark_anchor: &Anchor, base_anchor: &Anchor) {
+        self.positions[mark].x_offset = base_anchor.x as i32 - mark_anchor.x as i32;
+        self.positions[mark].y_offset = base_anchor.y as i32 - mark_anchor.y as i32;
+        self.attachments[mark] = Some(Attachment::Mark(base));
+    }
+
+    /// Makes the offsets of attached glyphs relative to the start of the glyph they follow
+    fn propagate_attachments(&mut self) {
+        let mut done = vec![false; self.positions.len()];
+        let mut chain = Vec::new();
+
+        for index in 0..self.positions.len() {
+            // Walk up to the first glyph that is already resolved or not attached at all,
+            // then resolve the chain from there. Long chains of marks would overflow the
+            // stack if this recursed.
+            let mut current = index;
+            while !done[current] {
+                done[current] = true;
+                chain.push(current);
+
+                match self.attachments[current] {
+                    Some(Attachment::Cursive(parent) | Attachment::Mark(parent)) => current = parent,
+                    None => break
+                }
+            }
+
+            while let Some(glyph) = chain.pop() {
+                self.propagate_attachment(glyph);
+            }
+        }
+    }
+
+    /// Adds the offsets of the glyph `index` is attached to, which has to be resolved already
+    fn propagate_attachment(&mut self, index: usize) {
+        match self.attachments[index] {
+            Some(Attachment::Cursive(parent)) => {
+                self.positions[index].y_offset += self.positions[parent].y_offset;
+            },
+            Some(Attachment::Mark(base)) => {
+                self.positions[index].x_offset += self.positions[base].x_offset;
+                self.positions[index].y_offset += self.positions[base].y_offset;
+
+                // Marks are drawn at the pen position, which has moved past the base already
+                let advances: i32 = match self.direction {
+                    Direction::LeftToRight => -self.positions[base..index].iter().map(|glyph| glyph.x_advance).sum::<i32>(),
+                    Direction::RightToLeft => self.positions[base + 1..=index].iter().map(|glyph| glyph.x_advance).sum::<i32>()
+                };
+                self.positions[index].x_offset += advances;
+            },
+            None => {}
+        }
+    }
+}
+
+impl GlyphPositioningTable {
+    /// Applies the lookups of `features` for a script and language system to a glyph buffer,
+    /// adjusting `positions`, which have to be initialized with the glyphs' default advances.
+    /// Scripts and languages are given as tags, e.g. "latn" and "DEU".
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply(&self, buffer: &[GlyphInfo], positions: &mut [GlyphPosition], script: &str, language: Option<&str>, features: &[FeatureSetting], gdef: Option<&GlyphDefinitionTable>, direction: Direction) {
+        if buffer.len() != positions.len() {
+            warn!("Glyph buffer has {} glyphs but {} positions, not positioning", buffer.len(), positions.len());
+            return;
+        }
+
+        let mut state = PositioningState {
+            buffer,
+            positions,
+            attachments: vec![None; buffer.len()],
+            gdef,
+            direction
+        };
+
+        for (lookup_index, _) in self.lookup_indices(tag(script), language.map(tag), features) {
+            let lookup = &self.lookups[lookup_index as usize];
+
+            let mut position = 0;
+            while position < buffer.len() {
+                position = match lookup.skips(buffer[position].glyph_index, gdef) {
+                    true => position + 1,
+                    false => self.apply_lookup(lookup_index, &mut state, position, 0)
+                        .unwrap_or(position + 1)
+                };
+            }
+        }
+
+        state.propagate_attachments();
+    }
+
+    /// Applies the first matching subtable of a lookup at `position`. Returns the position
+    /// to continue at if the lookup applied.
+    fn apply_lookup(&self, lookup_index: u16, state: &mut PositioningState, position: usize, depth: usize) -> Option<usize> {
+        if depth > MAX_NESTING_DEPTH {
+            warn!("Positioning lookups nested deeper than {} levels", MAX_NESTING_DEPTH);
+            return None;
+        }
+
+        let lookup = self.lookups.get(lookup_index as usize)?;
+        lookup.subtables.iter()
+            .find_map(|subtable| self.apply_subtable(lookup, subtable, state, position, depth))
+    }
+
+    fn apply_subtable(&self, lookup: &Lookup<PositioningSubtable>, subtable: &PositioningSubtable, state: &mut PositioningState, position: usize, depth: usize) -> Option<usize> {
+        let buffer = state.buffer;
+        let gdef = state.gdef;
+        let glyph_index = buffer[position].glyph_index;
+        let skips = |glyph_index: u16| lookup.skips(glyph_index, gdef);
+
+        match subtable {
+            PositioningSubtable::Single { coverage, value } => {
+                coverage.index(glyph_index)?;
+                state.adjust(position, value);
+
+                Some(position + 1)
+            },
+            PositioningSubtable::SingleList { coverage, values } => {
+                state.adjust(position, values.get(coverage.index(glyph_index)? as usize)?);
+                Some(position + 1)
+            },
+            PositioningSubtable::PairGlyphs { coverage, second_value_format, pair_sets } => {
+                let pairs = pair_sets.get(coverage.index(glyph_index)? as usize)?;
+                let second = next_position(buffer, position, skips)?;
+
+                let index = pairs.binary_search_by_key(&buffer[second].glyph_index, |pair| pair.second_glyph).ok()?;
+                state.adjust(position, &pairs[index].first_value);
+                state.adjust(second, &pairs[index].second_value);
+
+                Some(if *second_value_format != 0 { second + 1 } else { second })
+            },
+            PositioningSubtable::PairClasses { coverage, second_value_format, first_classes, second_classes, class_values } => {
+                coverage.index(glyph_index)?;
+                let second = next_position(buffer, position, skips)?;
+
+                let (first_value, second_value) = class_values.get(first_classes.class(glyph_index) as usize)?
+                    .get(second_classes.class(buffer[second].glyph_index) as usize)?;
+                state.adjust(position, first_value);
+                state.adjust(second, second_value);
+
+                Some(if *second_value_format != 0 { second + 1 } else { second })
+            },
+            PositioningSubtable::Cursive { coverage, entry_exits } => {
+                let (_, Some(exit)) = entry_exits.get(coverage.index(glyph_index)? as usize)? else {
+                    return None;
+                };
+
+                let next = next_position(buffer, position, skips)?;
+                let (Some(entry), _) = entry_exits.get(coverage.index(buffer[next].glyph_index)? as usize)? else {
+                    return None;
+                };
+
+                let positions = &mut *state.positions;
+                match state.direction {
+                    Direction::LeftToRight => {
+                        positions[position].x_advance = exit.x as i32 + positions[position].x_offset;
+
+                        let difference = entry.x as i32 + positions[next].x_offset;
+                        positions[next].x_advance -= difference;
+                        positions[next].x_offset -= difference;
+                    },
+                    Direction::RightToLeft => {
+                        let difference = exit.x as i32 + positions[position].x_offset;
+                        positions[position].x_advance -= difference;
+                        positions[position].x_offset -= difference;
+
+                        positions[next].x_advance = entry.x as i32 + positions[next].x_offset;
+                    }
+                }
+
+                // The glyph closer to the end of the line stays on the baseline unless the
+                // lookup asks for the last glyph to stay there
+                let (child, parent, y_offset) = match lookup.lookup_flag & RIGHT_TO_LEFT != 0 {
+                    true => (position, next, entry.y as i32 - exit.y as i32),
+                    false => (next, position, exit.y as i32 - entry.y as i32)
+                };
+
+                if matches!(state.attachments[parent], Some(Attachment::Cursive(index)) if index == child) {
+                    state.attachments[parent] = None;
+                }
+                positions[child].y_offset = y_offset;
+                state.attachments[child] = Some(Attachment::Cursive(parent));
+
+                Some(next)
+            },
+            PositioningSubtable::MarkToBase { mark_coverage, base_coverage, marks, bases } => {
+                let mark = marks.get(mark_coverage.index(glyph_index)? as usize)?;
+
+                // Marks attach to the closest preceding glyph that isn't a mark
+                let base = (0..position).rev().find(|&index| !state.is_mark(buffer[index].glyph_index))?;
+                let base_anchor = bases.get(base_coverage.index(buffer[base].glyph_index)? as usize)?
+                    .get(mark.class as usize)?.as_ref()?;
+
+                state.attach_mark(position, base, &mark.anchor, base_anchor);
+                Some(position + 1)
+            },
+            PositioningSubtable::MarkToLigature { mark_coverage, ligature_coverage, marks, ligatures } => {
+                let mark = marks.get(mark_coverage.index(glyph_index)? as usize)?;
+                let ligature = (0..position).rev().find(|&index| !state.is_mark(buffer[index].glyph_index))?;
+
+                let components = ligatures.get(ligature_coverage.index(buffer[ligature].glyph_index)? as usize)?;
+
+                // Marks skipped between the components during substitution know which one
+                // they follow, marks behind the ligature attach to its last component
+                let (ligature_id, mark_glyph) = (buffer[ligature].ligature_id, buffer[position]);
+                let component = match mark_glyph.ligature_component {
+                    component if ligature_id != 0 && mark_glyph.ligature_id == ligature_id
+                        => (component as usize).checked_sub(1).and_then(|index| components.get(index)).or(components.last())?,
+                    _ => components.last()?
+                };
+                let ligature_anchor = component.get(mark.class as usize)?.as_ref()?;
+
+                state.attach_mark(position, ligature, &mark.anchor, ligature_anchor);
+                Some(position + 1)
+            },
+            PositioningSubtable::MarkToMark { mark_coverage, base_mark_coverage, marks, base_marks } => {
+                let mark = marks.get(mark_coverage.index(glyph_index)? as usize)?;
+                let base = previous_position(buffer, position, skips)?;
+                let base_anchor = base_marks.get(base_mark_coverage.index(buffer[base].glyph_index)? as usize)?
+                    .get(mark.class as usize)?.as_ref()?;
+
+                state.attach_mark(position, base, &mark.anchor, base_anchor);
+                Some(position + 1)
+            },
+            PositioningSubtable::Context(context) | PositioningSubtable::ChainedContext(context) => {
+                let (positions, records) = context.match_at(buffer, position, skips)?;
+
+                for record in records {
+                    if let Some(&target) = positions.get(record.sequence_index as usize) {
+                        self.apply_lookup(record.lookup_list_index, state, target, depth + 1);
+                    }
+                }
+
+                Some(*positions.last()? + 1)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::table::layout::{Anchor, ClassDef, Coverage, Direction, GlyphDefinitionTable, GlyphInfo, GlyphPosition, LayoutTable, Lookup};
+
+    use std::io::Cursor;
+
+    use super::{load_anchor_matrix, Attachment, GlyphPositioningTable, MarkRecord, PositioningState, PositioningSubtable};
+
+    /// Glyphs 10 and 11 are marks
+    fn gdef() -> GlyphDefinitionTable {
+        GlyphDefinitionTable {
+            major_version: 1,
+            minor_version: 0,
+            glyph_class_def: Some(ClassDef::Array { start_glyph: 10, classes: vec![3, 3] }),
+            attach_list: None,
+            lig_caret_list: None,
+            mark_attach_class_def: None,
+            mark_glyph_sets: vec![],
+            item_variation_store: None
+        }
+    }
+
+    fn anchor(x: i16, y: i16) -> Anchor {
+        Anchor { x, y, ..Anchor::default() }
+    }
+
+    #[test]
+    fn marks_attach_to_the_ligature_component_they_follow() {
+        let mark = MarkRecord { class: 0, anchor: anchor(0, 0) };
+        let gpos: GlyphPositioningTable = LayoutTable {
+            major_version: 1,
+            minor_version: 0,
+            scripts: vec![],
+            features: vec![],
+            lookups: vec![Lookup {
+                lookup_type: 5,
+                lookup_flag: 0,
+                mark_filtering_set: None,
+                subtables: vec![PositioningSubtable::MarkToLigature {
+                    mark_coverage: Coverage::Glyphs(vec![10, 11]),
+                    ligature_coverage: Coverage::Glyphs(vec![5]),
+                    marks: vec![mark.clone(), mark],
+                    ligatures: vec![vec![vec![Some(anchor(100, 500))], vec![Some(anchor(400, 600))]]]
+                }]
+            }]
+        };
+
+        // A ligature of two components with a mark after each of them
+        let buffer = [
+            GlyphInfo { ligature_id: 1, ..GlyphInfo::new(5, 0) },
+            GlyphInfo { ligature_id: 1, ligature_component: 1, ..GlyphInfo::new(10, 1) },
+            GlyphInfo::new(11, 3)
+        ];
+        let mut positions = [GlyphPosition { x_advance: 600, ..GlyphPosition::default() }, GlyphPosition::default(), GlyphPosition::default()];
+
+        let gdef = gdef();
+        let mut state = PositioningState {
+            buffer: &buffer,
+            positions: &mut positions,
+            attachments: vec![None; buffer.len()],
+            gdef: Some(&gdef),
+            direction: Direction::LeftToRight
+        };
+        assert_eq!(gpos.apply_lookup(0, &mut state, 1, 0), Some(2));
+        assert_eq!(gpos.apply_lookup(0, &mut state, 2, 0), Some(3));
+        state.propagate_attachments();
+
+        assert_eq!((positions[1].x_offset, positions[1].y_offset), (100 - 600, 500));
+        assert_eq!((positions[2].x_offset, positions[2].y_offset), (400 - 600, 600));
+    }
+
+    #[test]
+    fn ligatures_without_components_take_no_marks() {
+        let gpos: GlyphPositioningTable = LayoutTable {
+            major_version: 1,
+            minor_version: 0,
+            scripts: vec![],
+            features: vec![],
+            lookups: vec![Lookup {
+                lookup_type: 5,
+                lookup_flag: 0,
+                mark_filtering_set: None,
+                subtables: vec![PositioningSubtable::MarkToLigature {
+                    mark_coverage: Coverage::Glyphs(vec![10]),
+                    ligature_coverage: Coverage::Glyphs(vec![5]),
+                    marks: vec![MarkRecord { class: 0, anchor: anchor(0, 0) }],
+                    ligatures: vec![vec![]]
+                }]
+            }]
+        };
+
+        let buffer = [
+            GlyphInfo { ligature_id: 1, ..GlyphInfo::new(5, 0) },
+            GlyphInfo { ligature_id: 1, ligature_component: 1, ..GlyphInfo::new(10, 1) }
+        ];
+        let mut positions = [GlyphPosition::default(); 2];
+
+        let gdef = gdef();
+        let mut state = PositioningState {
+            buffer: &buffer,
+            positions: &mut positions,
+            attachments: vec![None; buffer.len()],
+            gdef: Some(&gdef),
+            direction: Direction::LeftToRight
+        };
+        assert_eq!(gpos.apply_lookup(0, &mut state, 1, 0), None);
+    }
+
+    #[test]
+    fn anchor_matrix_larger_than_the_table_is_an_error() {
+        let data = [0xFF, 0xFF, 0, 0, 0, 0];
+        assert!(load_anchor_matrix(&mut Cursor::new(&data), u16::MAX).is_err());
+    }
+
+    #[test]
+    fn long_attachment_chains_are_resolved() {
+        let length = 200_000;
+        let buffer = vec![GlyphInfo::new(10, 0); length];
+        let mut positions = vec![GlyphPosition { y_offset: 1, ..GlyphPosition::default() }; length];
+
+        let mut state = PositioningState {
+            buffer: &buffer,
+            positions: &mut positions,
+            attachments: (0..length).map(|index| index.checked_sub(1).map(Attachment::Mark)).collect(),
+            gdef: None,
+            direction: Direction::LeftToRight
+        };
+        state.propagate_attachments();
+
+        assert!(positions.iter().enumerate().all(|(index, position)| position.y_offset == index as i32 + 1));
+    }
+}
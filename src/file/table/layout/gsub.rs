@@ -0,0 +1,385 @@
+use std::io::{Read, Seek};
+
+use log::warn;
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::{FontError, Result}, loader::TableDirectoryEntry, table::Table};
+
+use super::{
+    context::{match_backtrack, match_sequence},
+    load_at, tag, Coverage, FeatureSetting, GlyphDefinitionTable, GlyphInfo, LayoutTable, Lookup, LookupSubtable, SequenceContext
+};
+
+/// How deep contextual lookups may nest before they are cut off
+const MAX_NESTING_DEPTH: usize = 64;
+
+/// A ligature glyph and the glyphs it replaces
+#[derive(Debug, Clone)]
+pub struct Ligature {
+    pub ligature_glyph: u16,
+    /// The components following the first one, which is matched by the coverage table
+    pub components: Vec<u16>
+}
+
+/// The subtables of GSUB lookups
+#[derive(Debug, Clone)]
+pub enum SubstitutionSubtable {
+    /// Type 1 format 1, adds a delta to the glyph index
+    SingleDelta {
+        coverage: Coverage,
+        delta_glyph_id: i16
+    },
+
+    /// Type 1 format 2, replaces a glyph with the substitute at its coverage index
+    SingleList {
+        coverage: Coverage,
+        substitutes: Vec<u16>
+    },
+
+    /// Type 2, replaces a glyph with a sequence of glyphs
+    Multiple {
+        coverage: Coverage,
+        sequences: Vec<Vec<u16>>
+    },
+
+    /// Type 3, replaces a glyph with one of several alternates
+    Alternate {
+        coverage: Coverage,
+        alternate_sets: Vec<Vec<u16>>
+    },
+
+    /// Type 4, replaces a sequence of glyphs with a single glyph
+    Ligature {
+        coverage: Coverage,
+        ligature_sets: Vec<Vec<Ligature>>
+    },
+
+    /// Type 5
+    Context(SequenceContext),
+
+    /// Type 6
+    ChainedContext(SequenceContext),
+
+    /// Type 8, single substitutions applied from the end of the buffer
+    ReverseChainSingle {
+        coverage: Coverage,
+        backtrack: Vec<Coverage>,
+        lookahead: Vec<Coverage>,
+        substitutes: Vec<u16>
+    }
+}
+
+fn load_sets<S, T, F>(stream: &mut S, start: u64, load: F) -> Result<Vec<T>>
+    where S: Read + Seek,
+          T: Default,
+          F: Fn(&mut S) -> Result<T>
+{
+    let count: u16 = deserialize_from(stream)?;
+    let offsets: Vec<u16> = deserialize_vec_from(count as usize, stream)?;
+
+    let mut sets = Vec::with_capacity(offsets.len());
+    for offset in offsets {
+        sets.push(load_at(stream, start, offset as u32, &load)?.unwrap_or_default());
+    }
+
+    Ok(sets)
+}
+
+fn load_glyph_array<S>(stream: &mut S) -> Result<Vec<u16>>
+    where S: Read + Seek
+{
+    let count: u16 = deserialize_from(stream)?;
+    Ok(deserialize_vec_from(count as usize, stream)?)
+}
+
+fn load_coverage_array<S>(stream: &mut S, start: u64) -> Result<Vec<Coverage>>
+    where S: Read + Seek
+{
+    let offsets = load_glyph_array(stream)?;
+    let position = stream.stream_position()?;
+
+    let coverages = offsets.into_iter()
+        .map(|offset| Coverage::load_required(stream, start, offset))
+        .collect::<Result<Vec<Coverage>>>()?;
+
+    stream.seek(std::io::SeekFrom::Start(position))?;
+    Ok(coverages)
+}
+
+impl LookupSubtable for SubstitutionSubtable {
+    const EXTENSION_TYPE: u16 = 7;
+
+    fn load<S>(lookup_type: u16, stream: &mut S) -> Result<Option<Self>>
+        where S: Read + Seek
+    {
+        let start = stream.stream_position()?;
+
+        if lookup_type == 5 || lookup_type == 6 {
+            let context = SequenceContext::load(stream, lookup_type == 6)?;
+
+            return Ok(Some(match lookup_type {
+                5 => SubstitutionSubtable::Context(context),
+                _ => SubstitutionSubtable::ChainedContext(context)
+            }));
+        }
+
+        let format: u16 = deserialize_from(stream)?;
+        let coverage_offset: u16 = deserialize_from(stream)?;
+
+        let subtable = match (lookup_type, format) {
+            (1, 1) => SubstitutionSubtable::SingleDelta {
+                delta_glyph_id: deserialize_from(stream)?,
+                coverage: Coverage::load_required(stream, start, coverage_offset)?
+            },
+            (1, 2) => SubstitutionSubtable::SingleList {
+                substitutes: load_glyph_array(stream)?,
+                coverage: Coverage::load_required(stream, start, coverage_offset)?
+            },
+            (2, 1) => SubstitutionSubtable::Multiple {
+                sequences: load_sets(stream, start, load_glyph_array)?,
+                coverage: Coverage::load_required(stream, start, coverage_offset)?
+            },
+            (3, 1) => SubstitutionSubtable::Alternate {
+                alternate_sets: load_sets(stream, start, load_glyph_array)?,
+                coverage: Coverage::load_required(stream, start, coverage_offset)?
+            },
+            (4, 1) => SubstitutionSubtable::Ligature {
+                ligature_sets: load_sets(stream, start, |stream| {
+                    let set_start = stream.stream_position()?;
+
+                    load_sets(stream, set_start, |stream| {
+                        let ligature_glyph: u16 = deserialize_from(stream)?;
+                        let component_count: u16 = deserialize_from(stream)?;
+
+                        Ok(Some(Ligature {
+                            ligature_glyph,
+                            components: deserialize_vec_from(component_count.saturating_sub(1) as usize, stream)?
+                        }))
+                    }).map(|ligatures| ligatures.into_iter().flatten().collect())
+                })?,
+                coverage: Coverage::load_required(stream, start, coverage_offset)?
+            },
+            (8, 1) => {
+                let backtrack = load_coverage_array(stream, start)?;
+                let lookahead = load_coverage_array(stream, start)?;
+
+                SubstitutionSubtable::ReverseChainSingle {
+                    substitutes: load_glyph_array(stream)?,
+                    coverage: Coverage::load_required(stream, start, coverage_offset)?,
+                    backtrack,
+                    lookahead
+                }
+            },
+            (1..=8, _) => return Err(FontError::FontFormatError(
+                Some(start as u32),
+                format!("Unknown format {} for substitution lookup type {}", format, lookup_type)
+            )),
+            _ => return Ok(None)
+        };
+
+        Ok(Some(subtable))
+    }
+}
+
+/// The glyph substitution table
+pub type GlyphSubstitutionTable = LayoutTable<SubstitutionSubtable>;
+
+impl Table for GlyphSubstitutionTable {
+    type UserArgsType = ();
+
+    fn get_table_name() -> &'static str {
+        "Glyph Substitution"
+    }
+
+    fn load_impl<S>(dict_entry: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
+        where S: Read + Seek
+    {
+        LayoutTable::load(&dict_entry, stream)
+    }
+}
+
+impl GlyphSubstitutionTable {
+    /// Applies the lookups of `features` for a script and language system to a glyph buffer.
+    /// Scripts and languages are given as tags, e.g. "latn" and "DEU".
+    pub fn apply(&self, buffer: &mut Vec<GlyphInfo>, script: &str, language: Option<&str>, features: &[FeatureSetting], gdef: Option<&GlyphDefinitionTable>) {
+        for (lookup_index, value) in self.lookup_indices(tag(script), language.map(tag), features) {
+            let lookup = &self.lookups[lookup_index as usize];
+
+            if lookup.lookup_type == 8 {
+                for position in (0..buffer.len()).rev() {
+                    if !lookup.skips(buffer[position].glyph_index, gdef) {
+                        self.apply_lookup(lookup_index, value, buffer, position, gdef, 0);
+                    }
+                }
+
+                continue;
+            }
+
+            let mut position = 0;
+            while position < buffer.len() {
+                position = match lookup.skips(buffer[position].glyph_index, gdef) {
+                    true => position + 1,
+                    false => self.apply_lookup(lookup_index, value, buffer, position, gdef, 0)
+                        .unwrap_or(position + 1)
+                };
+            }
+        }
+    }
+
+    /// Applies the first matching subtable of a lookup at `position`. Returns the position
+    /// to continue at if a substitution was made.
+    fn apply_lookup(&self, lookup_index: u16, value: u32, buffer: &mut Vec<GlyphInfo>, position: usize, gdef: Option<&GlyphDefinitionTable>, depth: usize) -> Option<usize> {
+        if depth > MAX_NESTING_DEPTH {
+            warn!("Substitution lookups nested deeper than {} levels", MAX_NESTING_DEPTH);
+            return None;
+        }
+
+        let lookup = self.lookups.get(lookup_index as usize)?;
+        lookup.subtables.iter()
+            .find_map(|subtable| self.apply_subtable(lookup, subtable, value, buffer, position, gdef, depth))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn apply_subtable(&self, lookup: &Lookup<SubstitutionSubtable>, subtable: &SubstitutionSubtable, value: u32, buffer: &mut Vec<GlyphInfo>, position: usize, gdef: Option<&GlyphDefinitionTable>, depth: usize) -> Option<usize> {
+        let glyph_index = buffer[position].glyph_index;
+        let skips = |glyph_index: u16| lookup.skips(glyph_index, gdef);
+
+        match subtable {
+            SubstitutionSubtable::SingleDelta { coverage, delta_glyph_id } => {
+                coverage.index(glyph_index)?;
+                buffer[position].glyph_index = glyph_index.wrapping_add(*delta_glyph_id as u16);
+
+                Some(position + 1)
+            },
+            SubstitutionSubtable::SingleList { coverage, substitutes } => {
+                buffer[position].glyph_index = *substitutes.get(coverage.index(glyph_index)? as usize)?;
+                Some(position + 1)
+            },
+            SubstitutionSubtable::Multiple { coverage, sequences } => {
+                let sequence = sequences.get(coverage.index(glyph_index)? as usize)?;
+                let glyph = buffer[position];
+
+                buffer.splice(position..=position, sequence.iter().map(|&glyph_index| GlyphInfo { glyph_index, ..glyph }));
+                Some(position + sequence.len())
+            },
+            SubstitutionSubtable::Alternate { coverage, alternate_sets } => {
+                let alternates = alternate_sets.get(coverage.index(glyph_index)? as usize)?;
+                buffer[position].glyph_index = *alternates.get((value as usize).checked_sub(1)?)?;
+
+                Some(position + 1)
+            },
+            SubstitutionSubtable::Ligature { coverage, ligature_sets } => {
+                let ligatures = ligature_sets.get(coverage.index(glyph_index)? as usize)?;
+                let (ligature, positions) = ligatures.iter().find_map(|ligature| {
+                    let positions = match_sequence(buffer, position, &ligature.components, &skips, |&component, glyph_index| component == glyph_index)?;
+                    Some((ligature, positions))
+                })?;
+
+                // Skipped glyphs between the components stay where they are, remembering
+                // which component they follow so marks can be attached to it later
+                let ligature_id = buffer.iter().map(|glyph| glyph.ligature_id).max()?.wrapping_add(1).max(1);
+                for (component, pair) in positions.windows(2).enumerate() {
+                    for glyph in &mut buffer[pair[0] + 1..pair[1]] {
+                        glyph.ligature_id = ligature_id;
+                        glyph.ligature_component = component as u16 + 1;
+                    }
+                }
+
+                buffer[position] = GlyphInfo {
+                    glyph_index: ligature.ligature_glyph,
+                    cluster: positions.iter().map(|&index| buffer[index].cluster).min()?,
+                    ligature_id,
+                    ligature_component: 0
+                };
+                for &index in positions[1..].iter().rev() {
+                    buffer.remove(index);
+                }
+
+                Some(position + 1)
+            },
+            SubstitutionSubtable::Context(context) | SubstitutionSubtable::ChainedContext(context) => {
+                let (mut positions, records) = context.match_at(buffer, position, skips)?;
+                let mut end = *positions.last()? + 1;
+
+                for record in records {
+                    let Some(&target) = positions.get(record.sequence_index as usize) else {
+                        continue;
+                    };
+
+                    let length = buffer.len();
+                    self.apply_lookup(record.lookup_list_index, value, buffer, target, gdef, depth + 1);
+
+                    // Nested lookups may have changed the buffer length, move every
+                    // position behind the substituted glyph by the difference
+                    let difference = buffer.len() as isize - length as isize;
+                    if difference != 0 {
+                        for index in positions.iter_mut().filter(|index| **index > target) {
+                            *index = index.saturating_add_signed(difference).max(target);
+                        }
+                        end = end.saturating_add_signed(difference).max(target + 1);
+                    }
+                }
+
+                Some(end.min(buffer.len()).max(position + 1))
+            },
+            SubstitutionSubtable::ReverseChainSingle { coverage, backtrack, lookahead, substitutes } => {
+                let substitute = *substitutes.get(coverage.index(glyph_index)? as usize)?;
+
+                match_backtrack(buffer, position, backtrack, &skips, |coverage, glyph_index| coverage.contains(glyph_index))?;
+                match_sequence(buffer, position, lookahead, &skips, |coverage, glyph_index| coverage.contains(glyph_index))?;
+
+                buffer[position].glyph_index = substitute;
+                Some(position + 1)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::table::layout::{ClassDef, Coverage, GlyphDefinitionTable, GlyphInfo, LayoutTable, Lookup, IGNORE_MARKS};
+
+    use super::{GlyphSubstitutionTable, Ligature, SubstitutionSubtable};
+
+    /// Glyphs 10 and 11 are marks
+    fn gdef() -> GlyphDefinitionTable {
+        GlyphDefinitionTable {
+            major_version: 1,
+            minor_version: 0,
+            glyph_class_def: Some(ClassDef::Array { start_glyph: 10, classes: vec![3, 3] }),
+            attach_list: None,
+            lig_caret_list: None,
+            mark_attach_class_def: None,
+            mark_glyph_sets: vec![],
+            item_variation_store: None
+        }
+    }
+
+    #[test]
+    fn ligatures_number_the_marks_between_their_components() {
+        let gsub: GlyphSubstitutionTable = LayoutTable {
+            major_version: 1,
+            minor_version: 0,
+            scripts: vec![],
+            features: vec![],
+            lookups: vec![Lookup {
+                lookup_type: 4,
+                lookup_flag: IGNORE_MARKS,
+                mark_filtering_set: None,
+                subtables: vec![SubstitutionSubtable::Ligature {
+                    coverage: Coverage::Glyphs(vec![1]),
+                    ligature_sets: vec![vec![Ligature { ligature_glyph: 5, components: vec![2, 3] }]]
+                }]
+            }]
+        };
+
+        let mut buffer: Vec<GlyphInfo> = [1, 10, 2, 11, 3, 10].iter().enumerate()
+            .map(|(cluster, &glyph_index)| GlyphInfo::new(glyph_index, cluster))
+            .collect();
+        assert_eq!(gsub.apply_lookup(0, 0, &mut buffer, 0, Some(&gdef()), 0), Some(1));
+
+        let glyphs: Vec<(u16, u16, u16)> = buffer.iter().map(|glyph| (glyph.glyph_index, glyph.ligature_id, glyph.ligature_component)).collect();
+        assert_eq!(glyphs, [(5, 1, 0), (10, 1, 1), (11, 1, 2), (10, 0, 0)]);
+        assert_eq!(buffer[0].cluster, 0);
+        assert_eq!(buffer[2].cluster, 3);
+    }
+}
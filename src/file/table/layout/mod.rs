@@ -0,0 +1,26 @@
+mod common;
+pub use common::{
+    load_at, tag, ClassDef, ClassRangeRecord, Coverage, Feature, FeatureSetting, LangSys, LayoutTable, Lookup, LookupSubtable, RangeRecord, Script,
+    DEFAULT_FEATURES, IGNORE_BASE_GLYPHS, IGNORE_LIGATURES, IGNORE_MARKS, MARK_ATTACHMENT_TYPE, RIGHT_TO_LEFT, USE_MARK_FILTERING_SET
+};
+
+mod gdef;
+pub use gdef::{AttachList, CaretValue, GlyphClass, GlyphDefinitionTable, LigatureCaretList};
+
+mod buffer;
+pub use buffer::{Direction, GlyphInfo, GlyphPosition, ShapedGlyph};
+
+mod context;
+pub use context::{ContextRule, SequenceContext, SequenceLookupRecord};
+
+mod gsub;
+pub use gsub::{GlyphSubstitutionTable, Ligature, SubstitutionSubtable};
+
+mod value;
+pub use value::{
+    Anchor, Device, ValueRecord, X_ADVANCE, X_ADVANCE_DEVICE, X_PLACEMENT, X_PLACEMENT_DEVICE, Y_ADVANCE, Y_ADVANCE_DEVICE, Y_PLACEMENT,
+    Y_PLACEMENT_DEVICE
+};
+
+mod gpos;
+pub use gpos::{GlyphPositioningTable, MarkRecord, PairValueRecord, PositioningSubtable};
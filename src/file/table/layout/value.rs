@@ -0,0 +1,176 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::{FontError, Result}};
+
+use super::load_at;
+
+pub const X_PLACEMENT:          u16 = 0x0001;
+pub const Y_PLACEMENT:          u16 = 0x0002;
+pub const X_ADVANCE:            u16 = 0x0004;
+pub const Y_ADVANCE:            u16 = 0x0008;
+pub const X_PLACEMENT_DEVICE:   u16 = 0x0010;
+pub const Y_PLACEMENT_DEVICE:   u16 = 0x0020;
+pub const X_ADVANCE_DEVICE:     u16 = 0x0040;
+pub const Y_ADVANCE_DEVICE:     u16 = 0x0080;
+
+/// Size-specific adjustments of a value, or an index into the GDEF item variation store
+/// for variable fonts. They are parsed but not applied.
+#[derive(Debug, Clone)]
+pub enum Device {
+    Hinting {
+        start_size: u16,
+        end_size: u16,
+        delta_format: u16,
+        /// The packed delta values, see `delta_format` for their bit size
+        delta_values: Vec<u16>
+    },
+
+    Variation {
+        delta_set_outer_index: u16,
+        delta_set_inner_index: u16
+    }
+}
+
+impl Device {
+    pub fn load<S>(stream: &mut S) -> Result<Device>
+        where S: Read + Seek
+    {
+        let start = stream.stream_position()?;
+        let first: u16 = deserialize_from(stream)?;
+        let second: u16 = deserialize_from(stream)?;
+        let delta_format: u16 = deserialize_from(stream)?;
+
+        Ok(match delta_format {
+            1..=3 => {
+                // 2, 4 or 8 bits per delta
+                let bits = 1 << delta_format;
+                let count = (second.saturating_sub(first) as usize + 1) * bits;
+
+                Device::Hinting {
+                    start_size: first,
+                    end_size: second,
+                    delta_format,
+                    delta_values: deserialize_vec_from(count.div_ceil(16), stream)?
+                }
+            },
+            0x8000 => Device::Variation {
+                delta_set_outer_index: first,
+                delta_set_inner_index: second
+            },
+            _ => return Err(FontError::FontFormatError(Some(start as u32), format!("Unknown device table format {}", delta_format)))
+        })
+    }
+}
+
+fn load_device<S>(stream: &mut S, base: u64, offset: u16) -> Result<Option<Device>>
+    where S: Read + Seek
+{
+    load_at(stream, base, offset as u32, Device::load)
+}
+
+/// Adjustments to the placement and advance of a glyph. Fields not present in the
+/// value format are 0.
+#[derive(Debug, Clone, Default)]
+pub struct ValueRecord {
+    pub x_placement: i16,
+    pub y_placement: i16,
+    pub x_advance: i16,
+    pub y_advance: i16,
+
+    pub x_placement_device: Option<Device>,
+    pub y_placement_device: Option<Device>,
+    pub x_advance_device: Option<Device>,
+    pub y_advance_device: Option<Device>
+}
+
+impl ValueRecord {
+    /// Loads a value record with the fields set in `format`. Device offsets are relative
+    /// to `base`, the start of the positioning subtable.
+    pub fn load<S>(stream: &mut S, format: u16, base: u64) -> Result<ValueRecord>
+        where S: Read + Seek
+    {
+        let mut read = |flag: u16| -> Result<u16> {
+            match format & flag != 0 {
+                true => Ok(deserialize_from(stream)?),
+                false => Ok(0)
+            }
+        };
+
+        let x_placement = read(X_PLACEMENT)? as i16;
+        let y_placement = read(Y_PLACEMENT)? as i16;
+        let x_advance = read(X_ADVANCE)? as i16;
+        let y_advance = read(Y_ADVANCE)? as i16;
+        let device_offsets = [read(X_PLACEMENT_DEVICE)?, read(Y_PLACEMENT_DEVICE)?, read(X_ADVANCE_DEVICE)?, read(Y_ADVANCE_DEVICE)?];
+
+        if device_offsets.iter().all(|&offset| offset == 0) {
+            return Ok(ValueRecord { x_placement, y_placement, x_advance, y_advance, ..Default::default() });
+        }
+
+        let position = stream.stream_position()?;
+        let record = ValueRecord {
+            x_placement,
+            y_placement,
+            x_advance,
+            y_advance,
+
+            x_placement_device: load_device(stream, base, device_offsets[0])?,
+            y_placement_device: load_device(stream, base, device_offsets[1])?,
+            x_advance_device: load_device(stream, base, device_offsets[2])?,
+            y_advance_device: load_device(stream, base, device_offsets[3])?
+        };
+
+        stream.seek(SeekFrom::Start(position))?;
+        Ok(record)
+    }
+}
+
+/// A point on a glyph that other glyphs attach to
+#[derive(Debug, Clone, Default)]
+pub struct Anchor {
+    pub x: i16,
+    pub y: i16,
+
+    /// Format 2, the contour point the anchor is snapped to after hinting
+    pub anchor_point: Option<u16>,
+
+    /// Format 3
+    pub x_device: Option<Device>,
+    pub y_device: Option<Device>
+}
+
+impl Anchor {
+    /// Loads an anchor table starting at the current stream position
+    pub fn load<S>(stream: &mut S) -> Result<Anchor>
+        where S: Read + Seek
+    {
+        let start = stream.stream_position()?;
+        let format: u16 = deserialize_from(stream)?;
+        let x: i16 = deserialize_from(stream)?;
+        let y: i16 = deserialize_from(stream)?;
+
+        Ok(match format {
+            1 => Anchor { x, y, ..Default::default() },
+            2 => Anchor { x, y, anchor_point: Some(deserialize_from(stream)?), ..Default::default() },
+            3 => {
+                let x_device_offset: u16 = deserialize_from(stream)?;
+                let y_device_offset: u16 = deserialize_from(stream)?;
+
+                Anchor {
+                    x,
+                    y,
+                    anchor_point: None,
+                    x_device: load_device(stream, start, x_device_offset)?,
+                    y_device: load_device(stream, start, y_device_offset)?
+                }
+            },
+            _ => return Err(FontError::FontFormatError(Some(start as u32), format!("Unknown anchor format {}", format)))
+        })
+    }
+
+    /// Loads an anchor table at `base + offset`, returns `None` for null offsets
+    pub fn load_at<S>(stream: &mut S, base: u64, offset: u16) -> Result<Option<Anchor>>
+        where S: Read + Seek
+    {
+        load_at(stream, base, offset as u32, Anchor::load)
+    }
+}
@@ -0,0 +1,42 @@
+use std::io::{Read, Seek};
+
+use log::debug;
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::Result};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct SubtableHeader {
+    _length:        u16,
+    _language:      u16
+}
+
+/// CMAP table format 0, mapping single byte character codes
+#[derive(Debug, Clone)]
+pub struct ByteMapping {
+    glyph_ids: Vec<u8>
+}
+
+impl ByteMapping {
+    /// Implements decoding for CMAP table format 0
+    pub fn load<S>(stream: &mut S) -> Result<ByteMapping>
+        where S: Read + Seek
+    {
+        debug!("loading a byte encoding char map at 0x{:08x}", stream.stream_position()?);
+
+        let _: SubtableHeader = deserialize_from(stream)?;
+        let glyph_ids: Vec<u8> = deserialize_vec_from(256, stream)?;
+
+        Ok(ByteMapping {
+            glyph_ids
+        })
+    }
+
+    pub fn map(&self, codepoint: u32) -> Option<u16> {
+        self.glyph_ids.get(codepoint as usize).map(|&glyph_index| glyph_index as u16)
+    }
+
+    pub fn ranges(&self) -> Vec<(u32, u32)> {
+        vec![(0, 255)]
+    }
+}
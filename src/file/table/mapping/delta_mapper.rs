@@ -4,14 +4,12 @@ use itertools::izip;
 use log::{debug, warn};
 use serde::{de::DeserializeOwned, Deserialize};
 
-use crate::file::{self, deserialize_from, error::{FontError, Result}};
-
-use super::CharacterMap;
+use crate::file::{deserialize_from, deserialize_vec_from, error::Result};
 
 #[derive(Debug, Clone, Copy, Deserialize)]
 struct SubtableHeader {
-    length:         u16,
-    language:       u16,
+    _length:        u16,
+    _language:      u16,
     seg_count_x2:   u16,
     _unused:        [u16; 3]
 }
@@ -29,63 +27,97 @@ fn read_table<S, T>(entries: u16, stream: &mut S) -> Result<Vec<T>>
     Ok(result)
 }
 
-fn process_segment<F>(start: u16, end: u16, map: &mut CharacterMap, mut indexing_func: F) -> Result<()>
-    where F: FnMut(u16) -> Result<u16>
-{
-    for codepoint in start..=end {
-        let Some(character) = char::from_u32(codepoint as u32) else {
-            warn!("Codepoint 0x{:04x} could not be converted to a character (invalid unicode). Skipping.", codepoint);
-            continue;
-        };
-
-        let index = indexing_func(codepoint)?;
-        if let Some(old_val) = map.insert(character, index) {
-            warn!("Codepoint 0x{:04x} was mapped twice. Replaced previous glyph index (0x{:04x}) with new value (0x{:04x}).", codepoint, old_val, index);
-        }
-    }
-
-    Ok(())
+/// CMAP table format 4, mapping segments of the BMP either by a delta or through a glyph index array
+#[derive(Debug, Clone)]
+pub struct DeltaMapping {
+    end_codes:      Vec<u16>,
+    start_codes:    Vec<u16>,
+    deltas:         Vec<i16>,
+    range_offsets:  Vec<u16>,
+    glyph_ids:      Vec<u16>
 }
 
-/// Implements decoding for CMAP table format 4
-pub fn load<S>(stream: &mut S) -> Result<CharacterMap> 
-    where S: Read + Seek 
-{
-    debug!("loading a delta encoded char map at 0x{:08x}", stream.stream_position()?);
+impl DeltaMapping {
+    /// Implements decoding for CMAP table format 4. `table_end` is the offset of the end of
+    /// the character map table, which the glyph index array can't reach past.
+    pub fn load<S>(stream: &mut S, table_end: u64) -> Result<DeltaMapping> 
+        where S: Read + Seek 
+    {
+        debug!("loading a delta encoded char map at 0x{:08x}", stream.stream_position()?);
+
+        let header: SubtableHeader = deserialize_from(stream)?;
+        let num_segments = header.seg_count_x2 / 2;
+
+        debug!("Reading {} entries from tables", num_segments);
+
+        let end_codes: Vec<u16>     = read_table(num_segments, stream)?;
+        let _: u16                  = deserialize_from(stream)?;
+        let start_codes: Vec<u16>   = read_table(num_segments, stream)?;
+        let deltas: Vec<i16>        = read_table(num_segments, stream)?;
+        let range_offsets: Vec<u16> = read_table(num_segments, stream)?;
+
+        // The length field overflows for large subtables, so the size of the glyph index
+        // array is derived from the segments referencing it instead
+        let mut num_glyph_ids: usize = 0;
+
+        debug!("segments found: ");
+        for (i, (&start, &end, &delta, &offset)) in izip!(&start_codes, &end_codes, &deltas, &range_offsets).enumerate() {
+            debug!("    + {:04X}-{:04X} ({} codepoints)\toffset={},\tdelta={}", start, end, end.wrapping_sub(start).wrapping_add(1), offset, delta);
+            if offset != 0 && start <= end {
+                let last = (offset / 2) as usize + (end - start) as usize + i;
+                num_glyph_ids = num_glyph_ids.max((last + 1).saturating_sub(num_segments as usize));
+            }
+        }
 
-    let header: SubtableHeader = deserialize_from(stream)?;
-    let num_segments = header.seg_count_x2 / 2;
+        // A single broken range offset may point far past the subtable
+        let available = table_end.saturating_sub(stream.stream_position()?) as usize / 2;
+        if num_glyph_ids > available {
+            warn!("Glyph index array needs {} entries, but only {} fit into the character map", num_glyph_ids, available);
+            num_glyph_ids = available;
+        }
 
-    debug!("Reading {} entries from tables", num_segments);
+        let glyph_ids: Vec<u16> = deserialize_vec_from(num_glyph_ids, stream)?;
 
-    let end_table: Vec<u16>     = read_table(num_segments, stream)?;
-    let _: u16                  = deserialize_from(stream)?;
-    let start_table: Vec<u16>   = read_table(num_segments, stream)?;
-    let delta_table: Vec<i16>   = read_table(num_segments, stream)?;
-    let offsets_table: Vec<u16> = read_table(num_segments, stream)?;
+        Ok(DeltaMapping {
+            end_codes,
+            start_codes,
+            deltas,
+            range_offsets,
+            glyph_ids
+        })
+    }
+
+    pub fn map(&self, codepoint: u32) -> Option<u16> {
+        let codepoint = u16::try_from(codepoint).ok()?;
 
-    let glyphs_start = stream.stream_position()?;
-    let mut char_map = CharacterMap::new();
+        let i = self.end_codes.partition_point(|&end| end < codepoint);
+        let start = *self.start_codes.get(i)?;
+        if codepoint < start {
+            return None;
+        }
 
-    debug!("segments found: ");
-    for (i, (start, end, delta, offset)) in izip!(start_table, end_table, delta_table, offsets_table).enumerate() {
-        debug!("    + {:04X}-{:04X} ({} codepoints)\toffset={},\tdelta={}", start, end, end-start+1, offset, delta);
+        let (delta, offset) = (self.deltas[i], self.range_offsets[i]);
         if offset == 0 {
-            process_segment(start, end, &mut char_map, |codepoint: u16| Ok::<u16, FontError>(codepoint.wrapping_add_signed(delta)))?;
-        } else {
-            process_segment(start, end, &mut char_map, |codepoint: u16| {
-                let glyph_offset = offset + (codepoint - start) - (i as u16);
-                stream.seek(std::io::SeekFrom::Start(glyphs_start + glyph_offset as u64))?;
-                
-                let mut glyph_index: u16 = file::deserialize_from(stream)?;
-                if glyph_index != 0 {
-                    glyph_index = glyph_index.wrapping_add_signed(delta);
-                }
-
-                Ok::<u16, FontError>(glyph_index)
-            })?;
+            return Some(codepoint.wrapping_add_signed(delta));
+        }
+
+        // The offset is relative to this segment's entry in the offsets table. Indices outside
+        // of the glyph index array map to the missing glyph.
+        let glyph_index = ((offset / 2) as usize + (codepoint - start) as usize + i).checked_sub(self.end_codes.len())
+            .and_then(|index| self.glyph_ids.get(index))
+            .copied()
+            .unwrap_or(0);
+
+        match glyph_index {
+            0 => Some(0),
+            glyph_index => Some(glyph_index.wrapping_add_signed(delta))
         }
     }
 
-    Ok(char_map)
+    pub fn ranges(&self) -> Vec<(u32, u32)> {
+        self.start_codes.iter().zip(&self.end_codes)
+            .filter(|&(start, end)| start <= end)
+            .map(|(&start, &end)| (start as u32, end as u32))
+            .collect()
+    }
 }
@@ -0,0 +1,118 @@
+use encoding_rs::{Encoding, BIG5, EUC_KR, GBK, SHIFT_JIS};
+
+/// Characters 0x80 to 0xFF of the Mac Roman encoding
+const MAC_ROMAN: [char; 128] = [
+    '\u{00C4}', '\u{00C5}', '\u{00C7}', '\u{00C9}', '\u{00D1}', '\u{00D6}', '\u{00DC}', '\u{00E1}', '\u{00E0}', '\u{00E2}', '\u{00E4}', '\u{00E3}', '\u{00E5}', '\u{00E7}', '\u{00E9}', '\u{00E8}',
+    '\u{00EA}', '\u{00EB}', '\u{00ED}', '\u{00EC}', '\u{00EE}', '\u{00EF}', '\u{00F1}', '\u{00F3}', '\u{00F2}', '\u{00F4}', '\u{00F6}', '\u{00F5}', '\u{00FA}', '\u{00F9}', '\u{00FB}', '\u{00FC}',
+    '\u{2020}', '\u{00B0}', '\u{00A2}', '\u{00A3}', '\u{00A7}', '\u{2022}', '\u{00B6}', '\u{00DF}', '\u{00AE}', '\u{00A9}', '\u{2122}', '\u{00B4}', '\u{00A8}', '\u{2260}', '\u{00C6}', '\u{00D8}',
+    '\u{221E}', '\u{00B1}', '\u{2264}', '\u{2265}', '\u{00A5}', '\u{00B5}', '\u{2202}', '\u{2211}', '\u{220F}', '\u{03C0}', '\u{222B}', '\u{00AA}', '\u{00BA}', '\u{03A9}', '\u{00E6}', '\u{00F8}',
+    '\u{00BF}', '\u{00A1}', '\u{00AC}', '\u{221A}', '\u{0192}', '\u{2248}', '\u{2206}', '\u{00AB}', '\u{00BB}', '\u{2026}', '\u{00A0}', '\u{00C0}', '\u{00C3}', '\u{00D5}', '\u{0152}', '\u{0153}',
+    '\u{2013}', '\u{2014}', '\u{201C}', '\u{201D}', '\u{2018}', '\u{2019}', '\u{00F7}', '\u{25CA}', '\u{00FF}', '\u{0178}', '\u{2044}', '\u{20AC}', '\u{2039}', '\u{203A}', '\u{FB01}', '\u{FB02}',
+    '\u{2021}', '\u{00B7}', '\u{201A}', '\u{201E}', '\u{2030}', '\u{00C2}', '\u{00CA}', '\u{00C1}', '\u{00CB}', '\u{00C8}', '\u{00CD}', '\u{00CE}', '\u{00CF}', '\u{00CC}', '\u{00D3}', '\u{00D4}',
+    '\u{F8FF}', '\u{00D2}', '\u{00DA}', '\u{00DB}', '\u{00D9}', '\u{0131}', '\u{02C6}', '\u{02DC}', '\u{00AF}', '\u{02D8}', '\u{02D9}', '\u{02DA}', '\u{00B8}', '\u{02DD}', '\u{02DB}', '\u{02C7}'
+];
+
+/// Start of the private use area symbol fonts store their single byte codes in
+const SYMBOL_BASE: u32 = 0xF000;
+
+/// The encoding of the character codes in a character map subtable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharacterEncoding {
+    Unicode,
+
+    /// Windows symbol fonts, mapping byte `b` at code U+F000 + `b`
+    Symbol,
+    MacRoman,
+
+    ShiftJis,
+    Gbk,
+    Big5,
+    Wansung
+}
+
+impl CharacterEncoding {
+    /// Returns the encoding of an encoding record, or `None` if it isn't supported
+    pub fn new(platform_id: u16, encoding_id: u16) -> Option<CharacterEncoding> {
+        match (platform_id, encoding_id) {
+            (0, _) | (3, 1) | (3, 10)   => Some(CharacterEncoding::Unicode),
+            (3, 0)                      => Some(CharacterEncoding::Symbol),
+            (1, 0)                      => Some(CharacterEncoding::MacRoman),
+            (3, 2)                      => Some(CharacterEncoding::ShiftJis),
+            (3, 3)                      => Some(CharacterEncoding::Gbk),
+            (3, 4)                      => Some(CharacterEncoding::Big5),
+            (3, 5)                      => Some(CharacterEncoding::Wansung),
+            _                           => None
+        }
+    }
+
+    fn legacy_encoding(self) -> Option<&'static Encoding> {
+        match self {
+            CharacterEncoding::ShiftJis => Some(SHIFT_JIS),
+            CharacterEncoding::Gbk      => Some(GBK),
+            CharacterEncoding::Big5     => Some(BIG5),
+            CharacterEncoding::Wansung  => Some(EUC_KR),
+            _                           => None
+        }
+    }
+
+    /// Converts a character to its code in this encoding. Multibyte codes are returned
+    /// with their lead byte in the high byte, as format 2 subtables expect them.
+    pub fn encode(self, character: char) -> Option<u32> {
+        let codepoint = character as u32;
+
+        match self {
+            CharacterEncoding::Unicode => Some(codepoint),
+            CharacterEncoding::Symbol => match codepoint {
+                0..=0xFF => Some(SYMBOL_BASE + codepoint),
+                0xF000..=0xF0FF => Some(codepoint),
+                _ => None
+            },
+            CharacterEncoding::MacRoman => match codepoint {
+                0..=0x7F => Some(codepoint),
+                _ => MAC_ROMAN.iter().position(|&mac_character| mac_character == character).map(|index| 0x80 + index as u32)
+            },
+            _ => {
+                let encoding = self.legacy_encoding()?;
+                let mut buffer = [0u8; 4];
+                let (bytes, _, had_errors) = encoding.encode(character.encode_utf8(&mut buffer));
+
+                match (had_errors, &bytes[..]) {
+                    (false, &[byte]) => Some(byte as u32),
+                    (false, &[lead, trail]) => Some((lead as u32) << 8 | trail as u32),
+                    _ => None
+                }
+            }
+        }
+    }
+
+    /// Converts a code of this encoding back to a character
+    pub fn decode(self, code: u32) -> Option<char> {
+        match self {
+            CharacterEncoding::Unicode => char::from_u32(code),
+            CharacterEncoding::Symbol => match code {
+                0xF000..=0xF0FF => char::from_u32(code - SYMBOL_BASE),
+                _ => None
+            },
+            CharacterEncoding::MacRoman => match code {
+                0..=0x7F => char::from_u32(code),
+                0x80..=0xFF => Some(MAC_ROMAN[code as usize - 0x80]),
+                _ => None
+            },
+            _ => {
+                let bytes = match code {
+                    0..=0xFF => vec![code as u8],
+                    0x100..=0xFFFF => vec![(code >> 8) as u8, code as u8],
+                    _ => return None
+                };
+
+                let decoded = self.legacy_encoding()?.decode_without_bom_handling_and_without_replacement(&bytes)?;
+                let mut characters = decoded.chars();
+
+                match (characters.next(), characters.next()) {
+                    (Some(character), None) => Some(character),
+                    _ => None
+                }
+            }
+        }
+    }
+}
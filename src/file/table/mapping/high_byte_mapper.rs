@@ -0,0 +1,99 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use log::debug;
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::Result};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct SubtableHeader {
+    _length:        u16,
+    _language:      u16
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct SubHeader {
+    first_code:         u16,
+    entry_count:        u16,
+    id_delta:           i16,
+    id_range_offset:    u16
+}
+
+/// CMAP table format 2, mapping mixed one and two byte character codes. The character
+/// codes are those of the subtable's (usually CJK) encoding, not Unicode.
+#[derive(Debug, Clone)]
+pub struct HighByteMapping {
+    /// Index of the sub-header used for every high byte
+    sub_header_keys: Vec<u16>,
+    /// Sub-headers and the glyph index range each one points to
+    sub_headers: Vec<(SubHeader, Vec<u16>)>
+}
+
+impl HighByteMapping {
+    /// Implements decoding for CMAP table format 2
+    pub fn load<S>(stream: &mut S) -> Result<HighByteMapping>
+        where S: Read + Seek
+    {
+        debug!("loading a high byte mapping char map at 0x{:08x}", stream.stream_position()?);
+
+        let _: SubtableHeader = deserialize_from(stream)?;
+        let sub_header_keys: Vec<u16> = deserialize_vec_from::<u16, _>(256, stream)?.into_iter()
+            .map(|key| key / 8)
+            .collect();
+
+        let num_sub_headers = sub_header_keys.iter().max().copied().unwrap_or(0) as usize + 1;
+        let sub_headers_start = stream.stream_position()?;
+        let headers: Vec<SubHeader> = deserialize_vec_from(num_sub_headers, stream)?;
+
+        let mut sub_headers = Vec::with_capacity(headers.len());
+        for (index, header) in headers.into_iter().enumerate() {
+            // The range offset is relative to the position of the id_range_offset field itself
+            let range_start = sub_headers_start + index as u64 * 8 + 6 + header.id_range_offset as u64;
+            stream.seek(SeekFrom::Start(range_start))?;
+
+            sub_headers.push((header, deserialize_vec_from(header.entry_count as usize, stream)?));
+        }
+
+        Ok(HighByteMapping {
+            sub_header_keys,
+            sub_headers
+        })
+    }
+
+    pub fn map(&self, codepoint: u32) -> Option<u16> {
+        let (index, low_byte) = match codepoint {
+            // Single byte codes only exist where the byte doesn't start a two byte code
+            0..=0xFF if self.sub_header_keys[codepoint as usize] == 0 => (0, codepoint as u16),
+            0x100..=0xFFFF => match self.sub_header_keys[(codepoint >> 8) as usize] {
+                0 => return None,
+                index => (index as usize, (codepoint & 0xFF) as u16)
+            },
+            _ => return None
+        };
+
+        let (header, glyph_ids) = &self.sub_headers[index];
+        let glyph_index = *glyph_ids.get(low_byte.checked_sub(header.first_code)? as usize)?;
+
+        match glyph_index {
+            0 => Some(0),
+            _ => Some(glyph_index.wrapping_add_signed(header.id_delta))
+        }
+    }
+
+    pub fn ranges(&self) -> Vec<(u32, u32)> {
+        self.sub_header_keys.iter().enumerate()
+            .filter(|&(high_byte, &index)| high_byte == 0 || index != 0)
+            .flat_map(|(high_byte, &index)| {
+                let (header, _) = &self.sub_headers[index as usize];
+                let first = header.first_code as u32;
+                let last = (first + header.entry_count as u32).saturating_sub(1);
+
+                match index {
+                    0 => vec![(first, last.min(0xFF))],
+                    _ => vec![((high_byte as u32) << 8 | first, (high_byte as u32) << 8 | last.min(0xFF))]
+                }
+            })
+            .filter(|&(first, last)| first <= last)
+            .collect()
+    }
+}
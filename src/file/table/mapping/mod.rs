@@ -1,26 +1,67 @@
+mod byte_mapper;
+mod high_byte_mapper;
 mod delta_mapper;
+mod trimmed_mapper;
+mod segmented_mapper;
+mod variation_sequences;
+mod reverse;
+mod encoding;
 
-use std::{collections::HashMap, io::{Read, Seek}};
+pub use variation_sequences::{VariationGlyph, VariationSequences};
+pub use reverse::{GlyphCharacters, ReverseCharacterMap};
+pub use encoding::CharacterEncoding;
+
+use byte_mapper::ByteMapping;
+use high_byte_mapper::HighByteMapping;
+use delta_mapper::DeltaMapping;
+use trimmed_mapper::TrimmedMapping;
+use segmented_mapper::SegmentedMapping;
+
+use std::io::{Read, Seek};
 
 use log::debug;
 use serde::Deserialize;
 
-use crate::file::{self, table::Table, error::Result, loader::TableDirectoryEntry};
+use crate::file::{self, table::Table, error::{FontError, Result}, loader::TableDirectoryEntry};
+
+/// The encodings tried when selecting a character map, from most to least preferred.
+/// Full repertoire Unicode comes first, followed by BMP-only Unicode, symbol fonts and
+/// finally Mac Roman. Encodings `CharacterEncoding` doesn't support are never selected.
+pub const DEFAULT_ENCODING_PRIORITY: &[(u16, u16)] = &[
+    (3, 10),
+    (0, 6),
+    (0, 4),
+    (3, 1),
+    (0, 3),
+    (0, 2),
+    (0, 1),
+    (0, 0),
+    (3, 0),
+    (1, 0)
+];
 
-#[derive(Debug, Deserialize, Copy, Clone, Default)]
-struct EncodingRecord {
-    platform_id: u16,
-    encoding_id: u16,
-    subtable_offset: u32
+#[derive(Debug, Deserialize, Copy, Clone, Default, PartialEq, Eq)]
+pub struct EncodingRecord {
+    pub platform_id: u16,
+    pub encoding_id: u16,
+    pub subtable_offset: u32
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct CmapHeader {
-    version: u16,
-    num_tables: u16,
+    pub version: u16,
+    pub num_tables: u16,
+
+    #[serde(skip)]
+    pub encoding_records: Vec<EncodingRecord>,
 
+    /// Offset of the cmap table in the font file
     #[serde(skip)]
-    encoding_records: Vec<EncodingRecord>
+    pub offset: u32,
+
+    /// Length of the cmap table
+    #[serde(skip)]
+    pub length: u32
 }
 
 impl CmapHeader {
@@ -31,6 +72,8 @@ impl CmapHeader {
         stream.seek(std::io::SeekFrom::Start(dict_entry.offset as u64))?;
 
         let mut header: CmapHeader = file::deserialize_from(stream)?;
+        header.offset = dict_entry.offset;
+        header.length = dict_entry.length;
 
         header.encoding_records = vec![];
         for _ in 0..header.num_tables {
@@ -41,38 +84,499 @@ impl CmapHeader {
 
         Ok(header)
     }
+
+    /// Returns the first encoding record matching an entry of `priority`, a list of
+    /// (platform id, encoding id) pairs ordered from most to least preferred. Entries with
+    /// an unsupported encoding are skipped.
+    pub fn select_encoding(&self, priority: &[(u16, u16)]) -> Option<EncodingRecord> {
+        priority.iter()
+            .filter(|&&(platform_id, encoding_id)| CharacterEncoding::new(platform_id, encoding_id).is_some())
+            .find_map(|&(platform_id, encoding_id)| self.encoding_records.iter()
+                .find(|record| record.platform_id == platform_id && record.encoding_id == encoding_id))
+            .copied()
+    }
+
+    /// Returns the format of the subtable an encoding record points to
+    pub fn subtable_format<S>(&self, record: &EncodingRecord, stream: &mut S) -> Result<u16>
+        where S: Read + Seek
+    {
+        stream.seek(std::io::SeekFrom::Start(self.offset as u64 + record.subtable_offset as u64))?;
+        Ok(file::deserialize_from(stream)?)
+    }
+
+    /// Loads the subtable an encoding record points to. Format 14 subtables don't map single
+    /// characters and have to be loaded as `VariationSequences` instead.
+    pub fn load_subtable<S>(&self, record: &EncodingRecord, stream: &mut S) -> Result<CharacterMap>
+        where S: Read + Seek
+    {
+        debug!("loading character map for platform {} encoding {}", record.platform_id, record.encoding_id);
+
+        let Some(encoding) = CharacterEncoding::new(record.platform_id, record.encoding_id) else {
+            return Err(FontError::FontFormatError(
+                Some(self.offset + record.subtable_offset),
+                format!("Unsupported encoding {} of platform {}", record.encoding_id, record.platform_id)
+            ));
+        };
+
+        let format = self.subtable_format(record, stream)?;
+        let subtable = match format {
+            0 => Subtable::Byte(ByteMapping::load(stream)?),
+            2 => Subtable::HighByte(HighByteMapping::load(stream)?),
+            4 => Subtable::Delta(DeltaMapping::load(stream, self.offset as u64 + self.length as u64)?),
+            6 => Subtable::Trimmed(TrimmedMapping::load(stream)?),
+            10 => Subtable::Trimmed(TrimmedMapping::load_wide(stream)?),
+            12 => Subtable::Segmented(SegmentedMapping::load(stream, false)?),
+            13 => Subtable::Segmented(SegmentedMapping::load(stream, true)?),
+            format => return Err(FontError::FontFormatError(
+                Some(self.offset + record.subtable_offset),
+                format!("Unsupported character map format {}", format)
+            ))
+        };
+
+        Ok(CharacterMap::new(format, encoding, subtable))
+    }
+}
+
+impl Table for CmapHeader {
+    type UserArgsType = ();
+
+    fn get_table_name() -> &'static str {
+        "Character To Glyph Mapping Header"
+    }
+
+    fn load_impl<S>(dict_entry: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
+        where S: Read + Seek
+    {
+        CmapHeader::load(dict_entry, stream)
+    }
 }
 
-// TODO: Really I shouldn't parse every code point into a hash map...
-// rather i sohuld return a handle to the file segment and perform a binary
-// search as inteded
-pub type CharacterMap = HashMap<char, u16>;
+#[derive(Debug, Clone)]
+enum Subtable {
+    Byte(ByteMapping),
+    HighByte(HighByteMapping),
+    Delta(DeltaMapping),
+    Trimmed(TrimmedMapping),
+    Segmented(SegmentedMapping)
+}
+
+/// A single character map subtable. Only the subtable's arrays are kept in memory,
+/// characters are looked up in them on demand.
+#[derive(Debug, Clone)]
+pub struct CharacterMap {
+    format: u16,
+    encoding: CharacterEncoding,
+    subtable: Subtable
+}
+
+impl CharacterMap {
+    fn new(format: u16, encoding: CharacterEncoding, subtable: Subtable) -> CharacterMap {
+        CharacterMap {
+            format,
+            encoding,
+            subtable
+        }
+    }
+
+    /// Returns the format of the subtable this map was loaded from
+    pub fn format(&self) -> u16 {
+        self.format
+    }
+
+    /// Returns the encoding of the subtable's character codes
+    pub fn encoding(&self) -> CharacterEncoding {
+        self.encoding
+    }
+
+    /// Returns the glyph index a character code of the subtable's encoding is mapped to.
+    /// Characters mapped to the missing glyph (index 0) return `None`.
+    pub fn map_code(&self, codepoint: u32) -> Option<u16> {
+        let glyph_index = match &self.subtable {
+            Subtable::Byte(mapping)         => mapping.map(codepoint),
+            Subtable::HighByte(mapping)     => mapping.map(codepoint),
+            Subtable::Delta(mapping)        => mapping.map(codepoint),
+            Subtable::Trimmed(mapping)      => mapping.map(codepoint),
+            Subtable::Segmented(mapping)    => mapping.map(codepoint)
+        };
+
+        glyph_index.filter(|&glyph_index| glyph_index != 0)
+    }
+
+    /// Returns the glyph index a character is mapped to, if any. The character is converted
+    /// to the subtable's encoding first.
+    pub fn map(&self, character: char) -> Option<u16> {
+        self.map_code(self.encoding.encode(character)?)
+    }
+
+    /// Returns the ranges (first, last) of character codes the subtable covers. Single
+    /// codes within a range may still be unmapped.
+    pub fn ranges(&self) -> Vec<(u32, u32)> {
+        match &self.subtable {
+            Subtable::Byte(mapping)         => mapping.ranges(),
+            Subtable::HighByte(mapping)     => mapping.ranges(),
+            Subtable::Delta(mapping)        => mapping.ranges(),
+            Subtable::Trimmed(mapping)      => mapping.ranges(),
+            Subtable::Segmented(mapping)    => mapping.ranges()
+        }
+    }
+
+    /// Iterates over all mapped characters and their glyph indices. Character codes are
+    /// converted from the subtable's encoding, codes without a character are skipped.
+    pub fn iter(&self) -> impl Iterator<Item = (char, u16)> + '_ {
+        self.ranges().into_iter()
+            .flat_map(|(first, last)| first..=last)
+            .filter_map(|code| Some((self.encoding.decode(code)?, self.map_code(code)?)))
+    }
+}
 
 impl Table for CharacterMap {
-    type UserArgsType = ();
+    /// The encodings to try, see `DEFAULT_ENCODING_PRIORITY`
+    type UserArgsType = &'static [(u16, u16)];
 
     fn get_table_name() -> &'static str {
         "Character To Glyph Mapping"
     }
 
-    fn load_impl<S>(dict_entry: TableDirectoryEntry, stream: &mut S, user_data: Self::UserArgsType) -> Result<Self>
+    fn load_impl<S>(dict_entry: TableDirectoryEntry, stream: &mut S, priority: Self::UserArgsType) -> Result<Self>
         where S: Read + Seek
     {
-        let table_offset = dict_entry.offset as u64;
         let header = CmapHeader::load(dict_entry, stream)?;
 
-        // For now only Unicode 2.0 BMP is supported
-        let Some(result) = 
-            header.encoding_records.iter()
-            .find(|item| item.platform_id == 0 && item.encoding_id == 3) 
-        else {
-            todo!();
+        let Some(record) = header.select_encoding(priority) else {
+            return Err(FontError::FontFormatError(
+                Some(dict_entry.offset),
+                "Character map has none of the requested encodings".into()
+            ));
         };
 
-        stream.seek(std::io::SeekFrom::Start(table_offset + result.subtable_offset as u64))?;
-        match file::deserialize_from::<u16, _>(stream)? {
-            4 => delta_mapper::load(stream),
-            _ => todo!()
+        header.load_subtable(&record, stream)
+    }
+}
+
+impl Table for VariationSequences {
+    type UserArgsType = ();
+
+    fn get_table_name() -> &'static str {
+        "Unicode Variation Sequences"
+    }
+
+    fn load_impl<S>(dict_entry: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
+        where S: Read + Seek
+    {
+        let header = CmapHeader::load(dict_entry, stream)?;
+
+        // Variation sequences are optional, a font without them simply has none
+        let Some(record) = header.select_encoding(&[(0, 5)]) else {
+            debug!("character map has no unicode variation sequences");
+            return Ok(VariationSequences::default());
+        };
+
+        match header.subtable_format(&record, stream)? {
+            14 => VariationSequences::load(stream),
+            format => Err(FontError::FontFormatError(
+                Some(header.offset + record.subtable_offset),
+                format!("Expected a format 14 subtable for variation sequences, found format {}", format)
+            ))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::file::loader::TableDirectoryEntry;
+
+    use super::{CharacterEncoding, CharacterMap, CmapHeader, ReverseCharacterMap, VariationGlyph, VariationSequences};
+
+    fn be16(values: &[u16]) -> Vec<u8> {
+        values.iter().flat_map(|value| value.to_be_bytes()).collect()
+    }
+
+    fn be32(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|value| value.to_be_bytes()).collect()
+    }
+
+    /// Wraps a subtable in a cmap table with a single encoding record
+    fn cmap(platform_id: u16, encoding_id: u16, subtable: &[u8]) -> (CmapHeader, Cursor<Vec<u8>>) {
+        let mut data = be16(&[0, 1, platform_id, encoding_id]);
+        data.extend(be32(&[12]));
+        data.extend(subtable);
+
+        let entry = TableDirectoryEntry { tag: 0x636D6170, checksum: 0, offset: 0, length: data.len() as u32 };
+        let mut stream = Cursor::new(data);
+        let header = CmapHeader::load(entry, &mut stream).unwrap();
+
+        (header, stream)
+    }
+
+    fn load(platform_id: u16, encoding_id: u16, subtable: &[u8]) -> CharacterMap {
+        let (header, mut stream) = cmap(platform_id, encoding_id, subtable);
+        header.load_subtable(&header.encoding_records[0], &mut stream).unwrap()
+    }
+
+    #[test]
+    fn format_0() {
+        let mut glyph_ids = vec![0u8; 256];
+        glyph_ids[0x41] = 5;
+        glyph_ids[0xFF] = 6;
+
+        let mut subtable = be16(&[0, 262, 0]);
+        subtable.extend(glyph_ids);
+        let mapping = load(1, 0, &subtable);
+
+        assert_eq!(mapping.format(), 0);
+        assert_eq!(mapping.map_code(0x41), Some(5));
+        assert_eq!(mapping.map_code(0xFF), Some(6));
+        assert_eq!(mapping.map_code(0x42), None);
+        assert_eq!(mapping.map_code(0x141), None);
+    }
+
+    #[test]
+    fn format_2() {
+        // High byte 0x81 starts two byte codes using sub-header 1, all other bytes are single
+        // byte codes using sub-header 0
+        let mut keys = vec![0u16; 256];
+        keys[0x81] = 8;
+
+        let mut subtable = be16(&[2, 544, 0]);
+        subtable.extend(be16(&keys));
+        // Sub-header 0 at 518: codes 0x20-0x22, glyph array at 534
+        subtable.extend(be16(&[0x20, 3, 0, 10]));
+        // Sub-header 1 at 526: low bytes 0x40-0x41, delta 5, glyph array at 540
+        subtable.extend(be16(&[0x40, 2, 5, 8]));
+        subtable.extend(be16(&[10, 11, 0]));
+        subtable.extend(be16(&[20, 21]));
+        let mapping = load(3, 2, &subtable);
+
+        assert_eq!(mapping.map_code(0x20), Some(10));
+        assert_eq!(mapping.map_code(0x21), Some(11));
+        assert_eq!(mapping.map_code(0x22), None);
+        assert_eq!(mapping.map_code(0x23), None);
+        // A lead byte on its own isn't a character
+        assert_eq!(mapping.map_code(0x81), None);
+
+        assert_eq!(mapping.map_code(0x8140), Some(25));
+        assert_eq!(mapping.map_code(0x8141), Some(26));
+        assert_eq!(mapping.map_code(0x8142), None);
+        assert_eq!(mapping.map_code(0x8240), None);
+
+        assert_eq!(mapping.ranges(), vec![(0x20, 0x22), (0x8140, 0x8141)]);
+
+        // Shift JIS 0x8140 and 0x8141 are the ideographic space and comma
+        assert_eq!(mapping.map(' '), Some(10));
+        assert_eq!(mapping.map('\u{3000}'), Some(25));
+        assert_eq!(mapping.map('\u{3001}'), Some(26));
+        assert_eq!(mapping.map('\u{8140}'), None);
+    }
+
+    #[test]
+    fn symbol_characters_are_offset_into_the_private_use_area() {
+        let mut subtable = be16(&[6, 14, 0, 0xF041, 2]);
+        subtable.extend(be16(&[7, 8]));
+        let mapping = load(3, 0, &subtable);
+
+        assert_eq!(mapping.encoding(), CharacterEncoding::Symbol);
+        assert_eq!(mapping.map('A'), Some(7));
+        assert_eq!(mapping.map('B'), Some(8));
+        assert_eq!(mapping.map('\u{F041}'), Some(7));
+        assert_eq!(mapping.map('C'), None);
+        assert_eq!(mapping.map('\u{0141}'), None);
+    }
+
+    #[test]
+    fn mac_roman_characters_are_converted() {
+        let mut glyph_ids = vec![0u8; 256];
+        glyph_ids[0x41] = 3;
+        glyph_ids[0x8A] = 9;
+
+        let mut subtable = be16(&[0, 262, 0]);
+        subtable.extend(glyph_ids);
+        let mapping = load(1, 0, &subtable);
+
+        assert_eq!(mapping.map('A'), Some(3));
+        assert_eq!(mapping.map('\u{E4}'), Some(9));
+        assert_eq!(mapping.map('\u{8A}'), None);
+    }
+
+    #[test]
+    fn reverse_map_converts_codes_to_characters() {
+        let mut glyph_ids = vec![0u8; 256];
+        glyph_ids[0x41] = 3;
+        glyph_ids[0x8A] = 9;
+
+        let mut subtable = be16(&[0, 262, 0]);
+        subtable.extend(glyph_ids);
+        let mapping = load(1, 0, &subtable);
+        let reverse = ReverseCharacterMap::new(&mapping, &VariationSequences::default(), 10);
+
+        assert_eq!(reverse.codepoints(3), &['A']);
+        assert_eq!(reverse.codepoints(9), &['\u{E4}']);
+    }
+
+    #[test]
+    fn reverse_map_skips_unconvertible_codes() {
+        let mut subtable = be16(&[6, 14, 0, 0xF041, 2]);
+        subtable.extend(be16(&[7, 8]));
+        let symbol = load(3, 0, &subtable);
+        let reverse = ReverseCharacterMap::new(&symbol, &VariationSequences::default(), 10);
+
+        assert_eq!(reverse.codepoints(7), &['A']);
+        assert_eq!(reverse.codepoints(8), &['B']);
+
+        // Symbol codes outside of U+F000 to U+F0FF have no byte they stand for
+        let mut subtable = be16(&[6, 14, 0, 0x41, 2]);
+        subtable.extend(be16(&[7, 8]));
+        let symbol = load(3, 0, &subtable);
+        let reverse = ReverseCharacterMap::new(&symbol, &VariationSequences::default(), 10);
+
+        assert!(reverse.codepoints(7).is_empty());
+        assert!(reverse.get(8).unwrap().unreachable);
+    }
+
+    #[test]
+    fn unsupported_encodings_are_not_selected() {
+        let subtable = be16(&[6, 12, 0, 0x20, 1, 5]);
+        let (header, mut stream) = cmap(3, 6, &subtable);
+
+        assert!(header.select_encoding(&[(3, 6)]).is_none());
+        assert!(header.select_encoding(super::DEFAULT_ENCODING_PRIORITY).is_none());
+        assert!(header.load_subtable(&header.encoding_records[0], &mut stream).is_err());
+    }
+
+    #[test]
+    fn format_4() {
+        let mut subtable = be16(&[4, 0, 0, 6, 0, 0, 0]);
+        subtable.extend(be16(&[0x43, 0x62, 0xFFFF, 0]));
+        subtable.extend(be16(&[0x41, 0x61, 0xFFFF]));
+        subtable.extend(be16(&[0, (-0x5F_i16) as u16, 1]));
+        subtable.extend(be16(&[6, 0, 0]));
+        subtable.extend(be16(&[5, 0, 7]));
+        let mapping = load(3, 1, &subtable);
+
+        assert_eq!(mapping.map_code(0x40), None);
+        assert_eq!(mapping.map_code(0x41), Some(5));
+        assert_eq!(mapping.map_code(0x42), None);
+        assert_eq!(mapping.map_code(0x43), Some(7));
+        assert_eq!(mapping.map_code(0x61), Some(2));
+        assert_eq!(mapping.map_code(0x62), Some(3));
+        assert_eq!(mapping.map_code(0xFFFF), None);
+    }
+
+    #[test]
+    fn format_4_range_offset_past_the_table() {
+        let mut subtable = be16(&[4, 0, 0, 6, 0, 0, 0]);
+        subtable.extend(be16(&[0x43, 0x101, 0xFFFF, 0]));
+        subtable.extend(be16(&[0x41, 0x100, 0xFFFF]));
+        subtable.extend(be16(&[0, 0, 1]));
+        subtable.extend(be16(&[6, 0x8000, 0]));
+        subtable.extend(be16(&[5, 6, 7]));
+        let mapping = load(3, 1, &subtable);
+
+        assert_eq!(mapping.map_code(0x41), Some(5));
+        assert_eq!(mapping.map_code(0x43), Some(7));
+        assert_eq!(mapping.map_code(0x100), None);
+        assert_eq!(mapping.map_code(0x101), None);
+    }
+
+    #[test]
+    fn format_6() {
+        let mut subtable = be16(&[6, 16, 0, 0x30, 3]);
+        subtable.extend(be16(&[7, 8, 9]));
+        let mapping = load(3, 1, &subtable);
+
+        assert_eq!(mapping.map_code(0x2F), None);
+        assert_eq!(mapping.map_code(0x30), Some(7));
+        assert_eq!(mapping.map_code(0x32), Some(9));
+        assert_eq!(mapping.map_code(0x33), None);
+    }
+
+    #[test]
+    fn format_10() {
+        let mut subtable = be16(&[10, 0]);
+        subtable.extend(be32(&[24, 0, 0x10000, 2]));
+        subtable.extend(be16(&[3, 4]));
+        let mapping = load(3, 10, &subtable);
+
+        assert_eq!(mapping.map_code(0xFFFF), None);
+        assert_eq!(mapping.map_code(0x10001), Some(4));
+        assert_eq!(mapping.map('\u{10000}'), Some(3));
+        assert_eq!(mapping.ranges(), vec![(0x10000, 0x10001)]);
+    }
+
+    #[test]
+    fn format_10_range_past_the_last_code() {
+        let mut subtable = be16(&[10, 0]);
+        subtable.extend(be32(&[24, 0, 0xFFFFFFFF, 2]));
+        subtable.extend(be16(&[3, 4]));
+        let mapping = load(3, 10, &subtable);
+
+        assert_eq!(mapping.map_code(0xFFFFFFFF), Some(3));
+        assert_eq!(mapping.ranges(), vec![(0xFFFFFFFF, 0xFFFFFFFF)]);
+    }
+
+    #[test]
+    fn format_12() {
+        let mut subtable = be16(&[12, 0]);
+        subtable.extend(be32(&[40, 0, 2, 0x41, 0x43, 10, 0x1F600, 0x1F601, 50]));
+        let mapping = load(3, 10, &subtable);
+
+        assert_eq!(mapping.map_code(0x40), None);
+        assert_eq!(mapping.map_code(0x42), Some(11));
+        assert_eq!(mapping.map_code(0x44), None);
+        assert_eq!(mapping.map('\u{1F601}'), Some(51));
+    }
+
+    #[test]
+    fn format_12_glyph_ids_past_the_limit() {
+        let mut subtable = be16(&[12, 0]);
+        subtable.extend(be32(&[40, 0, 2, 0x41, 0x42, 0xFFFF, 0x100, 0x101, 0xFFFFFFFF]));
+        let mapping = load(3, 10, &subtable);
+
+        assert_eq!(mapping.map_code(0x41), Some(0xFFFF));
+        assert_eq!(mapping.map_code(0x42), None);
+        assert_eq!(mapping.map_code(0x101), None);
+    }
+
+    #[test]
+    fn format_13() {
+        let mut subtable = be16(&[13, 0]);
+        subtable.extend(be32(&[28, 0, 1, 0x4E00, 0x4E05, 7]));
+        let mapping = load(3, 10, &subtable);
+
+        assert_eq!(mapping.map_code(0x4E00), Some(7));
+        assert_eq!(mapping.map_code(0x4E05), Some(7));
+        assert_eq!(mapping.map_code(0x4E06), None);
+    }
+
+    #[test]
+    fn format_14() {
+        let mut subtable = be16(&[14]);
+        subtable.extend(be32(&[58, 2]));
+        // FE0E only has a non-default mapping at 49
+        subtable.extend([0x00, 0xFE, 0x0E]);
+        subtable.extend(be32(&[0, 49]));
+        // FE0F has a default range at 32 and a non-default mapping at 40
+        subtable.extend([0x00, 0xFE, 0x0F]);
+        subtable.extend(be32(&[32, 40]));
+        subtable.extend(be32(&[1]));
+        subtable.extend([0x00, 0x27, 0x64, 1]);
+        subtable.extend(be32(&[1]));
+        subtable.extend([0x00, 0x26, 0x3A, 0x00, 42]);
+        subtable.extend(be32(&[1]));
+        subtable.extend([0x00, 0x27, 0x64, 0x00, 43]);
+
+        let (header, mut stream) = cmap(0, 5, &subtable);
+        assert_eq!(header.subtable_format(&header.encoding_records[0], &mut stream).unwrap(), 14);
+        let sequences = VariationSequences::load(&mut stream).unwrap();
+
+        assert_eq!(sequences.lookup('\u{2764}', '\u{FE0F}'), Some(VariationGlyph::Default));
+        assert_eq!(sequences.lookup('\u{2765}', '\u{FE0F}'), Some(VariationGlyph::Default));
+        assert_eq!(sequences.lookup('\u{2766}', '\u{FE0F}'), None);
+        assert_eq!(sequences.lookup('\u{263A}', '\u{FE0F}'), Some(VariationGlyph::Glyph(42)));
+        assert_eq!(sequences.lookup('\u{2764}', '\u{FE0E}'), Some(VariationGlyph::Glyph(43)));
+        assert_eq!(sequences.lookup('\u{263A}', '\u{FE0E}'), None);
+        assert_eq!(sequences.lookup('\u{2764}', '\u{FE00}'), None);
+        assert_eq!(sequences.iter().count(), 4);
+    }
+}
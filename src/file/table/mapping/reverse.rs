@@ -0,0 +1,76 @@
+use log::warn;
+
+use super::{CharacterMap, VariationGlyph, VariationSequences};
+
+/// All characters that map to a single glyph
+#[derive(Debug, Clone, Default)]
+pub struct GlyphCharacters {
+    pub codepoints: Vec<char>,
+    /// Variation sequences as (base character, variation selector)
+    pub variation_sequences: Vec<(char, char)>,
+    /// Set if neither a character nor a variation sequence maps to the glyph
+    pub unreachable: bool
+}
+
+/// Maps glyph indices back to the characters that reach them
+#[derive(Debug, Clone)]
+pub struct ReverseCharacterMap {
+    glyphs: Vec<GlyphCharacters>
+}
+
+impl ReverseCharacterMap {
+    pub fn new(mapping: &CharacterMap, variations: &VariationSequences, num_glyphs: u16) -> ReverseCharacterMap {
+        let mut glyphs = vec![GlyphCharacters::default(); num_glyphs as usize];
+
+        for (character, glyph_index) in mapping.iter() {
+            let Some(glyph) = glyphs.get_mut(glyph_index as usize) else {
+                warn!("Character {:?} maps to glyph {}, but the font only has {} glyphs", character, glyph_index, num_glyphs);
+                continue;
+            };
+
+            glyph.codepoints.push(character);
+        }
+
+        for (base, selector, variation) in variations.iter() {
+            let glyph_index = match variation {
+                VariationGlyph::Glyph(glyph_index) => glyph_index,
+                VariationGlyph::Default => match mapping.map(base) {
+                    Some(glyph_index) => glyph_index,
+                    None => continue
+                }
+            };
+
+            let Some(glyph) = glyphs.get_mut(glyph_index as usize) else {
+                warn!("Variation sequence {:?} {:?} maps to glyph {}, but the font only has {} glyphs", base, selector, glyph_index, num_glyphs);
+                continue;
+            };
+
+            glyph.variation_sequences.push((base, selector));
+        }
+
+        for glyph in &mut glyphs {
+            glyph.unreachable = glyph.codepoints.is_empty() && glyph.variation_sequences.is_empty();
+        }
+
+        ReverseCharacterMap {
+            glyphs
+        }
+    }
+
+    /// Returns the characters mapping to a glyph, or `None` if the glyph index is out of range
+    pub fn get(&self, glyph_index: u16) -> Option<&GlyphCharacters> {
+        self.glyphs.get(glyph_index as usize)
+    }
+
+    /// Returns the characters (without variation sequences) mapping to a glyph
+    pub fn codepoints(&self, glyph_index: u16) -> &[char] {
+        self.get(glyph_index).map_or(&[], |glyph| &glyph.codepoints)
+    }
+
+    /// Iterates over the indices of all glyphs no character maps to
+    pub fn unreachable_glyphs(&self) -> impl Iterator<Item = u16> + '_ {
+        self.glyphs.iter().enumerate()
+            .filter(|(_, glyph)| glyph.unreachable)
+            .map(|(glyph_index, _)| glyph_index as u16)
+    }
+}
@@ -0,0 +1,82 @@
+use std::io::{Read, Seek};
+
+use log::{debug, warn};
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::Result};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct SubtableHeader {
+    _reserved:      u16,
+    _length:        u32,
+    _language:      u32,
+    num_groups:     u32
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct SequentialMapGroup {
+    start_char_code:    u32,
+    end_char_code:      u32,
+    start_glyph_id:     u32
+}
+
+/// CMAP table formats 12 (segmented coverage) and 13 (many-to-one range mappings)
+#[derive(Debug, Clone)]
+pub struct SegmentedMapping {
+    /// Groups sorted by character code
+    groups: Vec<SequentialMapGroup>,
+    many_to_one: bool
+}
+
+impl SegmentedMapping {
+    /// Implements decoding for CMAP table formats 12 and 13
+    pub fn load<S>(stream: &mut S, many_to_one: bool) -> Result<SegmentedMapping>
+        where S: Read + Seek
+    {
+        debug!("loading a segmented char map at 0x{:08x}", stream.stream_position()?);
+
+        let header: SubtableHeader = deserialize_from(stream)?;
+        let mut groups: Vec<SequentialMapGroup> = deserialize_vec_from(header.num_groups as usize, stream)?;
+
+        debug!("groups found: ");
+        groups.retain(|group| {
+            debug!("    + {:06X}-{:06X}\tstart glyph={}", group.start_char_code, group.end_char_code, group.start_glyph_id);
+
+            let valid = group.start_char_code <= group.end_char_code;
+            if !valid {
+                warn!("Skipping invalid group {:06X}-{:06X}", group.start_char_code, group.end_char_code);
+            }
+
+            valid
+        });
+
+        if !groups.is_sorted_by_key(|group| group.start_char_code) {
+            warn!("Character map groups are not sorted by character code");
+            groups.sort_by_key(|group| group.start_char_code);
+        }
+
+        Ok(SegmentedMapping {
+            groups,
+            many_to_one
+        })
+    }
+
+    pub fn map(&self, codepoint: u32) -> Option<u16> {
+        let group = self.groups.get(self.groups.partition_point(|group| group.end_char_code < codepoint))?;
+        if codepoint < group.start_char_code {
+            return None;
+        }
+
+        match self.many_to_one {
+            true => Some(group.start_glyph_id as u16),
+            false => group.start_glyph_id.checked_add(codepoint - group.start_char_code)
+                .and_then(|glyph_id| u16::try_from(glyph_id).ok())
+        }
+    }
+
+    pub fn ranges(&self) -> Vec<(u32, u32)> {
+        self.groups.iter()
+            .map(|group| (group.start_char_code, group.end_char_code))
+            .collect()
+    }
+}
@@ -0,0 +1,71 @@
+use std::io::{Read, Seek};
+
+use log::debug;
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::Result};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct SubtableHeader {
+    _length:        u16,
+    _language:      u16,
+    first_code:     u16,
+    entry_count:    u16
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct WideSubtableHeader {
+    _reserved:      u16,
+    _length:        u32,
+    _language:      u32,
+    first_code:     u32,
+    entry_count:    u32
+}
+
+/// CMAP table formats 6 and 10, mapping a single dense range of character codes
+#[derive(Debug, Clone)]
+pub struct TrimmedMapping {
+    first_code: u32,
+    glyph_ids: Vec<u16>
+}
+
+impl TrimmedMapping {
+    /// Implements decoding for CMAP table format 6
+    pub fn load<S>(stream: &mut S) -> Result<TrimmedMapping>
+        where S: Read + Seek
+    {
+        debug!("loading a trimmed table char map at 0x{:08x}", stream.stream_position()?);
+
+        let header: SubtableHeader = deserialize_from(stream)?;
+
+        Ok(TrimmedMapping {
+            first_code: header.first_code as u32,
+            glyph_ids: deserialize_vec_from(header.entry_count as usize, stream)?
+        })
+    }
+
+    /// Implements decoding for CMAP table format 10
+    pub fn load_wide<S>(stream: &mut S) -> Result<TrimmedMapping>
+        where S: Read + Seek
+    {
+        debug!("loading a trimmed array char map at 0x{:08x}", stream.stream_position()?);
+
+        let header: WideSubtableHeader = deserialize_from(stream)?;
+
+        Ok(TrimmedMapping {
+            first_code: header.first_code,
+            glyph_ids: deserialize_vec_from(header.entry_count as usize, stream)?
+        })
+    }
+
+    pub fn map(&self, codepoint: u32) -> Option<u16> {
+        self.glyph_ids.get(codepoint.checked_sub(self.first_code)? as usize).copied()
+    }
+
+    pub fn ranges(&self) -> Vec<(u32, u32)> {
+        match self.glyph_ids.len() {
+            0 => vec![],
+            len => vec![(self.first_code, self.first_code.saturating_add((len - 1) as u32))]
+        }
+    }
+}
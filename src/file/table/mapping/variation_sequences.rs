@@ -0,0 +1,137 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use log::debug;
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, error::Result};
+
+/// The glyph a variation sequence maps to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariationGlyph {
+    /// The sequence uses the glyph the base character is mapped to in the regular character map
+    Default,
+    Glyph(u16)
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct VariationSelectorRecord {
+    var_selector:           [u8; 3],
+    default_uvs_offset:     u32,
+    non_default_uvs_offset: u32
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct UnicodeRange {
+    start_unicode_value:    [u8; 3],
+    additional_count:       u8
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct UvsMapping {
+    unicode_value:          [u8; 3],
+    glyph_id:               u16
+}
+
+fn u24(bytes: [u8; 3]) -> u32 {
+    u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]])
+}
+
+#[derive(Debug, Clone, Default)]
+struct SelectorMappings {
+    /// Ranges (start, end) of base characters that use their default glyph
+    default_ranges: Vec<(u32, u32)>,
+    /// Base characters mapped to specific glyphs, sorted by character
+    mappings: Vec<(u32, u16)>
+}
+
+/// Unicode Variation Sequences (CMAP table format 14), mapping pairs of a base character
+/// and a variation selector to glyphs
+#[derive(Debug, Clone, Default)]
+pub struct VariationSequences {
+    /// Mappings for every variation selector, sorted by selector
+    selectors: Vec<(u32, SelectorMappings)>
+}
+
+impl VariationSequences {
+    /// Implements decoding for CMAP table format 14. The stream must be positioned
+    /// right after the format field.
+    pub fn load<S>(stream: &mut S) -> Result<VariationSequences>
+        where S: Read + Seek
+    {
+        let subtable_start = stream.stream_position()? - 2;
+        debug!("loading unicode variation sequences at 0x{:08x}", subtable_start);
+
+        let _length: u32 = deserialize_from(stream)?;
+        let num_records: u32 = deserialize_from(stream)?;
+
+        let mut records: Vec<VariationSelectorRecord> = Vec::with_capacity(num_records as usize);
+        for _ in 0..num_records {
+            records.push(deserialize_from(stream)?);
+        }
+
+        let mut selectors: Vec<(u32, SelectorMappings)> = Vec::with_capacity(records.len());
+        for record in records {
+            let mut mappings = SelectorMappings::default();
+
+            if record.default_uvs_offset != 0 {
+                stream.seek(SeekFrom::Start(subtable_start + record.default_uvs_offset as u64))?;
+                let num_ranges: u32 = deserialize_from(stream)?;
+                for _ in 0..num_ranges {
+                    let range: UnicodeRange = deserialize_from(stream)?;
+                    let start = u24(range.start_unicode_value);
+                    mappings.default_ranges.push((start, start + range.additional_count as u32));
+                }
+            }
+
+            if record.non_default_uvs_offset != 0 {
+                stream.seek(SeekFrom::Start(subtable_start + record.non_default_uvs_offset as u64))?;
+                let num_mappings: u32 = deserialize_from(stream)?;
+                for _ in 0..num_mappings {
+                    let mapping: UvsMapping = deserialize_from(stream)?;
+                    mappings.mappings.push((u24(mapping.unicode_value), mapping.glyph_id));
+                }
+            }
+
+            mappings.default_ranges.sort_unstable();
+            mappings.mappings.sort_unstable();
+            selectors.push((u24(record.var_selector), mappings));
+        }
+
+        selectors.sort_unstable_by_key(|&(selector, _)| selector);
+        debug!("found {} variation selectors", selectors.len());
+
+        Ok(VariationSequences {
+            selectors
+        })
+    }
+
+    /// Looks up the glyph for a base character followed by a variation selector
+    pub fn lookup(&self, base: char, selector: char) -> Option<VariationGlyph> {
+        let (base, selector) = (base as u32, selector as u32);
+
+        let index = self.selectors.binary_search_by_key(&selector, |&(selector, _)| selector).ok()?;
+        let mappings = &self.selectors[index].1;
+
+        if let Ok(index) = mappings.mappings.binary_search_by_key(&base, |&(character, _)| character) {
+            return Some(VariationGlyph::Glyph(mappings.mappings[index].1));
+        }
+
+        let range = mappings.default_ranges.partition_point(|&(start, _)| start <= base).checked_sub(1)?;
+        (base <= mappings.default_ranges[range].1).then_some(VariationGlyph::Default)
+    }
+
+    /// Iterates over all sequences as (base character, variation selector, glyph)
+    pub fn iter(&self) -> impl Iterator<Item = (char, char, VariationGlyph)> + '_ {
+        self.selectors.iter().flat_map(|(selector, mappings)| {
+            let selector = char::from_u32(*selector);
+
+            let defaults = mappings.default_ranges.iter()
+                .flat_map(|&(start, end)| start..=end)
+                .map(|base| (base, VariationGlyph::Default));
+            let glyphs = mappings.mappings.iter()
+                .map(|&(base, glyph_index)| (base, VariationGlyph::Glyph(glyph_index)));
+
+            defaults.chain(glyphs).filter_map(move |(base, glyph)| Some((char::from_u32(base)?, selector?, glyph)))
+        })
+    }
+}
@@ -1,6 +1,6 @@
 use std::io::{Read, Seek};
 
-use log::{debug, error};
+use log::debug;
 use serde::Deserialize;
 
 use crate::file::{deserialize_from, error::{Result, FontError}, loader::TableDirectoryEntry};
@@ -36,6 +36,15 @@ pub enum MaximumProfile {
     Version10(MaxpV10)
 }
 
+impl MaximumProfile {
+    pub fn num_glyphs(&self) -> u16 {
+        match self {
+            MaximumProfile::Version05(table) => table.num_glyphs,
+            MaximumProfile::Version10(table) => table.num_glyphs
+        }
+    }
+}
+
 impl Table for MaximumProfile {
     type UserArgsType = ();
 
@@ -43,7 +52,7 @@ impl Table for MaximumProfile {
         "Maximum Profile"
     }
 
-    fn load_impl<S>(entry: TableDirectoryEntry, stream: &mut S, user_data: Self::UserArgsType) -> Result<Self>
+    fn load_impl<S>(entry: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
         where S: Read + Seek
     {
         let version: u32 = deserialize_from(stream)?;
@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 mod table;
 pub use table::Table;
 
@@ -7,11 +8,51 @@ pub use header::FontHeader;
 mod hheader;
 pub use hheader::{Caret, HorizontalHeader, MinSideBearing};
 
+mod hmtx;
+pub use hmtx::{HorizontalMetrics, LongHorMetric};
+
+mod os2;
+pub use os2::{EmbeddingLicense, EmbeddingPermissions, OS2, OS2Base, OS2V0, OS2V1, OS2V2, OS2V5, ScriptMetrics, Strikeout, TypoMetrics};
+
+mod post;
+pub use post::{GlyphNames, PostHeader, PostScriptTable, MACINTOSH_GLYPH_NAMES};
+
+pub mod name;
+pub use name::{NameRecord, NamingTable};
+
+mod vheader;
+pub use vheader::VerticalHeader;
+
+mod vmtx;
+pub use vmtx::{LongVerMetric, VerticalMetrics};
+
+mod vorg;
+pub use vorg::{VertOriginYMetrics, VerticalOrigin};
+
+mod kern;
+pub use kern::{ClassTable, KerningCoverage, KerningData, KerningPair, KerningSubtable, KerningTable};
+
+mod layout;
+pub use layout::{
+    Anchor, AttachList, CaretValue, ClassDef, ClassRangeRecord, ContextRule, Coverage, Device, Direction, Feature, FeatureSetting, GlyphClass,
+    GlyphDefinitionTable, GlyphInfo, GlyphPosition, GlyphPositioningTable, GlyphSubstitutionTable, LangSys, LayoutTable, Ligature, LigatureCaretList,
+    Lookup, LookupSubtable, MarkRecord, PairValueRecord, PositioningSubtable, RangeRecord, Script, SequenceContext, SequenceLookupRecord, ShapedGlyph,
+    SubstitutionSubtable, ValueRecord, DEFAULT_FEATURES, IGNORE_BASE_GLYPHS, IGNORE_LIGATURES, IGNORE_MARKS, MARK_ATTACHMENT_TYPE, RIGHT_TO_LEFT,
+    USE_MARK_FILTERING_SET, X_ADVANCE, X_ADVANCE_DEVICE, X_PLACEMENT, X_PLACEMENT_DEVICE, Y_ADVANCE, Y_ADVANCE_DEVICE, Y_PLACEMENT,
+    Y_PLACEMENT_DEVICE
+};
+
 mod maxp;
 pub use maxp::{MaximumProfile, MaxpV05, MaxpV10};
 
+mod cff;
+pub use cff::{Cff2Header, CffHeader, CharString, Charset, CompactFontFormat, CompactFontFormat2, Dict, Encoding, FdSelect, Index, PathCommand, PrivateData, Seac};
+
+mod variations;
+pub use variations::{ItemVariationData, ItemVariationStore, RegionAxisCoordinates, VariationRegion};
+
 mod mapping;
-pub use mapping::{CmapHeader, CharacterMap};
+pub use mapping::{CmapHeader, CharacterEncoding, CharacterMap, EncodingRecord, GlyphCharacters, ReverseCharacterMap, DEFAULT_ENCODING_PRIORITY, VariationGlyph, VariationSequences};
 
 mod truetype;
-pub use truetype::{Locations, Glyph, Glyphs, GlyphDescription, SimpleGlyph, CompositeGlyph};
+pub use truetype::{Component, ComponentPlacement, ComponentTransform, Contour, ContourPoint, Locations, Glyph, Glyphs, GlyphHeader, GlyphDescription, SimpleGlyph, CompositeGlyph};
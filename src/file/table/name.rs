@@ -0,0 +1,344 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use log::{debug, warn};
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::{FontError, Result}, loader::TableDirectoryEntry};
+
+use super::{table::Table, CharacterEncoding};
+
+pub const COPYRIGHT:                u16 = 0;
+pub const FAMILY:                   u16 = 1;
+pub const SUBFAMILY:                u16 = 2;
+pub const UNIQUE_ID:                u16 = 3;
+pub const FULL_NAME:                u16 = 4;
+pub const VERSION:                  u16 = 5;
+pub const POSTSCRIPT_NAME:          u16 = 6;
+pub const LICENSE:                  u16 = 13;
+pub const LICENSE_URL:              u16 = 14;
+pub const TYPOGRAPHIC_FAMILY:       u16 = 16;
+pub const TYPOGRAPHIC_SUBFAMILY:    u16 = 17;
+
+fn decode_mac_roman(data: &[u8]) -> String {
+    data.iter()
+        .filter_map(|&byte| CharacterEncoding::MacRoman.decode(byte as u32))
+        .collect()
+}
+
+fn decode_utf16(data: &[u8]) -> Option<String> {
+    if !data.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let units = data.chunks_exact(2).map(|unit| u16::from_be_bytes([unit[0], unit[1]]));
+    char::decode_utf16(units).collect::<std::result::Result<String, _>>().ok()
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct NameHeader {
+    version:        u16,
+    count:          u16,
+    storage_offset: u16
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct RawNameRecord {
+    platform_id:    u16,
+    encoding_id:    u16,
+    language_id:    u16,
+    name_id:        u16,
+    length:         u16,
+    string_offset:  u16
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct LangTagRecord {
+    length:         u16,
+    lang_tag_offset: u16
+}
+
+#[derive(Debug, Clone)]
+pub struct NameRecord {
+    pub platform_id:    u16,
+    pub encoding_id:    u16,
+    pub language_id:    u16,
+    pub name_id:        u16,
+
+    /// The decoded string, `None` if its encoding isn't supported
+    pub value:          Option<String>
+}
+
+impl NameRecord {
+    fn decode(platform_id: u16, encoding_id: u16, data: &[u8]) -> Option<String> {
+        match (platform_id, encoding_id) {
+            (0, _) | (3, _) | (2, 1)    => decode_utf16(data),
+            (1, 0)                      => Some(decode_mac_roman(data)),
+            (2, 0) | (2, 2)             => Some(data.iter().map(|&byte| byte as char).collect()),
+            _                           => None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NamingTable {
+    pub version:        u16,
+    pub records:        Vec<NameRecord>,
+
+    /// Language tags of format 1 tables, referenced by language IDs starting at 0x8000
+    pub language_tags:  Vec<Option<String>>
+}
+
+impl NamingTable {
+    /// Returns the language tag (e.g. "en-US") a language ID 0x8000 and up refers to
+    pub fn language_tag(&self, language_id: u16) -> Option<&str> {
+        self.language_tags.get(language_id.checked_sub(0x8000)? as usize)?.as_deref()
+    }
+
+    /// Returns all decoded strings with the given name ID
+    pub fn names(&self, name_id: u16) -> impl Iterator<Item = &NameRecord> {
+        self.records.iter().filter(move |record| record.name_id == name_id && record.value.is_some())
+    }
+
+    /// Returns the string for a name ID, preferring English Windows names, then any
+    /// Unicode name, then English Mac names
+    pub fn get(&self, name_id: u16) -> Option<&str> {
+        let rank = |record: &NameRecord| match (record.platform_id, record.language_id) {
+            (3, 0x0409) => 0,
+            (3, _)      => 1,
+            (0, _)      => 2,
+            (1, 0)      => 3,
+            _           => 4
+        };
+
+        self.names(name_id)
+            .min_by_key(|record| rank(record))
+            .and_then(|record| record.value.as_deref())
+    }
+
+    pub fn copyright(&self) -> Option<&str> {
+        self.get(COPYRIGHT)
+    }
+
+    pub fn family(&self) -> Option<&str> {
+        self.get(FAMILY)
+    }
+
+    pub fn subfamily(&self) -> Option<&str> {
+        self.get(SUBFAMILY)
+    }
+
+    pub fn full_name(&self) -> Option<&str> {
+        self.get(FULL_NAME)
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.get(VERSION)
+    }
+
+    pub fn postscript_name(&self) -> Option<&str> {
+        self.get(POSTSCRIPT_NAME)
+    }
+
+    pub fn license(&self) -> Option<&str> {
+        self.get(LICENSE)
+    }
+
+    pub fn typographic_family(&self) -> Option<&str> {
+        self.get(TYPOGRAPHIC_FAMILY)
+    }
+
+    pub fn typographic_subfamily(&self) -> Option<&str> {
+        self.get(TYPOGRAPHIC_SUBFAMILY)
+    }
+
+    /// Returns the typographic family if present, as it groups more styles than the legacy family
+    pub fn preferred_family(&self) -> Option<&str> {
+        self.typographic_family().or_else(|| self.family())
+    }
+
+    /// Returns the typographic subfamily if present, otherwise the legacy subfamily
+    pub fn preferred_subfamily(&self) -> Option<&str> {
+        self.typographic_subfamily().or_else(|| self.subfamily())
+    }
+}
+
+fn read_string<S>(stream: &mut S, entry: &TableDirectoryEntry, storage: u64, offset: u16, length: u16) -> Result<Option<Vec<u8>>>
+    where S: Read + Seek
+{
+    let start = storage + offset as u64;
+    if start + length as u64 > entry.length as u64 {
+        warn!("Name string at offset {} ({} bytes) lies outside of the naming table", start, length);
+        return Ok(None);
+    }
+
+    stream.seek(SeekFrom::Start(entry.offset as u64 + start))?;
+    Ok(Some(deserialize_vec_from(length as usize, stream)?))
+}
+
+impl Table for NamingTable {
+    type UserArgsType = ();
+
+    fn get_table_name() -> &'static str {
+        "Naming Table"
+    }
+
+    fn load_impl<S>(entry: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
+        where S: Read + Seek
+    {
+        let header: NameHeader = deserialize_from(stream)?;
+        if header.version > 1 {
+            return Err(FontError::FontFormatError(
+                Some(entry.offset),
+                format!("naming table has unknown version: {}", header.version)
+            ));
+        }
+
+        let raw_records: Vec<RawNameRecord> = deserialize_vec_from(header.count as usize, stream)?;
+        let lang_tag_records: Vec<LangTagRecord> = match header.version {
+            1 => {
+                let count: u16 = deserialize_from(stream)?;
+                deserialize_vec_from(count as usize, stream)?
+            },
+            _ => vec![]
+        };
+
+        let storage = header.storage_offset as u64;
+
+        let mut records = Vec::with_capacity(raw_records.len());
+        for record in raw_records {
+            let value = read_string(stream, &entry, storage, record.string_offset, record.length)?
+                .and_then(|data| NameRecord::decode(record.platform_id, record.encoding_id, &data));
+
+            if value.is_none() {
+                warn!("Could not decode name {} for platform {} encoding {}", record.name_id, record.platform_id, record.encoding_id);
+            }
+
+            records.push(NameRecord {
+                platform_id: record.platform_id,
+                encoding_id: record.encoding_id,
+                language_id: record.language_id,
+                name_id: record.name_id,
+                value
+            });
+        }
+
+        let mut language_tags = Vec::with_capacity(lang_tag_records.len());
+        for record in lang_tag_records {
+            language_tags.push(read_string(stream, &entry, storage, record.lang_tag_offset, record.length)?
+                .and_then(|data| decode_utf16(&data)));
+        }
+
+        let table = NamingTable {
+            version: header.version,
+            records,
+            language_tags
+        };
+        debug!("{:?}", table);
+
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::file::{loader::TableDirectoryEntry, table::Table};
+
+    use super::{NamingTable, FAMILY, FULL_NAME};
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(|unit| unit.to_be_bytes()).collect()
+    }
+
+    /// Builds a naming table from (platform, encoding, language, name ID, string) records,
+    /// adding a language tag list if `tags` is given
+    fn load(records: &[(u16, u16, u16, u16, &[u8])], tags: Option<&[&[u8]]>) -> NamingTable {
+        let mut header = vec![];
+        let mut storage = vec![];
+        let mut push_string = |header: &mut Vec<u16>, data: &[u8]| {
+            header.extend([data.len() as u16, storage.len() as u16]);
+            storage.extend_from_slice(data);
+        };
+
+        for &(platform_id, encoding_id, language_id, name_id, data) in records {
+            header.extend([platform_id, encoding_id, language_id, name_id]);
+            push_string(&mut header, data);
+        }
+
+        if let Some(tags) = tags {
+            header.push(tags.len() as u16);
+            for tag in tags {
+                push_string(&mut header, tag);
+            }
+        }
+
+        let storage_offset = 6 + 2 * header.len() as u16;
+        let mut data: Vec<u8> = [tags.is_some() as u16, records.len() as u16, storage_offset].into_iter()
+            .chain(header)
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+        data.extend(storage);
+
+        let entry = TableDirectoryEntry { tag: 0x6E616D65, checksum: 0, offset: 0, length: data.len() as u32 };
+        NamingTable::load(entry, &mut Cursor::new(data), ()).unwrap()
+    }
+
+    #[test]
+    fn decodes_utf16_and_mac_roman() {
+        let name = load(&[
+            (3, 1, 0x0409, FAMILY, &utf16("Caf\u{e9} \u{1F600}")),
+            (1, 0, 0, FULL_NAME, b"Caf\x8E"),
+            (3, 1, 0x0409, FULL_NAME, &[0x00, 0x41, 0x00])
+        ], None);
+
+        assert_eq!(name.records[0].value.as_deref(), Some("Café 😀"));
+        assert_eq!(name.records[1].value.as_deref(), Some("Café"));
+
+        // Odd length UTF-16 strings can't be decoded, so the Mac name is used instead
+        assert_eq!(name.records[2].value, None);
+        assert_eq!(name.full_name(), Some("Café"));
+    }
+
+    #[test]
+    fn unsupported_encodings_are_not_decoded() {
+        let name = load(&[(1, 1, 0, FAMILY, b"\x82\xa0")], None);
+        assert_eq!(name.records[0].value, None);
+        assert_eq!(name.family(), None);
+    }
+
+    #[test]
+    fn language_tags_are_referenced_from_0x8000() {
+        let name = load(&[(0, 4, 0x8001, FAMILY, &utf16("Schrift"))], Some(&[&utf16("en"), &utf16("de-CH")]));
+
+        assert_eq!(name.version, 1);
+        assert_eq!(name.language_tag(0x8000), Some("en"));
+        assert_eq!(name.language_tag(name.records[0].language_id), Some("de-CH"));
+        assert_eq!(name.language_tag(0x8002), None);
+        assert_eq!(name.language_tag(0x0409), None);
+    }
+
+    #[test]
+    fn get_prefers_english_windows_names() {
+        let mac = (1, 0, 0, FAMILY, &b"Mac"[..]);
+        let unicode = (0, 3, 0, FAMILY, &utf16("Unicode")[..]);
+        let windows = (3, 1, 0x0407, FAMILY, &utf16("Windows")[..]);
+        let english = (3, 1, 0x0409, FAMILY, &utf16("English")[..]);
+
+        assert_eq!(load(&[mac, unicode, windows, english], None).family(), Some("English"));
+        assert_eq!(load(&[mac, unicode, windows], None).family(), Some("Windows"));
+        assert_eq!(load(&[mac, unicode], None).family(), Some("Unicode"));
+        assert_eq!(load(&[(1, 0, 2, FAMILY, b"Deutsch"), mac], None).family(), Some("Mac"));
+        assert_eq!(load(&[mac], None).full_name(), None);
+    }
+
+    #[test]
+    fn strings_outside_of_the_table_are_skipped() {
+        let data: Vec<u8> = [0, 1, 18, 3, 1, 0x0409, FAMILY, 100, 0].iter().flat_map(|value: &u16| value.to_be_bytes()).collect();
+        let entry = TableDirectoryEntry { tag: 0x6E616D65, checksum: 0, offset: 0, length: data.len() as u32 };
+        let name = NamingTable::load(entry, &mut Cursor::new(data), ()).unwrap();
+
+        assert_eq!(name.records[0].value, None);
+        assert_eq!(name.family(), None);
+    }
+}
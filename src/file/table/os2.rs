@@ -0,0 +1,333 @@
+use std::io::{Read, Seek};
+
+use log::debug;
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, error::{FontError, Result}, loader::TableDirectoryEntry};
+
+use super::table::Table;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ScriptMetrics {
+    pub x_size:     i16,
+    pub y_size:     i16,
+    pub x_offset:   i16,
+    pub y_offset:   i16
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Strikeout {
+    pub size:       i16,
+    pub position:   i16
+}
+
+/// The fields shared by all versions, which is all a truncated version 0 table contains
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct OS2Base {
+    pub x_avg_char_width:       i16,
+    pub weight_class:           u16,
+    pub width_class:            u16,
+    pub fs_type:                u16,
+    pub subscript:              ScriptMetrics,
+    pub superscript:            ScriptMetrics,
+    pub strikeout:              Strikeout,
+    pub family_class:           i16,
+    pub panose:                 [u8; 10],
+    pub unicode_range:          [u32; 4],
+    pub vendor_id:              [u8; 4],
+    pub fs_selection:           u16,
+    pub first_char_index:       u16,
+    pub last_char_index:        u16
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct TypoMetrics {
+    pub typo_ascender:          i16,
+    pub typo_descender:         i16,
+    pub typo_line_gap:          i16,
+    pub win_ascent:             u16,
+    pub win_descent:            u16
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OS2V0 {
+    pub base:                   OS2Base,
+
+    /// Missing from the truncated tables of some old Apple fonts
+    pub metrics:                Option<TypoMetrics>
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct OS2V1 {
+    pub base:                   OS2Base,
+    pub metrics:                TypoMetrics,
+    pub code_page_range:        [u32; 2]
+}
+
+/// Versions 2, 3 and 4 share the same layout
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct OS2V2 {
+    pub base:                   OS2Base,
+    pub metrics:                TypoMetrics,
+    pub code_page_range:        [u32; 2],
+    pub x_height:               i16,
+    pub cap_height:             i16,
+    pub default_char:           u16,
+    pub break_char:             u16,
+    pub max_context:            u16
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct OS2V5 {
+    pub base:                   OS2Base,
+    pub metrics:                TypoMetrics,
+    pub code_page_range:        [u32; 2],
+    pub x_height:               i16,
+    pub cap_height:             i16,
+    pub default_char:           u16,
+    pub break_char:             u16,
+    pub max_context:            u16,
+    pub lower_optical_point_size: u16,
+    pub upper_optical_point_size: u16
+}
+
+/// How a font may be embedded in documents, decoded from `fs_type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingLicense {
+    Installable,
+    Restricted,
+    PreviewAndPrint,
+    Editable
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbeddingPermissions {
+    pub license: EmbeddingLicense,
+    pub no_subsetting: bool,
+    pub bitmap_only: bool
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum OS2 {
+    Version0(OS2V0),
+    Version1(OS2V1),
+    Version2(OS2V2),
+    Version3(OS2V2),
+    Version4(OS2V2),
+    Version5(OS2V5)
+}
+
+impl OS2 {
+    pub fn version(&self) -> u16 {
+        match self {
+            OS2::Version0(_) => 0,
+            OS2::Version1(_) => 1,
+            OS2::Version2(_) => 2,
+            OS2::Version3(_) => 3,
+            OS2::Version4(_) => 4,
+            OS2::Version5(_) => 5
+        }
+    }
+
+    pub fn base(&self) -> &OS2Base {
+        match self {
+            OS2::Version0(table) => &table.base,
+            OS2::Version1(table) => &table.base,
+            OS2::Version2(table) | OS2::Version3(table) | OS2::Version4(table) => &table.base,
+            OS2::Version5(table) => &table.base
+        }
+    }
+
+    pub fn weight_class(&self) -> u16 {
+        self.base().weight_class
+    }
+
+    pub fn width_class(&self) -> u16 {
+        self.base().width_class
+    }
+
+    pub fn panose(&self) -> [u8; 10] {
+        self.base().panose
+    }
+
+    pub fn unicode_range(&self) -> [u32; 4] {
+        self.base().unicode_range
+    }
+
+    pub fn embedding_permissions(&self) -> EmbeddingPermissions {
+        let fs_type = self.base().fs_type;
+
+        // Fonts may set several of the exclusive license bits, the least restrictive one wins
+        let license = if fs_type & 0x0008 != 0 {
+            EmbeddingLicense::Editable
+        } else if fs_type & 0x0004 != 0 {
+            EmbeddingLicense::PreviewAndPrint
+        } else if fs_type & 0x0002 != 0 {
+            EmbeddingLicense::Restricted
+        } else {
+            EmbeddingLicense::Installable
+        };
+
+        EmbeddingPermissions {
+            license,
+            no_subsetting: fs_type & 0x0100 != 0,
+            bitmap_only: fs_type & 0x0200 != 0
+        }
+    }
+
+    pub fn typo_metrics(&self) -> Option<&TypoMetrics> {
+        match self {
+            OS2::Version0(table) => table.metrics.as_ref(),
+            OS2::Version1(table) => Some(&table.metrics),
+            OS2::Version2(table) | OS2::Version3(table) | OS2::Version4(table) => Some(&table.metrics),
+            OS2::Version5(table) => Some(&table.metrics)
+        }
+    }
+
+    pub fn code_page_range(&self) -> Option<[u32; 2]> {
+        match self {
+            OS2::Version0(_) => None,
+            OS2::Version1(table) => Some(table.code_page_range),
+            OS2::Version2(table) | OS2::Version3(table) | OS2::Version4(table) => Some(table.code_page_range),
+            OS2::Version5(table) => Some(table.code_page_range)
+        }
+    }
+
+    pub fn x_height(&self) -> Option<i16> {
+        match self {
+            OS2::Version0(_) | OS2::Version1(_) => None,
+            OS2::Version2(table) | OS2::Version3(table) | OS2::Version4(table) => Some(table.x_height),
+            OS2::Version5(table) => Some(table.x_height)
+        }
+    }
+
+    pub fn cap_height(&self) -> Option<i16> {
+        match self {
+            OS2::Version0(_) | OS2::Version1(_) => None,
+            OS2::Version2(table) | OS2::Version3(table) | OS2::Version4(table) => Some(table.cap_height),
+            OS2::Version5(table) => Some(table.cap_height)
+        }
+    }
+
+    /// Returns the range of point sizes (lower inclusive, upper exclusive) the font is designed for
+    pub fn optical_size(&self) -> Option<(f32, f32)> {
+        match self {
+            OS2::Version5(table) => Some((
+                table.lower_optical_point_size as f32 / 20.0,
+                table.upper_optical_point_size as f32 / 20.0
+            )),
+            _ => None
+        }
+    }
+}
+
+impl Table for OS2 {
+    type UserArgsType = ();
+
+    fn get_table_name() -> &'static str {
+        "OS/2 and Windows Metrics"
+    }
+
+    fn load_impl<S>(entry: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
+        where S: Read + Seek
+    {
+        let version: u16 = deserialize_from(stream)?;
+
+        let required_length = match version {
+            0 => 68,
+            1 => 86,
+            2..=4 => 96,
+            5 => 100,
+            _ => return Err(
+                FontError::FontFormatError(
+                    Some(entry.offset),
+                    format!("OS/2 table has unknown table version: {}", version)
+                )
+            )
+        };
+
+        if entry.length < required_length {
+            return Err(FontError::FontFormatError(
+                Some(entry.offset),
+                format!("OS/2 table version {} needs {} bytes, but is only {} long", version, required_length, entry.length)
+            ));
+        }
+
+        let table = match version {
+            0 => Self::Version0(OS2V0 {
+                base: deserialize_from(stream)?,
+                metrics: match entry.length >= 78 {
+                    true => Some(deserialize_from(stream)?),
+                    false => None
+                }
+            }),
+            1 => Self::Version1(deserialize_from(stream)?),
+            2 => Self::Version2(deserialize_from(stream)?),
+            3 => Self::Version3(deserialize_from(stream)?),
+            4 => Self::Version4(deserialize_from(stream)?),
+            _ => Self::Version5(deserialize_from(stream)?)
+        };
+
+        debug!("{:?}", table);
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::file::{error::Result, loader::TableDirectoryEntry, table::Table};
+
+    use super::{EmbeddingLicense, OS2};
+
+    /// Loads a table of `length` bytes with weight 700, fsType 0x010C, typo ascender 800,
+    /// x-height 500 and an optical size range of 6 to 12 points
+    fn load(version: u16, length: usize) -> Result<OS2> {
+        let mut data = vec![0; length.max(100)];
+        for (offset, value) in [(0, version), (4, 700), (8, 0x010C), (68, 800), (86, 500), (96, 120), (98, 240)] {
+            data[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+        }
+        data.truncate(length);
+
+        let entry = TableDirectoryEntry { tag: 0x4F532F32, checksum: 0, offset: 0, length: length as u32 };
+        OS2::load(entry, &mut Cursor::new(data), ())
+    }
+
+    #[test]
+    fn version_boundaries() {
+        for (version, length) in [(0, 78), (1, 86), (2, 96), (3, 96), (4, 96), (5, 100)] {
+            let os2 = load(version, length).unwrap();
+            assert_eq!(os2.version(), version);
+            assert_eq!(os2.weight_class(), 700);
+            assert_eq!(os2.typo_metrics().map(|metrics| metrics.typo_ascender), Some(800));
+            assert_eq!(os2.code_page_range().is_some(), version >= 1);
+            assert_eq!(os2.x_height(), (version >= 2).then_some(500));
+            assert_eq!(os2.optical_size(), (version == 5).then_some((6.0, 12.0)));
+
+            assert_eq!(load(version, length - 1).is_err(), version > 0);
+        }
+    }
+
+    #[test]
+    fn truncated_version_0_has_no_typo_metrics() {
+        let os2 = load(0, 68).unwrap();
+        assert_eq!(os2.weight_class(), 700);
+        assert!(os2.typo_metrics().is_none());
+
+        assert!(load(0, 77).unwrap().typo_metrics().is_none());
+        assert!(load(0, 67).is_err());
+    }
+
+    #[test]
+    fn unknown_version_is_an_error() {
+        assert!(load(6, 100).is_err());
+    }
+
+    #[test]
+    fn least_restrictive_license_wins() {
+        let permissions = load(4, 96).unwrap().embedding_permissions();
+        assert_eq!(permissions.license, EmbeddingLicense::Editable);
+        assert!(permissions.no_subsetting && !permissions.bitmap_only);
+    }
+}
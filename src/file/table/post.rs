@@ -0,0 +1,247 @@
+use std::io::{Read, Seek};
+
+use log::{debug, warn};
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::{FontError, Result}, loader::TableDirectoryEntry};
+
+use super::table::Table;
+
+/// The 258 glyph names of the standard Macintosh character set, which format 1.0
+/// uses for the first glyphs and formats 2.0 and 2.5 refer to by index
+pub const MACINTOSH_GLYPH_NAMES: [&str; 258] = [
+    ".notdef", ".null", "nonmarkingreturn", "space", "exclam", "quotedbl", "numbersign", "dollar",
+    "percent", "ampersand", "quotesingle", "parenleft", "parenright", "asterisk", "plus", "comma",
+    "hyphen", "period", "slash", "zero", "one", "two", "three", "four",
+    "five", "six", "seven", "eight", "nine", "colon", "semicolon", "less",
+    "equal", "greater", "question", "at", "A", "B", "C", "D",
+    "E", "F", "G", "H", "I", "J", "K", "L",
+    "M", "N", "O", "P", "Q", "R", "S", "T",
+    "U", "V", "W", "X", "Y", "Z", "bracketleft", "backslash",
+    "bracketright", "asciicircum", "underscore", "grave", "a", "b", "c", "d",
+    "e", "f", "g", "h", "i", "j", "k", "l",
+    "m", "n", "o", "p", "q", "r", "s", "t",
+    "u", "v", "w", "x", "y", "z", "braceleft", "bar",
+    "braceright", "asciitilde", "Adieresis", "Aring", "Ccedilla", "Eacute", "Ntilde", "Odieresis",
+    "Udieresis", "aacute", "agrave", "acircumflex", "adieresis", "atilde", "aring", "ccedilla",
+    "eacute", "egrave", "ecircumflex", "edieresis", "iacute", "igrave", "icircumflex", "idieresis",
+    "ntilde", "oacute", "ograve", "ocircumflex", "odieresis", "otilde", "uacute", "ugrave",
+    "ucircumflex", "udieresis", "dagger", "degree", "cent", "sterling", "section", "bullet",
+    "paragraph", "germandbls", "registered", "copyright", "trademark", "acute", "dieresis", "notequal",
+    "AE", "Oslash", "infinity", "plusminus", "lessequal", "greaterequal", "yen", "mu",
+    "partialdiff", "summation", "product", "pi", "integral", "ordfeminine", "ordmasculine", "Omega",
+    "ae", "oslash", "questiondown", "exclamdown", "logicalnot", "radical", "florin", "approxequal",
+    "Delta", "guillemotleft", "guillemotright", "ellipsis", "nonbreakingspace", "Agrave", "Atilde", "Otilde",
+    "OE", "oe", "endash", "emdash", "quotedblleft", "quotedblright", "quoteleft", "quoteright",
+    "divide", "lozenge", "ydieresis", "Ydieresis", "fraction", "currency", "guilsinglleft", "guilsinglright",
+    "fi", "fl", "daggerdbl", "periodcentered", "quotesinglbase", "quotedblbase", "perthousand", "Acircumflex",
+    "Ecircumflex", "Aacute", "Edieresis", "Egrave", "Iacute", "Icircumflex", "Idieresis", "Igrave",
+    "Oacute", "Ocircumflex", "apple", "Ograve", "Uacute", "Ucircumflex", "Ugrave", "dotlessi",
+    "circumflex", "tilde", "macron", "breve", "dotaccent", "ring", "cedilla", "hungarumlaut",
+    "ogonek", "caron", "Lslash", "lslash", "Scaron", "scaron", "Zcaron", "zcaron",
+    "brokenbar", "Eth", "eth", "Yacute", "yacute", "Thorn", "thorn", "minus",
+    "multiply", "onesuperior", "twosuperior", "threesuperior", "onehalf", "onequarter", "threequarters", "franc",
+    "Gbreve", "gbreve", "Idotaccent", "Scedilla", "scedilla", "Cacute", "cacute", "Ccaron",
+    "ccaron", "dcroat"
+];
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct PostHeader {
+    pub version:                u32,
+    pub italic_angle:           i32,
+    pub underline_position:     i16,
+    pub underline_thickness:    i16,
+    pub is_fixed_pitch:         u32,
+    pub min_mem_type42:         u32,
+    pub max_mem_type42:         u32,
+    pub min_mem_type1:          u32,
+    pub max_mem_type1:          u32
+}
+
+#[derive(Debug, Clone)]
+pub enum GlyphNames {
+    /// Format 3.0 (and unsupported formats) store no names
+    None,
+
+    /// Format 1.0, the font uses the standard Macintosh glyph order
+    Standard,
+
+    /// Format 2.0, indices below 258 refer to the standard names, the rest to the string pool
+    Indexed { indices: Vec<u16>, names: Vec<String> },
+
+    /// Format 2.5, offsets of each glyph into the standard glyph order
+    Offsets(Vec<i8>)
+}
+
+#[derive(Debug, Clone)]
+pub struct PostScriptTable {
+    pub header: PostHeader,
+    pub names: GlyphNames
+}
+
+impl PostScriptTable {
+    pub fn version(&self) -> u32 {
+        self.header.version
+    }
+
+    /// Returns the italic angle in degrees counter-clockwise from the vertical
+    pub fn italic_angle(&self) -> f32 {
+        self.header.italic_angle as f32 / 65536.0
+    }
+
+    pub fn underline_position(&self) -> i16 {
+        self.header.underline_position
+    }
+
+    pub fn underline_thickness(&self) -> i16 {
+        self.header.underline_thickness
+    }
+
+    pub fn is_fixed_pitch(&self) -> bool {
+        self.header.is_fixed_pitch != 0
+    }
+
+    pub fn glyph_name(&self, glyph_index: u16) -> Option<&str> {
+        let glyph_index = glyph_index as usize;
+
+        match &self.names {
+            GlyphNames::None => None,
+            GlyphNames::Standard => MACINTOSH_GLYPH_NAMES.get(glyph_index).copied(),
+            GlyphNames::Indexed { indices, names } => match *indices.get(glyph_index)? as usize {
+                index @ 0..=257 => Some(MACINTOSH_GLYPH_NAMES[index]),
+                index => names.get(index - MACINTOSH_GLYPH_NAMES.len()).map(String::as_str)
+            },
+            GlyphNames::Offsets(offsets) => {
+                let index = glyph_index.checked_add_signed(*offsets.get(glyph_index)? as isize)?;
+                MACINTOSH_GLYPH_NAMES.get(index).copied()
+            }
+        }
+    }
+}
+
+impl Table for PostScriptTable {
+    type UserArgsType = ();
+
+    fn get_table_name() -> &'static str {
+        "PostScript"
+    }
+
+    fn load_impl<S>(entry: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
+        where S: Read + Seek
+    {
+        let header: PostHeader = deserialize_from(stream)?;
+        debug!("{:?}", header);
+
+        let names = match header.version {
+            0x00010000 => GlyphNames::Standard,
+            0x00020000 => {
+                let num_glyphs: u16 = deserialize_from(stream)?;
+                let indices: Vec<u16> = deserialize_vec_from(num_glyphs as usize, stream)?;
+
+                // The string pool runs until the end of the table
+                let pool_start = stream.stream_position()? - entry.offset as u64;
+                let pool_length = (entry.length as u64).saturating_sub(pool_start) as usize;
+                let pool: Vec<u8> = deserialize_vec_from(pool_length, stream)?;
+
+                let mut names = vec![];
+                let mut remaining = pool.as_slice();
+                while let Some((&length, rest)) = remaining.split_first() {
+                    if rest.len() < length as usize {
+                        warn!("Glyph name string pool is truncated");
+                        break;
+                    }
+
+                    let (name, rest) = rest.split_at(length as usize);
+                    names.push(String::from_utf8_lossy(name).into_owned());
+                    remaining = rest;
+                }
+
+                debug!("found {} custom glyph names", names.len());
+                GlyphNames::Indexed { indices, names }
+            },
+            0x00025000 => {
+                let num_glyphs: u16 = deserialize_from(stream)?;
+                GlyphNames::Offsets(deserialize_vec_from(num_glyphs as usize, stream)?)
+            },
+            0x00030000 => GlyphNames::None,
+            0x00040000 => {
+                warn!("PostScript table format 4.0 is not supported, glyph names will be unavailable");
+                GlyphNames::None
+            },
+            _ => return Err(
+                FontError::FontFormatError(
+                    Some(entry.offset),
+                    format!("PostScript table has unknown version: {:#08x}", header.version)
+                )
+            )
+        };
+
+        Ok(PostScriptTable {
+            header,
+            names
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::file::{loader::TableDirectoryEntry, table::Table};
+
+    use super::PostScriptTable;
+
+    /// Loads a table with the given version and an italic angle of -12.5 degrees,
+    /// followed by the glyph name data
+    fn load(version: u32, names: &[u8]) -> PostScriptTable {
+        let mut data = version.to_be_bytes().to_vec();
+        data.extend((-12 * 65536 - 32768i32).to_be_bytes());
+        data.extend([0; 24]);
+        data.extend_from_slice(names);
+
+        let entry = TableDirectoryEntry { tag: 0x706F7374, checksum: 0, offset: 0, length: data.len() as u32 };
+        PostScriptTable::load(entry, &mut Cursor::new(data), ()).unwrap()
+    }
+
+    #[test]
+    fn format_1_uses_the_standard_names() {
+        let post = load(0x00010000, &[]);
+
+        assert_eq!(post.italic_angle(), -12.5);
+        assert_eq!(post.glyph_name(0), Some(".notdef"));
+        assert_eq!(post.glyph_name(36), Some("A"));
+        assert_eq!(post.glyph_name(257), Some("dcroat"));
+        assert_eq!(post.glyph_name(258), None);
+    }
+
+    #[test]
+    fn format_2_mixes_standard_and_custom_names() {
+        let post = load(0x00020000, &[
+            0, 5, 0, 0, 1, 3, 1, 2, 0, 36, 1, 4,
+            // the last name is truncated
+            3, b'f', b'_', b'f', 4, b'A', b'.', b's', b's', 9, b'c'
+        ]);
+
+        assert_eq!(post.glyph_name(0), Some(".notdef"));
+        assert_eq!(post.glyph_name(1), Some("A.ss"));
+        assert_eq!(post.glyph_name(2), Some("f_f"));
+        assert_eq!(post.glyph_name(3), Some("A"));
+        assert_eq!(post.glyph_name(4), None);
+        assert_eq!(post.glyph_name(5), None);
+    }
+
+    #[test]
+    fn format_2_5_offsets_into_the_standard_names() {
+        let post = load(0x00025000, &[0, 3, 0, 35, 0xFF]);
+
+        assert_eq!(post.glyph_name(0), Some(".notdef"));
+        assert_eq!(post.glyph_name(1), Some("A"));
+        assert_eq!(post.glyph_name(2), Some(".null"));
+        assert_eq!(post.glyph_name(3), None);
+    }
+
+    #[test]
+    fn format_3_has_no_names() {
+        assert_eq!(load(0x00030000, &[]).glyph_name(0), None);
+    }
+}
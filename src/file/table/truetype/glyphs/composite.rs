@@ -0,0 +1,387 @@
+use std::io::{Read, Seek};
+
+use log::debug;
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::{FontError, Result}, Point};
+
+use super::{Contour, ContourPoint, Glyph, GlyphDescription};
+
+const ARG_1_AND_2_ARE_WORDS:        u16 = 0x0001;
+const ARGS_ARE_XY_VALUES:           u16 = 0x0002;
+const ROUND_XY_TO_GRID:             u16 = 0x0004;
+const WE_HAVE_A_SCALE:              u16 = 0x0008;
+const MORE_COMPONENTS:              u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE:     u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO:         u16 = 0x0080;
+const WE_HAVE_INSTRUCTIONS:         u16 = 0x0100;
+const USE_MY_METRICS:               u16 = 0x0200;
+const OVERLAP_COMPOUND:             u16 = 0x0400;
+const SCALED_COMPONENT_OFFSET:      u16 = 0x0800;
+
+/// Describes where a component is placed relative to the glyph it is part of
+#[derive(Debug, Clone, Copy)]
+pub enum ComponentPlacement {
+    /// The component is moved by a fixed offset
+    Offset { x: i16, y: i16 },
+
+    /// The point `child` of the component is aligned with the point `parent`
+    /// of the outline assembled so far
+    Anchor { parent: u16, child: u16 }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ComponentTransform {
+    Identity,
+    Scale(f32),
+    XYScale { x: f32, y: f32 },
+    TwoByTwo { xx: f32, xy: f32, yx: f32, yy: f32 }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Component {
+    pub glyph_index: u16,
+    pub placement: ComponentPlacement,
+    pub transform: ComponentTransform,
+
+    pub round_xy_to_grid: bool,
+    pub use_my_metrics: bool,
+    pub overlap: bool,
+    pub scaled_offset: bool
+}
+
+#[derive(Debug, Clone)]
+pub struct CompositeGlyph {
+    pub components: Vec<Component>,
+    pub instructions: Vec<u8>
+}
+
+fn read_f2dot14<S>(stream: &mut S) -> Result<f32>
+    where S: Read + Seek
+{
+    Ok(deserialize_from::<i16, _>(stream)? as f32 / 16384.0)
+}
+
+impl ComponentTransform {
+    fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        match *self {
+            ComponentTransform::Identity                    => (x, y),
+            ComponentTransform::Scale(scale)                => (x * scale, y * scale),
+            ComponentTransform::XYScale { x: sx, y: sy }    => (x * sx, y * sy),
+            ComponentTransform::TwoByTwo { xx, xy, yx, yy } => (xx * x + yx * y, xy * x + yy * y)
+        }
+    }
+}
+
+impl Component {
+    fn load<S>(stream: &mut S) -> Result<(Self, u16)>
+        where S: Read + Seek
+    {
+        let flags: u16 = deserialize_from(stream)?;
+        let glyph_index: u16 = deserialize_from(stream)?;
+
+        let placement = match (flags & ARG_1_AND_2_ARE_WORDS != 0, flags & ARGS_ARE_XY_VALUES != 0) {
+            (true, true)    => ComponentPlacement::Offset { x: deserialize_from(stream)?, y: deserialize_from(stream)? },
+            (true, false)   => ComponentPlacement::Anchor { parent: deserialize_from(stream)?, child: deserialize_from(stream)? },
+            (false, true)   => ComponentPlacement::Offset {
+                x: deserialize_from::<i8, _>(stream)? as i16,
+                y: deserialize_from::<i8, _>(stream)? as i16
+            },
+            (false, false)  => ComponentPlacement::Anchor {
+                parent: deserialize_from::<u8, _>(stream)? as u16,
+                child: deserialize_from::<u8, _>(stream)? as u16
+            }
+        };
+
+        let transform = if flags & WE_HAVE_A_SCALE != 0 {
+            ComponentTransform::Scale(read_f2dot14(stream)?)
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            ComponentTransform::XYScale { x: read_f2dot14(stream)?, y: read_f2dot14(stream)? }
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            ComponentTransform::TwoByTwo {
+                xx: read_f2dot14(stream)?,
+                xy: read_f2dot14(stream)?,
+                yx: read_f2dot14(stream)?,
+                yy: read_f2dot14(stream)?
+            }
+        } else {
+            ComponentTransform::Identity
+        };
+
+        Ok((Component {
+            glyph_index,
+            placement,
+            transform,
+
+            round_xy_to_grid: flags & ROUND_XY_TO_GRID != 0,
+            use_my_metrics: flags & USE_MY_METRICS != 0,
+            overlap: flags & OVERLAP_COMPOUND != 0,
+            scaled_offset: flags & SCALED_COMPONENT_OFFSET != 0
+        }, flags))
+    }
+}
+
+impl CompositeGlyph {
+    pub fn load<S>(stream: &mut S) -> Result<Self>
+        where S: Read + Seek
+    {
+        let mut components: Vec<Component> = vec![];
+        let mut has_instructions = false;
+
+        loop {
+            let (component, flags) = Component::load(stream)?;
+            debug!("{:?}", component);
+
+            components.push(component);
+            has_instructions |= flags & WE_HAVE_INSTRUCTIONS != 0;
+
+            if flags & MORE_COMPONENTS == 0 {
+                break;
+            }
+        }
+
+        let instructions: Vec<u8> = if has_instructions {
+            let len_instructions: u16 = deserialize_from(stream)?;
+            deserialize_vec_from(len_instructions as usize, stream)?
+        } else {
+            vec![]
+        };
+        debug!("{} instructions: {:?}", instructions.len(), instructions);
+
+        Ok(CompositeGlyph {
+            components,
+            instructions
+        })
+    }
+
+    /// Returns the index of the component whose metrics should be used for the composite
+    pub fn metrics_glyph(&self) -> Option<u16> {
+        self.components.iter()
+            .find(|component| component.use_my_metrics)
+            .map(|component| component.glyph_index)
+    }
+
+    /// Flattens this composite into a single outline by loading every component through
+    /// `load_glyph` and applying its transform and placement. `glyph_index` is the index of
+    /// this composite, and `max_depth` the maximum nesting level (see `MaxpV10::component_depth`).
+    pub fn resolve<F>(&self, glyph_index: u16, max_depth: u16, mut load_glyph: F) -> Result<Vec<Contour>>
+        where F: FnMut(u16) -> Result<Option<Glyph>>
+    {
+        let mut stack = vec![glyph_index];
+        self.resolve_impl(max_depth, &mut stack, &mut load_glyph)
+    }
+
+    fn resolve_impl<F>(&self, max_depth: u16, stack: &mut Vec<u16>, load_glyph: &mut F) -> Result<Vec<Contour>>
+        where F: FnMut(u16) -> Result<Option<Glyph>>
+    {
+        if stack.len() > max_depth as usize {
+            return Err(FontError::FontFormatError(
+                None,
+                format!("Composite glyph {} exceeds the maximum component depth of {}", stack[0], max_depth)
+            ));
+        }
+
+        let mut contours: Vec<Contour> = vec![];
+
+        for component in &self.components {
+            if stack.contains(&component.glyph_index) {
+                return Err(FontError::FontFormatError(
+                    None,
+                    format!("Composite glyph {} references itself through glyph {}", stack[0], component.glyph_index)
+                ));
+            }
+
+            let component_contours = match load_glyph(component.glyph_index)? {
+                None => vec![],
+                Some(glyph) => match glyph.description {
+                    GlyphDescription::Simple(simple) => simple.contours,
+                    GlyphDescription::Composite(composite) => {
+                        stack.push(component.glyph_index);
+                        let result = composite.resolve_impl(max_depth, stack, load_glyph);
+                        stack.pop();
+
+                        result?
+                    }
+                }
+            };
+
+            let transformed: Vec<Vec<(f32, f32, bool)>> = component_contours.iter()
+                .map(|contour| contour.iter()
+                    .map(|point| {
+                        let (x, y) = component.transform.apply(point.point.x as f32, point.point.y as f32);
+                        (x, y, point.on_curve)
+                    })
+                    .collect())
+                .collect();
+
+            let (dx, dy) = match component.placement {
+                ComponentPlacement::Offset { x, y } if component.scaled_offset => component.transform.apply(x as f32, y as f32),
+                ComponentPlacement::Offset { x, y } => (x as f32, y as f32),
+                ComponentPlacement::Anchor { parent, child } => {
+                    let Some(parent_point) = contours.iter().flatten().nth(parent as usize) else {
+                        return Err(FontError::FontFormatError(
+                            None,
+                            format!("Anchor point {} of composite glyph {} is out of range", parent, stack[0])
+                        ));
+                    };
+
+                    let Some(&(child_x, child_y, _)) = transformed.iter().flatten().nth(child as usize) else {
+                        return Err(FontError::FontFormatError(
+                            None,
+                            format!("Anchor point {} of component {} is out of range", child, component.glyph_index)
+                        ));
+                    };
+
+                    (parent_point.point.x as f32 - child_x, parent_point.point.y as f32 - child_y)
+                }
+            };
+
+            contours.extend(transformed.into_iter().map(|contour| contour.into_iter()
+                .map(|(x, y, on_curve)| ContourPoint {
+                    point: Point {
+                        x: (x + dx).round() as i16,
+                        y: (y + dy).round() as i16
+                    },
+                    on_curve
+                })
+                .collect()));
+        }
+
+        Ok(contours)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::file::{table::{Contour, ContourPoint, Glyph, GlyphDescription, GlyphHeader, SimpleGlyph}, BoundingBox, Point};
+
+    use super::{CompositeGlyph, ComponentPlacement, ComponentTransform};
+
+    fn load(data: &[u8]) -> CompositeGlyph {
+        CompositeGlyph::load(&mut Cursor::new(data)).unwrap()
+    }
+
+    fn header(num_contours: i16) -> GlyphHeader {
+        GlyphHeader {
+            num_contours,
+            bounding_box: BoundingBox { left: 0, bottom: 0, right: 0, top: 0 }
+        }
+    }
+
+    /// A simple glyph with a single triangle (0, 0), (100, 0), (100, 100)
+    fn triangle() -> Glyph {
+        let contour: Contour = [(0, 0), (100, 0), (100, 100)].iter()
+            .map(|&(x, y)| ContourPoint { point: Point { x, y }, on_curve: true })
+            .collect();
+
+        Glyph {
+            header: header(1),
+            description: GlyphDescription::Simple(SimpleGlyph { contours: vec![contour], instructions: vec![] })
+        }
+    }
+
+    fn composite(data: &[u8]) -> Glyph {
+        Glyph {
+            header: header(-1),
+            description: GlyphDescription::Composite(load(data))
+        }
+    }
+
+    /// Resolves `glyph_index`, where glyph 1 is a triangle and the other glyphs are composites
+    fn resolve(glyph_index: u16, composites: &[(u16, &[u8])], max_depth: u16) -> Option<Vec<(i16, i16)>> {
+        let find = |index: u16| composites.iter().find(|(glyph, _)| *glyph == index).map(|(_, data)| composite(data));
+        let GlyphDescription::Composite(glyph) = find(glyph_index)?.description else {
+            return None;
+        };
+
+        let contours = glyph.resolve(glyph_index, max_depth, |index| Ok(match index {
+            1 => Some(triangle()),
+            _ => find(index)
+        })).ok()?;
+
+        Some(contours.iter().flatten().map(|point| (point.point.x, point.point.y)).collect())
+    }
+
+    #[test]
+    fn decodes_offsets() {
+        let glyph = load(&[
+            0x00, 0x23, 0x00, 0x01, 0xFE, 0xD4, 0x01, 0xF4,    // words, x/y values, more components
+            0x00, 0x02, 0x00, 0x02, 0xF6, 0x14                 // bytes, x/y values
+        ]);
+
+        assert!(matches!(glyph.components[0].placement, ComponentPlacement::Offset { x: -300, y: 500 }));
+        assert!(matches!(glyph.components[1].placement, ComponentPlacement::Offset { x: -10, y: 20 }));
+        assert_eq!(glyph.components.iter().map(|component| component.glyph_index).collect::<Vec<_>>(), [1, 2]);
+        assert!(glyph.components.iter().all(|component| matches!(component.transform, ComponentTransform::Identity)));
+    }
+
+    #[test]
+    fn decodes_anchor_points() {
+        let glyph = load(&[
+            0x00, 0x21, 0x00, 0x01, 0x01, 0x00, 0x00, 0x02,    // words, point numbers, more components
+            0x00, 0x00, 0x00, 0x01, 0x03, 0x01                 // bytes, point numbers
+        ]);
+
+        assert!(matches!(glyph.components[0].placement, ComponentPlacement::Anchor { parent: 256, child: 2 }));
+        assert!(matches!(glyph.components[1].placement, ComponentPlacement::Anchor { parent: 3, child: 1 }));
+    }
+
+    #[test]
+    fn decodes_scales_and_instructions() {
+        let glyph = load(&[
+            0x00, 0x2A, 0x00, 0x01, 0x00, 0x00, 0x20, 0x00,                            // scale
+            0x00, 0x62, 0x00, 0x01, 0x00, 0x00, 0x40, 0x00, 0xC0, 0x00,                // x and y scale
+            0x01, 0x82, 0x00, 0x01, 0x00, 0x00, 0x40, 0x00, 0x20, 0x00, 0x10, 0x00, 0x40, 0x00, // 2x2, instructions
+            0x00, 0x02, 0xB0, 0x01
+        ]);
+
+        assert!(matches!(glyph.components[0].transform, ComponentTransform::Scale(0.5)));
+        assert!(matches!(glyph.components[1].transform, ComponentTransform::XYScale { x: 1.0, y: -1.0 }));
+        assert!(matches!(glyph.components[2].transform, ComponentTransform::TwoByTwo { xx: 1.0, xy: 0.5, yx: 0.25, yy: 1.0 }));
+        assert_eq!(glyph.instructions, [0xB0, 0x01]);
+    }
+
+    #[test]
+    fn applies_the_two_by_two_transform() {
+        // xx = 1, xy = 0.5, yx = 0, yy = 1 shears x into y
+        let data = [0x00, 0x82, 0x00, 0x01, 0x00, 0x00, 0x40, 0x00, 0x20, 0x00, 0x00, 0x00, 0x40, 0x00];
+        assert_eq!(resolve(2, &[(2, &data)], 1), Some(vec![(0, 0), (100, 50), (100, 150)]));
+    }
+
+    #[test]
+    fn aligns_anchor_points() {
+        let data = [
+            0x00, 0x23, 0x00, 0x01, 0x00, 0x0A, 0x00, 0x14,    // offset by (10, 20)
+            0x00, 0x00, 0x00, 0x01, 0x02, 0x00                 // point 0 on point 2 of the outline so far
+        ];
+
+        assert_eq!(resolve(2, &[(2, &data)], 1), Some(vec![(10, 20), (110, 20), (110, 120), (110, 120), (210, 120), (210, 220)]));
+    }
+
+    #[test]
+    fn anchor_points_out_of_range_are_errors() {
+        let data = [0x00, 0x00, 0x00, 0x01, 0x00, 0x00];
+        assert_eq!(resolve(2, &[(2, &data)], 1), None);
+    }
+
+    #[test]
+    fn cycles_are_errors() {
+        let to_itself = [0x00, 0x02, 0x00, 0x02, 0x00, 0x00];
+        assert_eq!(resolve(2, &[(2, &to_itself)], 8), None);
+
+        let to_3 = [0x00, 0x02, 0x00, 0x03, 0x00, 0x00];
+        let to_2 = [0x00, 0x02, 0x00, 0x02, 0x00, 0x00];
+        assert_eq!(resolve(2, &[(2, &to_3), (3, &to_2)], 8), None);
+    }
+
+    #[test]
+    fn component_depth_is_limited() {
+        let to_3 = [0x00, 0x02, 0x00, 0x03, 0x00, 0x00];
+        let to_1 = [0x00, 0x02, 0x00, 0x01, 0x00, 0x00];
+        let glyphs: [(u16, &[u8]); 2] = [(2, &to_3), (3, &to_1)];
+
+        assert_eq!(resolve(2, &glyphs, 2), Some(vec![(0, 0), (100, 0), (100, 100)]));
+        assert_eq!(resolve(2, &glyphs, 1), None);
+    }
+}
@@ -1,16 +1,15 @@
-use std::{collections::HashMap, io::{Read, Seek}};
+use std::{io::{Read, Seek, SeekFrom}, sync::Arc};
 
+use log::{debug, warn};
 use serde::Deserialize;
 
-use crate::file::{deserialize_from, error::Result, loader::TableDirectoryEntry, table::Table, BoundingBox};
+use crate::file::{deserialize_from, error::{FontError, Result}, loader::TableDirectoryEntry, table::{Locations, Table}, BoundingBox};
 
 mod simple;
-pub use simple::SimpleGlyph;
+pub use simple::{Contour, ContourPoint, SimpleGlyph};
 
-#[derive(Debug, Clone, Copy)]
-pub struct CompositeGlyph {
-    
-}
+mod composite;
+pub use composite::{Component, ComponentPlacement, ComponentTransform, CompositeGlyph};
 
 #[derive(Debug, Clone)]
 pub enum GlyphDescription {
@@ -20,36 +19,110 @@ pub enum GlyphDescription {
 
 #[derive(Debug, Clone, Copy, Deserialize)]
 pub struct GlyphHeader {
-    num_contours: i16,
-    bounding_box: BoundingBox
+    pub num_contours: i16,
+    pub bounding_box: BoundingBox
 }
 
 #[derive(Debug, Clone)]
 pub struct Glyph {
-    header: GlyphHeader,
-    description: GlyphDescription
+    pub header: GlyphHeader,
+    pub description: GlyphDescription
 }
 
-pub type Glyphs = HashMap<u32, Glyph>;
+/// Handle to the glyph data table. Glyphs are only decoded when they are requested,
+/// using the offsets from the `loca` table to find them.
+#[derive(Debug, Clone)]
+pub struct Glyphs {
+    entry: TableDirectoryEntry,
+    locations: Arc<Locations>
+}
 
 impl Table for Glyphs {
-    type UserArgsType = u16;
+    type UserArgsType = Arc<Locations>;
     
     fn get_table_name() -> &'static str {
         "glyf"
     }
 
-    fn load_impl<S>(entry: TableDirectoryEntry, stream: &mut S, num_glyphs: Self::UserArgsType) -> Result<Self>
+    fn load_impl<S>(entry: TableDirectoryEntry, _: &mut S, locations: Self::UserArgsType) -> Result<Self>
+        where S: Read + Seek
+    {
+        if let Some(&end) = locations.last() {
+            if end > entry.length {
+                warn!("loca table points past the end of the glyf table ({} > {})", end, entry.length);
+            }
+        }
+
+        Ok(Glyphs {
+            entry,
+            locations
+        })
+    }
+}
+
+impl Glyphs {
+    pub fn num_glyphs(&self) -> u16 {
+        self.locations.len().saturating_sub(1) as u16
+    }
+
+    /// Loads the glyph with the given index. Returns `None` if the glyph has no outline
+    pub fn glyph<S>(&self, glyph_index: u16, stream: &mut S) -> Result<Option<Glyph>>
+        where S: Read + Seek
+    {
+        let Some(header) = self.header(glyph_index, stream)? else {
+            return Ok(None);
+        };
+        let description = GlyphDescription::load(&header, stream)?;
+
+        Ok(Some(Glyph { header, description }))
+    }
+
+    /// Loads only the header of a glyph, leaving the stream at the start of its description.
+    /// Returns `None` if the glyph has no outline
+    pub fn header<S>(&self, glyph_index: u16, stream: &mut S) -> Result<Option<GlyphHeader>>
         where S: Read + Seek
     {
-        for _ in 0..num_glyphs {
-            let offset = stream.stream_position()? - entry.offset as u64;
-            let header: GlyphHeader = deserialize_from(stream)?;
+        let index = glyph_index as usize;
+        let (Some(&start), Some(&end)) = (self.locations.get(index), self.locations.get(index + 1)) else {
+            return Err(FontError::FontFormatError(
+                None,
+                format!("Glyph index {} is out of range (font has {} glyphs)", glyph_index, self.num_glyphs())
+            ));
+        };
 
-            let description = GlyphDescription::load(&header, stream)?;
+        if start == end {
+            return Ok(None);
         }
 
-        todo!()
+        // Both values come from the file, so the position is calculated without overflowing
+        let position = self.entry.offset as u64 + start as u64;
+
+        if start > end || end > self.entry.length {
+            return Err(FontError::FontFormatError(
+                u32::try_from(position).ok(),
+                format!("Glyph {} has invalid location {}..{} (glyf table is {} bytes long)", glyph_index, start, end, self.entry.length)
+            ));
+        }
+
+        debug!("loading glyph {} at 0x{:08x}", glyph_index, position);
+        stream.seek(SeekFrom::Start(position))?;
+
+        Ok(Some(deserialize_from(stream)?))
+    }
+
+    /// Loads the outline of a glyph, resolving composite glyphs into their components.
+    /// `max_depth` limits how deeply composite glyphs may be nested.
+    pub fn outline<S>(&self, glyph_index: u16, max_depth: u16, stream: &mut S) -> Result<Option<Vec<Contour>>>
+        where S: Read + Seek
+    {
+        let Some(glyph) = self.glyph(glyph_index, stream)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(match glyph.description {
+            GlyphDescription::Simple(simple) => simple.contours,
+            GlyphDescription::Composite(composite) => composite.resolve(glyph_index, max_depth, |index| self.glyph(index, stream))?
+        }))
     }
 }
 
@@ -58,8 +131,38 @@ impl GlyphDescription {
         where S: Read + Seek
     {
         Ok(match header.num_contours {
-            -1 => todo!(),
+            ..=-1 => GlyphDescription::Composite(CompositeGlyph::load(stream)?),
             _  => GlyphDescription::Simple(SimpleGlyph::load(header, stream)?)
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, sync::Arc};
+
+    use crate::file::loader::TableDirectoryEntry;
+
+    use super::Glyphs;
+
+    #[test]
+    fn glyph_offset_past_u32_is_an_error() {
+        let glyphs = Glyphs {
+            entry: TableDirectoryEntry { tag: 0x676C7966, checksum: 0, offset: u32::MAX - 4, length: 0x20 },
+            locations: Arc::new(vec![0x10, 0x20])
+        };
+
+        assert!(glyphs.glyph(0, &mut Cursor::new(vec![0u8; 16])).is_err());
+    }
+
+    #[test]
+    fn empty_glyph_has_no_outline() {
+        let glyphs = Glyphs {
+            entry: TableDirectoryEntry { tag: 0x676C7966, checksum: 0, offset: 0, length: 0 },
+            locations: Arc::new(vec![0, 0])
+        };
+
+        assert!(glyphs.glyph(0, &mut Cursor::new(vec![])).unwrap().is_none());
+        assert!(glyphs.glyph(1, &mut Cursor::new(vec![])).is_err());
+    }
+}
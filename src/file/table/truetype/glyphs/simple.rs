@@ -6,10 +6,18 @@ use crate::file::{deserialize_from, deserialize_vec_from, error::{FontError, Res
 
 use super::GlyphHeader;
 
+#[derive(Debug, Clone, Copy)]
+pub struct ContourPoint {
+    pub point: Point,
+    pub on_curve: bool
+}
+
+pub type Contour = Vec<ContourPoint>;
+
 #[derive(Debug, Clone)]
 pub struct SimpleGlyph {
-    points: Vec<Point>,
-    instructions: Vec<u8>
+    pub contours: Vec<Contour>,
+    pub instructions: Vec<u8>
 }
 
 impl SimpleGlyph {
@@ -23,32 +31,76 @@ impl SimpleGlyph {
         let instructions: Vec<u8> = deserialize_vec_from(len_instructions as usize, stream)?;
         debug!("{} instructions: {:?}", len_instructions, instructions);
 
-        let points = SimpleGlyph::parse_points(*contour_endpoints.last().unwrap(), stream)?;
+        let Some(&last_endpoint) = contour_endpoints.last() else {
+            return Ok(SimpleGlyph {
+                contours: vec![],
+                instructions
+            });
+        };
+
+        let Some(num_points) = last_endpoint.checked_add(1) else {
+            return Err(FontError::FontFormatError(
+                Some(stream.stream_position()? as u32),
+                format!("Contour endpoint {} exceeds the maximum number of points", last_endpoint)
+            ));
+        };
+
+        let mut points = SimpleGlyph::parse_points(num_points, stream)?.into_iter();
+        let mut contours: Vec<Contour> = Vec::with_capacity(contour_endpoints.len());
+        let mut start = 0;
+
+        for endpoint in contour_endpoints {
+            // Also rejects endpoints past the last one, so `endpoint + 1` can't overflow
+            if endpoint < start || endpoint > last_endpoint {
+                return Err(FontError::FontFormatError(
+                    Some(stream.stream_position()? as u32),
+                    format!("Contour endpoints are not increasing ({} after {})", endpoint, start)
+                ));
+            }
+
+            contours.push(points.by_ref().take((endpoint + 1 - start) as usize).collect());
+            start = endpoint + 1;
+        }
+        debug!("{} contours: {:?}", contours.len(), contours);
 
-        todo!()
+        Ok(SimpleGlyph {
+            contours,
+            instructions
+        })
     }
 
-    fn parse_points<S>(num_points: u16, stream: &mut S) -> Result<Vec<Point>>
+    fn parse_points<S>(num_points: u16, stream: &mut S) -> Result<Vec<ContourPoint>>
         where S: Read + Seek
     {
-        let mut logical_flags = 0;
+        let mut logical_flags: u32 = 0;
         let mut flags: Vec<Flag> = vec![];
 
-        while logical_flags < num_points {
+        while logical_flags < num_points as u32 {
             let flag = Flag::new(stream)?;
             flags.push(flag);
-            logical_flags += flag.repeat_count as u16;
+            logical_flags += flag.repeat_count as u32;
         }
 
-        if logical_flags != num_points {
+        if logical_flags != num_points as u32 {
             warn!("Number of logical flags doesn't match number of points in glyph")
         }
 
-        let point_loader = PointsIterator {
-            flags, stream
-        };
-
-        todo!()
+        let flags: Vec<Flag> = flags.into_iter()
+            .flat_map(|flag| std::iter::repeat_n(flag, flag.repeat_count as usize))
+            .take(num_points as usize)
+            .collect();
+
+        let x_coords = CoordinateIterator::new(&flags, stream, |flag| (flag.x_short, flag.x_same_or_positive))
+            .collect::<Result<Vec<i16>>>()?;
+        let y_coords = CoordinateIterator::new(&flags, stream, |flag| (flag.y_short, flag.y_same_or_positive))
+            .collect::<Result<Vec<i16>>>()?;
+
+        Ok(flags.iter().zip(x_coords.into_iter().zip(y_coords))
+            .map(|(flag, (x, y))| ContourPoint {
+                point: Point { x, y },
+                on_curve: flag.on_curve
+            })
+            .collect())
     }
 }
 
@@ -59,30 +111,24 @@ struct Flag {
     y_short: bool,
     x_same_or_positive: bool,
     y_same_or_positive: bool,
-    overlap: bool,
 
-    repeat_count: u8
+    repeat_count: u16
 }
 
 macro_rules! test_bit {
-    ($value: expr, $bit: literal) => (($value & (1 << $bit)) == $value);
+    ($value: expr, $bit: literal) => (($value & (1 << $bit)) != 0);
 }
 
 impl Flag {
-    fn new<S>(stream: &mut S) -> Result<Self> 
+    fn new<S>(stream: &mut S) -> Result<Self>
         where S: Read + Seek
     {
-        let Some(flags) = stream.bytes().next().transpose()? else {
-            return Err(FontError::FontFormatError(Some(stream.stream_position()? as u32), "Unexpected EOF while reading flags array".into()));
-        };
+        let flags: u8 = deserialize_from(stream)?;
 
-        let mut repeat_count: u8 = 1;
+        let mut repeat_count: u16 = 1;
         if test_bit!(flags, 3) {
-            if let Some(repeats) = stream.bytes().next().transpose()? {
-                repeat_count = repeats;
-            } else {
-                return Err(FontError::FontFormatError(Some(stream.stream_position()? as u32), "Unexpected EOF while reading flags array".into()));
-            }
+            let repeats: u8 = deserialize_from(stream)?;
+            repeat_count += repeats as u16;
         }
 
         Ok(Flag {
@@ -91,27 +137,107 @@ impl Flag {
             y_short: test_bit!(flags, 2),
             x_same_or_positive: test_bit!(flags, 4),
             y_same_or_positive: test_bit!(flags, 5),
-            overlap: test_bit!(flags, 6),
 
             repeat_count
         })
     }
 }
 
-struct PointsIterator<'a, S> 
-    where S: Read + Seek
+/// Decodes one coordinate array (either all x or all y values) of a simple glyph,
+/// turning the stored deltas into absolute coordinates.
+struct CoordinateIterator<'a, S, F>
+    where S: Read + Seek,
+          F: Fn(&Flag) -> (bool, bool)
 {
-    flags: Vec<Flag>,
-    stream: &'a mut S
+    flags: std::slice::Iter<'a, Flag>,
+    stream: &'a mut S,
+    select: F,
+    current: i16
 }
 
-impl<'a, S> Iterator for PointsIterator<'a, S> 
-    where S: Read + Seek
+impl<'a, S, F> CoordinateIterator<'a, S, F>
+    where S: Read + Seek,
+          F: Fn(&Flag) -> (bool, bool)
 {
-    type Item = Point;
+    fn new(flags: &'a [Flag], stream: &'a mut S, select: F) -> Self {
+        CoordinateIterator {
+            flags: flags.iter(),
+            stream,
+            select,
+            current: 0
+        }
+    }
+
+    fn read_delta(&mut self, short: bool, same_or_positive: bool) -> Result<i16> {
+        Ok(match (short, same_or_positive) {
+            (true, true)    => deserialize_from::<u8, _>(self.stream)? as i16,
+            (true, false)   => -(deserialize_from::<u8, _>(self.stream)? as i16),
+            (false, true)   => 0,
+            (false, false)  => deserialize_from(self.stream)?
+        })
+    }
+}
+
+impl<'a, S, F> Iterator for CoordinateIterator<'a, S, F>
+    where S: Read + Seek,
+          F: Fn(&Flag) -> (bool, bool)
+{
+    type Item = Result<i16>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let flags = self.flags.first_mut()?;
-        todo!()
+        let (short, same_or_positive) = (self.select)(self.flags.next()?);
+
+        Some(self.read_delta(short, same_or_positive).map(|delta| {
+            self.current = self.current.wrapping_add(delta);
+            self.current
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::file::{table::GlyphHeader, BoundingBox};
+
+    use super::SimpleGlyph;
+
+    fn header(num_contours: i16) -> GlyphHeader {
+        GlyphHeader {
+            num_contours,
+            bounding_box: BoundingBox { left: 0, bottom: 0, right: 0, top: 0 }
+        }
+    }
+
+    #[test]
+    fn decodes_repeated_flags_and_deltas() {
+        let data = [
+            0x00, 0x01, 0x00, 0x02,     // endpoints 1, 2
+            0x00, 0x00,                 // no instructions
+            0x37,                       // on curve, positive short x and y
+            0x3F, 0x01,                 // the same, repeated once
+            0x0A, 0x05, 0x05,           // x deltas
+            0x14, 0x00, 0x0A            // y deltas
+        ];
+
+        let glyph = SimpleGlyph::load(&header(2), &mut Cursor::new(&data[..])).unwrap();
+        let points: Vec<Vec<(i16, i16)>> = glyph.contours.iter()
+            .map(|contour| contour.iter().map(|point| (point.point.x, point.point.y)).collect())
+            .collect();
+
+        assert_eq!(points, vec![vec![(10, 20), (15, 20)], vec![(20, 30)]]);
+        assert!(glyph.contours.iter().flatten().all(|point| point.on_curve));
+    }
+
+    #[test]
+    fn rejects_endpoint_past_point_limit() {
+        let data = [0xFF, 0xFF, 0x00, 0x00];
+        assert!(SimpleGlyph::load(&header(1), &mut Cursor::new(&data[..])).is_err());
+    }
+
+    #[test]
+    fn rejects_decreasing_endpoints() {
+        let data = [0xFF, 0xFF, 0x00, 0x03, 0x00, 0x00];
+        assert!(SimpleGlyph::load(&header(2), &mut Cursor::new(&data[..])).is_err());
     }
 }
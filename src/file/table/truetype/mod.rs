@@ -2,4 +2,4 @@ mod location;
 pub use location::Locations;
 
 mod glyphs;
-pub use glyphs::{Glyph, Glyphs, GlyphHeader, GlyphDescription, SimpleGlyph, CompositeGlyph};
+pub use glyphs::{Component, ComponentPlacement, ComponentTransform, Contour, ContourPoint, Glyph, Glyphs, GlyphHeader, GlyphDescription, SimpleGlyph, CompositeGlyph};
@@ -0,0 +1,167 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::Result};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RegionAxisCoordinates {
+    pub start: f32,
+    pub peak: f32,
+    pub end: f32
+}
+
+#[derive(Debug, Clone)]
+pub struct VariationRegion {
+    pub axes: Vec<RegionAxisCoordinates>
+}
+
+/// Delta sets of an item variation store. Only the regions are kept, as the
+/// deltas are not needed when the store is referenced from CFF2 charstrings.
+#[derive(Debug, Clone)]
+pub struct ItemVariationData {
+    pub item_count: u16,
+    pub region_indices: Vec<u16>
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ItemVariationStore {
+    pub format: u16,
+    pub regions: Vec<VariationRegion>,
+    pub data: Vec<ItemVariationData>
+}
+
+fn read_f2dot14<S>(stream: &mut S) -> Result<f32>
+    where S: Read + Seek
+{
+    Ok(deserialize_from::<i16, _>(stream)? as f32 / 16384.0)
+}
+
+impl VariationRegion {
+    /// Computes how much this region contributes at the given normalized coordinates
+    pub fn scalar(&self, coordinates: &[f32]) -> f32 {
+        self.axes.iter().enumerate().map(|(axis, region)| {
+            let coordinate = coordinates.get(axis).copied().unwrap_or(0.0);
+            let RegionAxisCoordinates { start, peak, end } = *region;
+
+            if start > peak || peak > end || (start < 0.0 && end > 0.0) || peak == 0.0 {
+                1.0
+            } else if coordinate < start || coordinate > end {
+                0.0
+            } else if coordinate == peak {
+                1.0
+            } else if coordinate < peak {
+                (coordinate - start) / (peak - start)
+            } else {
+                (end - coordinate) / (end - peak)
+            }
+        })
+        .product()
+    }
+}
+
+impl ItemVariationStore {
+    /// Loads an item variation store starting at the current stream position
+    pub fn load<S>(stream: &mut S) -> Result<ItemVariationStore>
+        where S: Read + Seek
+    {
+        let start = stream.stream_position()?;
+
+        let format: u16 = deserialize_from(stream)?;
+        let region_list_offset: u32 = deserialize_from(stream)?;
+        let data_count: u16 = deserialize_from(stream)?;
+        let data_offsets: Vec<u32> = deserialize_vec_from(data_count as usize, stream)?;
+
+        stream.seek(SeekFrom::Start(start + region_list_offset as u64))?;
+        let axis_count: u16 = deserialize_from(stream)?;
+        let region_count: u16 = deserialize_from(stream)?;
+
+        let mut regions: Vec<VariationRegion> = Vec::with_capacity(region_count as usize);
+        for _ in 0..region_count {
+            let mut axes: Vec<RegionAxisCoordinates> = Vec::with_capacity(axis_count as usize);
+            for _ in 0..axis_count {
+                axes.push(RegionAxisCoordinates {
+                    start: read_f2dot14(stream)?,
+                    peak: read_f2dot14(stream)?,
+                    end: read_f2dot14(stream)?
+                });
+            }
+
+            regions.push(VariationRegion { axes });
+        }
+
+        let mut data: Vec<ItemVariationData> = Vec::with_capacity(data_count as usize);
+        for offset in data_offsets {
+            stream.seek(SeekFrom::Start(start + offset as u64))?;
+
+            let item_count: u16 = deserialize_from(stream)?;
+            let _word_delta_count: u16 = deserialize_from(stream)?;
+            let region_index_count: u16 = deserialize_from(stream)?;
+
+            data.push(ItemVariationData {
+                item_count,
+                region_indices: deserialize_vec_from(region_index_count as usize, stream)?
+            });
+        }
+
+        Ok(ItemVariationStore {
+            format,
+            regions,
+            data
+        })
+    }
+
+    /// Returns the scalars of the regions referenced by one item variation data subtable
+    pub fn region_scalars(&self, data_index: usize, coordinates: &[f32]) -> Vec<f32> {
+        let Some(data) = self.data.get(data_index) else {
+            return vec![];
+        };
+
+        data.region_indices.iter()
+            .map(|&region| self.regions.get(region as usize).map_or(0.0, |region| region.scalar(coordinates)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ItemVariationData, ItemVariationStore, RegionAxisCoordinates, VariationRegion};
+
+    fn region(axes: &[(f32, f32, f32)]) -> VariationRegion {
+        VariationRegion {
+            axes: axes.iter().map(|&(start, peak, end)| RegionAxisCoordinates { start, peak, end }).collect()
+        }
+    }
+
+    #[test]
+    fn region_scalars_rise_to_the_peak_and_fall_to_the_end() {
+        let region = region(&[(0.0, 0.5, 1.0)]);
+
+        assert_eq!([-0.5, 0.0, 0.25, 0.5, 0.75, 1.0].map(|coordinate| region.scalar(&[coordinate])), [0.0, 0.0, 0.5, 1.0, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn region_scalars_multiply_over_the_axes() {
+        let region = region(&[(0.0, 1.0, 1.0), (-1.0, -1.0, 0.0)]);
+
+        assert_eq!(region.scalar(&[0.5, -0.5]), 0.25);
+        assert_eq!(region.scalar(&[0.5]), 0.0);
+    }
+
+    #[test]
+    fn invalid_axes_do_not_limit_the_region() {
+        assert_eq!(region(&[(0.5, 0.25, 1.0)]).scalar(&[0.0]), 1.0);
+        assert_eq!(region(&[(-1.0, 0.5, 1.0)]).scalar(&[0.0]), 1.0);
+        assert_eq!(region(&[(0.0, 0.0, 1.0)]).scalar(&[0.0]), 1.0);
+    }
+
+    #[test]
+    fn store_returns_the_scalars_of_a_subtable() {
+        let store = ItemVariationStore {
+            format: 1,
+            regions: vec![region(&[(0.0, 1.0, 1.0)]), region(&[(-1.0, -1.0, 0.0)])],
+            data: vec![ItemVariationData { item_count: 0, region_indices: vec![1, 0, 7] }]
+        };
+
+        assert_eq!(store.region_scalars(0, &[-0.5]), [0.5, 0.0, 0.0]);
+        assert!(store.region_scalars(1, &[-0.5]).is_empty());
+    }
+}
@@ -0,0 +1,44 @@
+use std::io::{Read, Seek};
+
+use log::debug;
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, error::Result, loader::TableDirectoryEntry};
+
+use super::{hheader::Caret, table::Table};
+
+#[derive(Debug, Deserialize)]
+pub struct VerticalHeader {
+    pub version:                (u16, u16),
+    /// Called vertTypoAscender in version 1.1
+    pub ascender:               i16,
+    /// Called vertTypoDescender in version 1.1
+    pub descender:              i16,
+    /// Called vertTypoLineGap in version 1.1
+    pub line_gap:               i16,
+    pub advance_height_max:     u16,
+    pub min_top_side_bearing:   i16,
+    pub min_bottom_side_bearing: i16,
+    pub y_max_extent:           i16,
+    pub caret:                  Caret,
+    pub _reserved:              (i16, i16, i16, i16),
+    pub metric_data_format:     i16,
+    pub number_of_v_metrics:    u16
+}
+
+impl Table for VerticalHeader {
+    type UserArgsType = ();
+
+    fn get_table_name() -> &'static str {
+        "Vertical Header"
+    }
+
+    fn load_impl<S>(_: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
+            where S: Read + Seek
+    {
+        let vheader: Self = deserialize_from(stream)?;
+        debug!("{:?}", vheader);
+
+        Ok(vheader)
+    }
+}
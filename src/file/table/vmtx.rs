@@ -0,0 +1,147 @@
+use std::io::{Read, Seek};
+
+use log::{debug, warn};
+use serde::Deserialize;
+
+use crate::file::{deserialize_vec_from, error::{FontError, Result}, loader::TableDirectoryEntry};
+
+use super::table::Table;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct LongVerMetric {
+    pub advance_height:     u16,
+    pub top_side_bearing:   i16
+}
+
+#[derive(Debug, Clone)]
+pub struct VerticalMetrics {
+    /// Metrics of the first `number_of_v_metrics` glyphs
+    pub v_metrics: Vec<LongVerMetric>,
+
+    /// Top side bearings of the remaining glyphs, which share the last advance height
+    pub top_side_bearings: Vec<i16>
+}
+
+impl VerticalMetrics {
+    pub fn num_glyphs(&self) -> usize {
+        self.v_metrics.len() + self.top_side_bearings.len()
+    }
+
+    /// Returns the advance height and top side bearing of a glyph
+    pub fn metrics(&self, glyph_index: u16) -> Option<LongVerMetric> {
+        let glyph_index = glyph_index as usize;
+        if let Some(&metric) = self.v_metrics.get(glyph_index) {
+            return Some(metric);
+        }
+
+        let top_side_bearing = *self.top_side_bearings.get(glyph_index - self.v_metrics.len())?;
+        Some(LongVerMetric {
+            advance_height: self.v_metrics.last()?.advance_height,
+            top_side_bearing
+        })
+    }
+
+    pub fn advance_height(&self, glyph_index: u16) -> Option<u16> {
+        self.metrics(glyph_index).map(|metric| metric.advance_height)
+    }
+
+    pub fn top_side_bearing(&self, glyph_index: u16) -> Option<i16> {
+        self.metrics(glyph_index).map(|metric| metric.top_side_bearing)
+    }
+}
+
+impl Table for VerticalMetrics {
+    /// (number_of_v_metrics from vhea, number of glyphs from maxp)
+    type UserArgsType = (u16, u16);
+
+    fn get_table_name() -> &'static str {
+        "Vertical Metrics"
+    }
+
+    fn load_impl<S>(entry: TableDirectoryEntry, stream: &mut S, (number_of_v_metrics, num_glyphs): Self::UserArgsType) -> Result<Self>
+        where S: Read + Seek
+    {
+        if number_of_v_metrics == 0 {
+            return Err(FontError::FontFormatError(Some(entry.offset), "Vertical metrics table has no long metrics".into()));
+        }
+
+        let num_metrics = if number_of_v_metrics > num_glyphs {
+            warn!("Vertical header lists {} metrics, but the font only has {} glyphs", number_of_v_metrics, num_glyphs);
+            num_glyphs.max(1) as usize
+        } else {
+            number_of_v_metrics as usize
+        };
+
+        let metrics_size = num_metrics * 4;
+        if metrics_size > entry.length as usize {
+            return Err(FontError::FontFormatError(
+                Some(entry.offset),
+                format!("Vertical metrics table is too short for {} metrics ({} bytes)", num_metrics, entry.length)
+            ));
+        }
+
+        let mut num_bearings = (num_glyphs as usize).saturating_sub(num_metrics);
+        let available = (entry.length as usize - metrics_size) / 2;
+        if num_bearings > available {
+            warn!("Vertical metrics table is missing {} top side bearings", num_bearings - available);
+            num_bearings = available;
+        }
+
+        let table = VerticalMetrics {
+            v_metrics: deserialize_vec_from(num_metrics, stream)?,
+            top_side_bearings: deserialize_vec_from(num_bearings, stream)?
+        };
+        debug!("loaded vertical metrics for {} glyphs", table.num_glyphs());
+
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::file::{error::Result, loader::TableDirectoryEntry, table::Table};
+
+    use super::VerticalMetrics;
+
+    fn load(values: &[i16], number_of_metrics: u16, num_glyphs: u16) -> Result<VerticalMetrics> {
+        let data: Vec<u8> = values.iter().flat_map(|value| value.to_be_bytes()).collect();
+        let entry = TableDirectoryEntry { tag: 0x766D7478, checksum: 0, offset: 0, length: data.len() as u32 };
+
+        VerticalMetrics::load(entry, &mut Cursor::new(data), (number_of_metrics, num_glyphs))
+    }
+
+    #[test]
+    fn trailing_bearings_share_the_last_advance() {
+        let metrics = load(&[500, 10, 600, 20, 30, -40], 2, 4).unwrap();
+
+        assert_eq!(metrics.num_glyphs(), 4);
+        assert_eq!(metrics.advance_height(0), Some(500));
+        assert_eq!(metrics.top_side_bearing(1), Some(20));
+        assert_eq!([2, 3].map(|glyph| metrics.advance_height(glyph)), [Some(600); 2]);
+        assert_eq!([2, 3].map(|glyph| metrics.top_side_bearing(glyph)), [Some(30), Some(-40)]);
+        assert!(metrics.metrics(4).is_none());
+    }
+
+    #[test]
+    fn missing_bearings_are_dropped() {
+        let metrics = load(&[500, 10, 30], 1, 5).unwrap();
+
+        assert_eq!(metrics.num_glyphs(), 2);
+        assert_eq!(metrics.top_side_bearing(1), Some(30));
+        assert!(metrics.metrics(2).is_none());
+    }
+
+    #[test]
+    fn metrics_count_is_limited_to_the_glyph_count() {
+        let metrics = load(&[500, 10, 600, 20], 2, 1).unwrap();
+        assert_eq!(metrics.num_glyphs(), 1);
+    }
+
+    #[test]
+    fn invalid_metrics_counts_are_errors() {
+        assert!(load(&[500, 10], 0, 1).is_err());
+        assert!(load(&[500, 10], 2, 2).is_err());
+    }
+}
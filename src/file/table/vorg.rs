@@ -0,0 +1,68 @@
+use std::io::{Read, Seek};
+
+use log::debug;
+use serde::Deserialize;
+
+use crate::file::{deserialize_from, deserialize_vec_from, error::{FontError, Result}, loader::TableDirectoryEntry};
+
+use super::table::Table;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct VertOriginYMetrics {
+    pub glyph_index:    u16,
+    pub vert_origin_y:  i16
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct VorgHeader {
+    major_version:          u16,
+    minor_version:          u16,
+    default_vert_origin_y:  i16,
+    num_vert_origin_y_metrics: u16
+}
+
+/// The y coordinate of the vertical origin of CFF glyphs
+#[derive(Debug, Clone)]
+pub struct VerticalOrigin {
+    pub default_vert_origin_y: i16,
+
+    /// Glyphs whose origin differs from the default, sorted by glyph index
+    pub metrics: Vec<VertOriginYMetrics>
+}
+
+impl VerticalOrigin {
+    pub fn vert_origin_y(&self, glyph_index: u16) -> i16 {
+        match self.metrics.binary_search_by_key(&glyph_index, |metric| metric.glyph_index) {
+            Ok(index) => self.metrics[index].vert_origin_y,
+            Err(_) => self.default_vert_origin_y
+        }
+    }
+}
+
+impl Table for VerticalOrigin {
+    type UserArgsType = ();
+
+    fn get_table_name() -> &'static str {
+        "Vertical Origin"
+    }
+
+    fn load_impl<S>(entry: TableDirectoryEntry, stream: &mut S, _: Self::UserArgsType) -> Result<Self>
+        where S: Read + Seek
+    {
+        let header: VorgHeader = deserialize_from(stream)?;
+        if header.major_version != 1 {
+            return Err(FontError::FontFormatError(
+                Some(entry.offset),
+                format!("vertical origin table has unknown version: {}.{}", header.major_version, header.minor_version)
+            ));
+        }
+
+        let table = VerticalOrigin {
+            default_vert_origin_y: header.default_vert_origin_y,
+            metrics: deserialize_vec_from(header.num_vert_origin_y_metrics as usize, stream)?
+        };
+        debug!("{:?}", table);
+
+        Ok(table)
+    }
+}
@@ -0,0 +1,433 @@
+use core::fmt;
+use std::io::{Read, Seek, SeekFrom};
+
+use log::{debug, warn};
+
+use crate::file::{deserialize_vec_from, error::Result, loader::{FontLoader, TableDirectoryEntry}, table::{FontHeader, HorizontalHeader, MaximumProfile, Table}, OpenTypeFont};
+
+const TRUETYPE_VERSION: u32 = 0x00010000;
+const CFF_VERSION: u32 = 0x4F54544F;
+const APPLE_TRUETYPE_VERSION: u32 = 0x74727565;
+
+const HEAD_MAGIC_NUMBER: u32 = 0x5F0F3CF5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error
+}
+
+/// A single finding of the validator
+#[derive(Debug, Clone)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    /// Offset from the start of the file the issue refers to
+    pub offset: Option<u32>,
+    pub message: String
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Info => write!(f, "info")?,
+            Severity::Warning => write!(f, "warning")?,
+            Severity::Error => write!(f, "error")?
+        }
+
+        if let Some(offset) = self.offset {
+            write!(f, " (at 0x{:08x})", offset)?;
+        }
+
+        write!(f, ": {}", self.message)
+    }
+}
+
+fn tag_name(tag: u32) -> String {
+    tag.to_be_bytes().iter().map(|&byte| char::from(byte)).collect()
+}
+
+/// The result of checking the table directory of a face and the invariants between its tables
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>
+}
+
+impl ValidationReport {
+    /// Checks the table directory of the loader's face and the tables that depend on each
+    /// other. Tables that can't be parsed are reported instead of failing validation, so
+    /// this also works on fonts `OpenTypeFont` refuses to load.
+    pub fn validate<S>(loader: &mut FontLoader<S>) -> Result<ValidationReport>
+        where S: Read + Seek
+    {
+        let mut report = ValidationReport::default();
+
+        report.validate_directory(loader)?;
+        report.validate_tables(loader);
+
+        for issue in &report.issues {
+            match issue.severity {
+                Severity::Info => debug!("{}", issue),
+                _ => warn!("{}", issue)
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn add(&mut self, severity: Severity, offset: Option<u32>, message: String) {
+        self.issues.push(ValidationIssue { severity, offset, message });
+    }
+
+    fn validate_directory<S>(&mut self, loader: &mut FontLoader<S>) -> Result<()>
+        where S: Read + Seek
+    {
+        let directory_offset = loader.table_directory_offset();
+        let table_dir = loader.get_table_dir().clone();
+
+        match table_dir.sfnt_version {
+            TRUETYPE_VERSION | CFF_VERSION => {},
+            APPLE_TRUETYPE_VERSION => self.add(Severity::Info, Some(directory_offset), "font uses the Apple 'true' sfnt version".into()),
+            version => self.add(Severity::Error, Some(directory_offset), format!("unknown sfnt version 0x{:08x}", version))
+        }
+
+        let num_tables = table_dir.num_tables;
+        let entry_selector = match num_tables {
+            0 => 0,
+            _ => num_tables.ilog2() as u16
+        };
+        let search_range = match num_tables {
+            0 => 0,
+            _ => (1u16 << entry_selector).wrapping_mul(16)
+        };
+        let range_shift = num_tables.wrapping_mul(16).wrapping_sub(search_range);
+
+        for (field, offset, stored, expected) in [
+            ("searchRange", 6, table_dir.search_range, search_range),
+            ("entrySelector", 8, table_dir.entry_selector, entry_selector),
+            ("rangeShift", 10, table_dir.range_shift, range_shift)
+        ] {
+            if stored != expected {
+                self.add(Severity::Warning, Some(directory_offset + offset), format!("{} is {}, but should be {} for {} tables", field, stored, expected, num_tables));
+            }
+        }
+
+        // The directory is read again since `TableDirectory` doesn't keep the order of its entries
+        let stream = loader.get_stream();
+        let file_length = stream.seek(SeekFrom::End(0))?;
+        stream.seek(SeekFrom::Start(directory_offset as u64 + 12))?;
+        let entries: Vec<TableDirectoryEntry> = deserialize_vec_from(num_tables as usize, stream)?;
+
+        for (index, pair) in entries.windows(2).enumerate() {
+            if pair[0].tag >= pair[1].tag {
+                self.add(
+                    Severity::Warning,
+                    Some(directory_offset + 12 + 16 * (index as u32 + 1)),
+                    format!("table directory isn't sorted by tag, {} follows {}", tag_name(pair[1].tag), tag_name(pair[0].tag))
+                );
+            }
+        }
+
+        let directory_end = directory_offset as u64 + 12 + 16 * num_tables as u64;
+        for entry in &entries {
+            let end = entry.offset as u64 + entry.length as u64;
+            if entry.length > 0 && end > directory_offset as u64 && (entry.offset as u64) < directory_end {
+                self.add(Severity::Error, Some(entry.offset), format!("table {} overlaps the table directory", tag_name(entry.tag)));
+            }
+
+            if end > file_length {
+                self.add(
+                    Severity::Error,
+                    Some(entry.offset),
+                    format!("table {} ends at 0x{:08x}, past the end of the file at 0x{:08x}", tag_name(entry.tag), end, file_length)
+                );
+            }
+
+            if !entry.offset.is_multiple_of(4) {
+                self.add(Severity::Warning, Some(entry.offset), format!("table {} isn't aligned to 4 bytes", tag_name(entry.tag)));
+            }
+
+            if entry.length == 0 {
+                self.add(Severity::Info, Some(entry.offset), format!("table {} is empty", tag_name(entry.tag)));
+            }
+        }
+
+        let mut by_offset: Vec<&TableDirectoryEntry> = entries.iter().filter(|entry| entry.length > 0).collect();
+        by_offset.sort_by_key(|entry| (entry.offset, entry.length));
+
+        for pair in by_offset.windows(2) {
+            if (pair[0].offset as u64 + pair[0].length as u64) > pair[1].offset as u64 {
+                self.add(
+                    Severity::Error,
+                    Some(pair[1].offset),
+                    format!("table {} overlaps table {} at 0x{:08x}", tag_name(pair[1].tag), tag_name(pair[0].tag), pair[0].offset)
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads a table for validation, reporting it as an error if it can't be parsed
+    fn load_table<S, T>(&mut self, loader: &mut FontLoader<S>, tag: &str, args: T::UserArgsType) -> Option<(T, TableDirectoryEntry)>
+        where S: Read + Seek,
+              T: Table
+    {
+        let entry = loader.table_entry(tag)?;
+
+        match loader.load_table(tag, args) {
+            Ok(table) => Some((table, entry)),
+            Err(err) => {
+                self.add(Severity::Error, Some(entry.offset), format!("table {} can't be parsed: {}", tag, err));
+                None
+            }
+        }
+    }
+
+    fn validate_tables<S>(&mut self, loader: &mut FontLoader<S>)
+        where S: Read + Seek
+    {
+        for tag in OpenTypeFont::REQUIRED_TAGS {
+            if !loader.has_table(tag) {
+                self.add(Severity::Error, None, format!("required table {} is missing", tag));
+            }
+        }
+
+        if loader.has_table("glyf") && !loader.has_table("loca") {
+            self.add(Severity::Error, None, "font has a glyf table, but no loca table".into());
+        }
+        if !loader.has_table("glyf") && !loader.has_table("CFF ") && !loader.has_table("CFF2") {
+            self.add(Severity::Info, None, "font has no outlines".into());
+        }
+
+        let head: Option<(FontHeader, _)> = self.load_table(loader, "head", ());
+        let hhea: Option<(HorizontalHeader, _)> = self.load_table(loader, "hhea", ());
+        let maxp: Option<(MaximumProfile, _)> = self.load_table(loader, "maxp", ());
+
+        if let Some((head, entry)) = &head {
+            if head.magic_number != HEAD_MAGIC_NUMBER {
+                self.add(Severity::Error, Some(entry.offset + 12), format!("head has magic number 0x{:08x}, expected 0x{:08x}", head.magic_number, HEAD_MAGIC_NUMBER));
+            }
+
+            if head.index_to_loc_format != 0 && head.index_to_loc_format != 1 {
+                self.add(Severity::Error, Some(entry.offset + 50), format!("head.indexToLocFormat is {}, but has to be 0 or 1", head.index_to_loc_format));
+            }
+        }
+
+        let Some((maxp, _)) = maxp else {
+            return;
+        };
+        let num_glyphs = maxp.num_glyphs() as u32;
+
+        if let Some((hhea, entry)) = &hhea {
+            let num_metrics = hhea.number_of_h_metrics as u32;
+            if num_metrics == 0 {
+                self.add(Severity::Error, Some(entry.offset + 34), "hhea.numberOfHMetrics is 0, but hmtx needs at least one metric".into());
+            } else if num_metrics > num_glyphs {
+                self.add(Severity::Error, Some(entry.offset + 34), format!("hhea.numberOfHMetrics is {}, but the font only has {} glyphs", num_metrics, num_glyphs));
+            } else if let Some(hmtx) = loader.table_entry("hmtx") {
+                let expected = 4 * num_metrics + 2 * (num_glyphs - num_metrics);
+                match hmtx.length {
+                    length if length < expected => self.add(Severity::Error, Some(hmtx.offset), format!("hmtx is {} bytes long, but needs {} bytes for {} glyphs", length, expected, num_glyphs)),
+                    length if length > expected => self.add(Severity::Info, Some(hmtx.offset), format!("hmtx is {} bytes long, {} more than needed", length, length - expected)),
+                    _ => {}
+                }
+            }
+        }
+
+        let index_to_loc_format = head.map(|(head, _)| head.index_to_loc_format);
+        let loca = loader.table_entry("loca");
+
+        if let (Some(format @ (0 | 1)), Some(loca)) = (index_to_loc_format, loca) {
+            let entry_size = if format == 0 { 2 } else { 4 };
+            let expected = (num_glyphs + 1) * entry_size;
+
+            match loca.length {
+                length if length < expected => self.add(
+                    Severity::Error,
+                    Some(loca.offset),
+                    format!("loca has {} offsets, but maxp.numGlyphs is {} and needs {}", length / entry_size, num_glyphs, num_glyphs + 1)
+                ),
+                length if length > expected => self.add(
+                    Severity::Warning,
+                    Some(loca.offset),
+                    format!("loca has {} offsets, but maxp.numGlyphs is {} and only needs {}", length / entry_size, num_glyphs, num_glyphs + 1)
+                ),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|issue| issue.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|issue| issue.severity == Severity::Warning)
+    }
+
+    /// Returns true if no errors were found. Warnings and infos don't make a font invalid.
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::file::loader::FontLoader;
+
+    use super::{Severity, ValidationReport, HEAD_MAGIC_NUMBER};
+
+    const TAGS: [&[u8; 4]; 10] = [b"OS/2", b"cmap", b"glyf", b"head", b"hhea", b"hmtx", b"loca", b"maxp", b"name", b"post"];
+
+    /// Builds a valid TrueType font with two empty glyphs and one horizontal metric.
+    /// Tables that aren't checked are 4 zero bytes.
+    fn font() -> Vec<u8> {
+        let mut head = vec![0u8; 54];
+        head[12..16].copy_from_slice(&HEAD_MAGIC_NUMBER.to_be_bytes());
+        let mut hhea = vec![0u8; 36];
+        hhea[34..36].copy_from_slice(&1u16.to_be_bytes());
+        let maxp = vec![0, 0, 0x50, 0, 0, 2];
+
+        let tables: Vec<Vec<u8>> = TAGS.iter()
+            .map(|&tag| match tag {
+                b"head" => head.clone(),
+                b"hhea" => hhea.clone(),
+                b"maxp" => maxp.clone(),
+                b"hmtx" | b"loca" => vec![0; 6],
+                _ => vec![0; 4]
+            })
+            .collect();
+
+        let mut font = vec![0, 1, 0, 0, 0, 10, 0, 128, 0, 3, 0, 32];
+        let mut offset = 12 + 16 * tables.len() as u32;
+        for (tag, table) in TAGS.iter().zip(&tables) {
+            font.extend(*tag);
+            font.extend([0; 4]);
+            font.extend(offset.to_be_bytes());
+            font.extend((table.len() as u32).to_be_bytes());
+            offset += (table.len() as u32).next_multiple_of(4);
+        }
+        for table in &tables {
+            font.extend(table);
+            font.resize(font.len().next_multiple_of(4), 0);
+        }
+
+        font
+    }
+
+    /// Returns the position of a table's directory entry
+    fn entry(tag: &[u8; 4]) -> usize {
+        12 + 16 * TAGS.iter().position(|&other| other == tag).unwrap()
+    }
+
+    fn read_u32(font: &[u8], position: usize) -> u32 {
+        u32::from_be_bytes(font[position..position + 4].try_into().unwrap())
+    }
+
+    fn write(font: &mut [u8], position: usize, bytes: &[u8]) {
+        font[position..position + bytes.len()].copy_from_slice(bytes);
+    }
+
+    /// Returns the offset of a table in the file
+    fn offset(font: &[u8], tag: &[u8; 4]) -> u32 {
+        read_u32(font, entry(tag) + 8)
+    }
+
+    /// Validates the font and returns the severity and offset of every issue
+    fn validate(font: Vec<u8>) -> Vec<(Severity, Option<u32>)> {
+        let report = ValidationReport::validate(&mut FontLoader::new(Cursor::new(font)).unwrap()).unwrap();
+        report.issues.iter().map(|issue| (issue.severity, issue.offset)).collect()
+    }
+
+    #[test]
+    fn valid_font_has_no_issues() {
+        assert_eq!(validate(font()), []);
+    }
+
+    #[test]
+    fn unsorted_tags() {
+        let mut font = font();
+        let (first, second) = (font[12..28].to_vec(), font[28..44].to_vec());
+        write(&mut font, 12, &second);
+        write(&mut font, 28, &first);
+
+        assert_eq!(validate(font), [(Severity::Warning, Some(28))]);
+    }
+
+    #[test]
+    fn misaligned_offset() {
+        // Moves post to the end of the file, one byte after a multiple of 4
+        let mut font = font();
+        let post = font.len() as u32 + 1;
+        font.extend([0; 5]);
+        write(&mut font, entry(b"post") + 8, &post.to_be_bytes());
+
+        assert_eq!(validate(font), [(Severity::Warning, Some(post))]);
+    }
+
+    #[test]
+    fn overlapping_tables() {
+        let mut font = font();
+        let os2 = offset(&font, b"OS/2");
+        write(&mut font, entry(b"cmap") + 8, &os2.to_be_bytes());
+
+        assert_eq!(validate(font), [(Severity::Error, Some(os2))]);
+    }
+
+    #[test]
+    fn table_overlapping_the_directory() {
+        let mut font = font();
+        write(&mut font, entry(b"cmap") + 8, &16u32.to_be_bytes());
+
+        assert_eq!(validate(font), [(Severity::Error, Some(16))]);
+    }
+
+    #[test]
+    fn table_past_the_end_of_the_file() {
+        let mut font = font();
+        write(&mut font, entry(b"post") + 12, &100u32.to_be_bytes());
+
+        assert_eq!(validate(font.clone()), [(Severity::Error, Some(offset(&font, b"post")))]);
+    }
+
+    #[test]
+    fn wrong_search_fields() {
+        let mut font = font();
+        write(&mut font, 6, &[0, 160, 0, 4, 0, 0]);
+
+        assert_eq!(validate(font), [(Severity::Warning, Some(6)), (Severity::Warning, Some(8)), (Severity::Warning, Some(10))]);
+    }
+
+    #[test]
+    fn more_horizontal_metrics_than_glyphs() {
+        let mut font = font();
+        let hhea = offset(&font, b"hhea");
+        write(&mut font, hhea as usize + 34, &3u16.to_be_bytes());
+
+        assert_eq!(validate(font), [(Severity::Error, Some(hhea + 34))]);
+    }
+
+    #[test]
+    fn loca_length_mismatch() {
+        let mut font = font();
+        let loca = offset(&font, b"loca");
+
+        write(&mut font, entry(b"loca") + 12, &4u32.to_be_bytes());
+        assert_eq!(validate(font.clone()), [(Severity::Error, Some(loca))]);
+
+        write(&mut font, entry(b"loca") + 12, &8u32.to_be_bytes());
+        assert_eq!(validate(font), [(Severity::Warning, Some(loca))]);
+    }
+
+    #[test]
+    fn bad_index_to_loc_format() {
+        let mut font = font();
+        let head = offset(&font, b"head");
+        write(&mut font, head as usize + 50, &2u16.to_be_bytes());
+
+        assert_eq!(validate(font), [(Severity::Error, Some(head + 50))]);
+    }
+}
@@ -0,0 +1,278 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use log::debug;
+use serde::Deserialize;
+
+use crate::file::{self, error::{FontError, Result}};
+
+/// Signature at the start of WOFF files
+pub const WOFF_SIGNATURE: u32 = 0x774F4646;
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct WoffHeader {
+    pub signature: u32,
+    /// The sfnt version of the contained font
+    pub flavor: u32,
+    pub length: u32,
+    pub num_tables: u16,
+    pub reserved: u16,
+    pub total_sfnt_size: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub meta_offset: u32,
+    pub meta_length: u32,
+    pub meta_orig_length: u32,
+    pub priv_offset: u32,
+    pub priv_length: u32
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct WoffTableEntry {
+    pub tag: u32,
+    pub offset: u32,
+    pub comp_length: u32,
+    pub orig_length: u32,
+    pub orig_checksum: u32
+}
+
+/// A table of the reconstructed font
+#[derive(Debug)]
+struct WoffTable {
+    entry: WoffTableEntry,
+    /// Offset of the table in the reconstructed font
+    sfnt_offset: u32,
+    /// The table data, inflated the first time the table is read
+    data: Option<Vec<u8>>
+}
+
+fn inflate(compressed: &[u8], orig_length: u32, offset: u32) -> Result<Vec<u8>> {
+    let data = miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(compressed, orig_length as usize)
+        .map_err(|err| FontError::FontFormatError(Some(offset), format!("Failed to inflate WOFF data: {}", err)))?;
+
+    if data.len() != orig_length as usize {
+        return Err(FontError::FontFormatError(
+            Some(offset),
+            format!("WOFF data inflated to {} bytes, expected {}", data.len(), orig_length)
+        ));
+    }
+
+    Ok(data)
+}
+
+/// Presents the font inside a WOFF file as a regular sfnt font. Tables are read and
+/// inflated when they are first accessed.
+#[derive(Debug)]
+pub struct WoffStream<S> {
+    stream: S,
+    header: WoffHeader,
+
+    /// The sfnt header and table directory of the reconstructed font
+    directory: Vec<u8>,
+    /// Ordered by their offset in the reconstructed font
+    tables: Vec<WoffTable>,
+    length: u64,
+    position: u64
+}
+
+impl<S> WoffStream<S>
+    where S: Read + Seek
+{
+    pub fn new(mut stream: S) -> Result<WoffStream<S>> {
+        stream.seek(SeekFrom::Start(0))?;
+        let header: WoffHeader = file::deserialize_from(&mut stream)?;
+        debug!("{:?}", header);
+
+        if header.signature != WOFF_SIGNATURE {
+            return Err(FontError::FontFormatError(Some(0), "Missing WOFF signature".into()));
+        }
+
+        let entries: Vec<WoffTableEntry> = file::deserialize_vec_from(header.num_tables as usize, &mut stream)?;
+
+        // Blocks are only read when they're needed, so their bounds are checked up front
+        // instead of allocating whatever length the directory claims
+        let file_length = stream.seek(SeekFrom::End(0))?.min(header.length as u64);
+        let blocks = entries.iter().map(|entry| ("table", entry.offset, entry.comp_length))
+            .chain([("metadata", header.meta_offset, header.meta_length), ("private data", header.priv_offset, header.priv_length)]);
+
+        for (name, offset, length) in blocks {
+            if offset as u64 + length as u64 > file_length {
+                return Err(FontError::FontFormatError(
+                    Some(offset),
+                    format!("WOFF {} of {} bytes reaches past the end of the file at 0x{:08x}", name, length, file_length)
+                ));
+            }
+        }
+
+        let num_tables = header.num_tables as u32;
+        let entry_selector = num_tables.max(1).ilog2();
+        let search_range = (1u32 << entry_selector) * 16;
+
+        let mut directory = Vec::with_capacity(12 + 16 * num_tables as usize);
+        directory.extend(header.flavor.to_be_bytes());
+        directory.extend(header.num_tables.to_be_bytes());
+        directory.extend((search_range as u16).to_be_bytes());
+        directory.extend((entry_selector as u16).to_be_bytes());
+        directory.extend(((num_tables * 16).saturating_sub(search_range) as u16).to_be_bytes());
+
+        // Tables are placed in the order of their data, which is the order of the original
+        // font. Otherwise the offsets in the directory and thus the font checksum would change.
+        let mut order: Vec<usize> = (0..entries.len()).collect();
+        order.sort_by_key(|&index| entries[index].offset);
+
+        let mut sfnt_offsets = vec![0; entries.len()];
+        let mut sfnt_offset = 12 + 16 * num_tables;
+        for &index in &order {
+            let entry = entries[index];
+            if entry.comp_length > entry.orig_length {
+                return Err(FontError::FontFormatError(
+                    Some(entry.offset),
+                    format!("WOFF table is {} bytes compressed but only {} bytes uncompressed", entry.comp_length, entry.orig_length)
+                ));
+            }
+
+            sfnt_offsets[index] = sfnt_offset;
+            sfnt_offset = entry.orig_length.checked_next_multiple_of(4)
+                .and_then(|length| sfnt_offset.checked_add(length))
+                .ok_or_else(|| FontError::FontFormatError(Some(entry.offset), "WOFF tables don't fit into a font of 4 GiB".into()))?;
+        }
+
+        for (entry, &sfnt_offset) in entries.iter().zip(&sfnt_offsets) {
+            directory.extend(entry.tag.to_be_bytes());
+            directory.extend(entry.orig_checksum.to_be_bytes());
+            directory.extend(sfnt_offset.to_be_bytes());
+            directory.extend(entry.orig_length.to_be_bytes());
+        }
+
+        let tables = order.into_iter()
+            .map(|index| WoffTable {
+                entry: entries[index],
+                sfnt_offset: sfnt_offsets[index],
+                data: None
+            })
+            .collect();
+
+        Ok(WoffStream {
+            stream,
+            header,
+            directory,
+            tables,
+            length: sfnt_offset as u64,
+            position: 0
+        })
+    }
+
+    pub fn header(&self) -> &WoffHeader {
+        &self.header
+    }
+
+    /// Returns the table entries in the order of their data
+    pub fn table_entries(&self) -> impl Iterator<Item = &WoffTableEntry> {
+        self.tables.iter().map(|table| &table.entry)
+    }
+
+    fn read_block(&mut self, offset: u32, length: u32) -> Result<Vec<u8>> {
+        let mut data = vec![0; length as usize];
+
+        self.stream.seek(SeekFrom::Start(offset as u64))?;
+        self.stream.read_exact(&mut data)?;
+
+        Ok(data)
+    }
+
+    /// Returns the extended metadata, an XML document, if the file has any
+    pub fn metadata(&mut self) -> Result<Option<String>> {
+        if self.header.meta_offset == 0 || self.header.meta_length == 0 {
+            return Ok(None);
+        }
+
+        let compressed = self.read_block(self.header.meta_offset, self.header.meta_length)?;
+        let data = inflate(&compressed, self.header.meta_orig_length, self.header.meta_offset)?;
+
+        String::from_utf8(data)
+            .map(Some)
+            .map_err(|_| FontError::FontFormatError(Some(self.header.meta_offset), "WOFF metadata isn't valid UTF-8".into()))
+    }
+
+    /// Returns the private data block, if the file has one
+    pub fn private_data(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.header.priv_offset == 0 || self.header.priv_length == 0 {
+            return Ok(None);
+        }
+
+        self.read_block(self.header.priv_offset, self.header.priv_length).map(Some)
+    }
+
+    /// Returns the data of the table at `index`, reading and inflating it if necessary
+    fn table_data(&mut self, index: usize) -> Result<&[u8]> {
+        if self.tables[index].data.is_none() {
+            let entry = self.tables[index].entry;
+            debug!("reading WOFF table at 0x{:08x}", entry.offset);
+
+            let stored = self.read_block(entry.offset, entry.comp_length)?;
+            let data = match entry.comp_length < entry.orig_length {
+                true => inflate(&stored, entry.orig_length, entry.offset)?,
+                false => stored
+            };
+
+            self.tables[index].data = Some(data);
+        }
+
+        Ok(self.tables[index].data.as_deref().unwrap_or_default())
+    }
+}
+
+impl<S> Read for WoffStream<S>
+    where S: Read + Seek
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let position = self.position;
+
+        let count = if position < self.directory.len() as u64 {
+            let data = &self.directory[position as usize..];
+            let count = data.len().min(buf.len());
+            buf[..count].copy_from_slice(&data[..count]);
+
+            count
+        } else {
+            // Find the table containing the position, including its padding
+            let index = self.tables.partition_point(|table| (table.sfnt_offset as u64) <= position);
+            let Some(index) = index.checked_sub(1) else {
+                return Ok(0);
+            };
+
+            let start = self.tables[index].sfnt_offset as u64;
+            let end = self.tables.get(index + 1).map_or(self.length, |table| table.sfnt_offset as u64);
+            if position >= end {
+                return Ok(0);
+            }
+
+            let data = self.table_data(index)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+
+            let count = ((end - position) as usize).min(buf.len());
+            for (offset, byte) in buf[..count].iter_mut().enumerate() {
+                *byte = data.get((position - start) as usize + offset).copied().unwrap_or(0);
+            }
+
+            count
+        };
+
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl<S> Seek for WoffStream<S>
+    where S: Read + Seek
+{
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.length.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset)
+        };
+
+        self.position = position.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek to a negative position"))?;
+        Ok(self.position)
+    }
+}
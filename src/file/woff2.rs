@@ -0,0 +1,861 @@
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+use log::{debug, info};
+use serde::Deserialize;
+
+use crate::file::{self, error::{FontError, Result}, loader::COLLECTION_TAG, table_checksum, CHECKSUM_MAGIC};
+
+/// Signature at the start of WOFF2 files
+pub const WOFF2_SIGNATURE: u32 = 0x774F4632;
+
+/// Tags that table directory entries refer to by their index
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post",
+    b"cvt ", b"fpgm", b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT",
+    b"EBLC", b"gasp", b"hdmx", b"kern", b"LTSH", b"PCLT", b"VDMX", b"vhea",
+    b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC", b"JSTF", b"MATH",
+    b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar",
+    b"gvar", b"hsty", b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop",
+    b"trak", b"Zapf", b"Silf", b"Glat", b"Gloc", b"Feat", b"Sill"
+];
+
+const GLYF: u32 = u32::from_be_bytes(*b"glyf");
+const LOCA: u32 = u32::from_be_bytes(*b"loca");
+const HMTX: u32 = u32::from_be_bytes(*b"hmtx");
+const HEAD: u32 = u32::from_be_bytes(*b"head");
+const HHEA: u32 = u32::from_be_bytes(*b"hhea");
+const MAXP: u32 = u32::from_be_bytes(*b"maxp");
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct Woff2Header {
+    pub signature: u32,
+    /// The sfnt version of the contained font
+    pub flavor: u32,
+    pub length: u32,
+    pub num_tables: u16,
+    pub reserved: u16,
+    pub total_sfnt_size: u32,
+    pub total_compressed_size: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub meta_offset: u32,
+    pub meta_length: u32,
+    pub meta_orig_length: u32,
+    pub priv_offset: u32,
+    pub priv_length: u32
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Woff2TableEntry {
+    pub tag: u32,
+    pub transform_version: u8,
+    pub orig_length: u32,
+    /// Length of the transformed table data, `None` if the table isn't transformed
+    pub transform_length: Option<u32>
+}
+
+impl Woff2TableEntry {
+    fn load<S>(stream: &mut S) -> Result<Woff2TableEntry>
+        where S: Read + Seek
+    {
+        let position = stream.stream_position()? as u32;
+        let flags: u8 = file::deserialize_from(stream)?;
+
+        let tag = match flags & 0x3F {
+            63 => file::deserialize_from(stream)?,
+            index => u32::from_be_bytes(*KNOWN_TAGS[index as usize])
+        };
+        let transform_version = flags >> 6;
+        let orig_length = read_base128(stream)?;
+
+        // glyf and loca are transformed unless they use the null transform 3, all other
+        // tables only if they use a transform other than 0
+        let transformed = match tag {
+            GLYF | LOCA => transform_version != 3,
+            _ => transform_version != 0
+        };
+
+        let entry = Woff2TableEntry {
+            tag,
+            transform_version,
+            orig_length,
+            transform_length: match transformed {
+                true => Some(read_base128(stream)?),
+                false => None
+            }
+        };
+
+        match (tag, entry.transform_version) {
+            (GLYF | LOCA, 0 | 3) | (HMTX, 0 | 1) | (_, 0) => Ok(entry),
+            _ => Err(FontError::FontFormatError(
+                Some(position),
+                format!("Unknown transform {} for WOFF2 table {}", transform_version, String::from_utf8_lossy(&tag.to_be_bytes()))
+            ))
+        }
+    }
+
+    /// Returns the length of the table data in the compressed data block
+    fn stored_length(&self) -> u32 {
+        self.transform_length.unwrap_or(self.orig_length)
+    }
+}
+
+/// Reads a variable length UIntBase128 number
+fn read_base128<S>(stream: &mut S) -> Result<u32>
+    where S: Read + Seek
+{
+    let position = stream.stream_position()? as u32;
+    let mut value: u32 = 0;
+
+    for index in 0..5 {
+        let byte: u8 = file::deserialize_from(stream)?;
+
+        if index == 0 && byte == 0x80 {
+            return Err(FontError::FontFormatError(Some(position), "UIntBase128 value with leading zeros".into()));
+        }
+        if value & 0xFE000000 != 0 {
+            return Err(FontError::FontFormatError(Some(position), "UIntBase128 value overflows 32 bits".into()));
+        }
+
+        value = (value << 7) | (byte & 0x7F) as u32;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(FontError::FontFormatError(Some(position), "UIntBase128 value is longer than 5 bytes".into()))
+}
+
+/// Reads big-endian values from one of the streams of a transformed table
+struct ByteReader<'a> {
+    name: &'static str,
+    data: &'a [u8],
+    position: usize
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(name: &'static str, data: &'a [u8]) -> ByteReader<'a> {
+        ByteReader {
+            name,
+            data,
+            position: 0
+        }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        let bytes = self.data.get(self.position..self.position + count)
+            .ok_or_else(|| FontError::FontFormatError(None, format!("Unexpected end of the WOFF2 {} stream", self.name)))?;
+
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a variable length 255UInt16 number
+    fn u16_255(&mut self) -> Result<u16> {
+        Ok(match self.u8()? {
+            253 => self.u16()?,
+            254 => self.u8()? as u16 + 506,
+            255 => self.u8()? as u16 + 253,
+            code => code as u16
+        })
+    }
+
+    /// Returns the number of bytes left in the stream
+    fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    /// Splits off the next `length` bytes as a separate stream
+    fn stream(&mut self, name: &'static str, length: u32) -> Result<ByteReader<'a>> {
+        Ok(ByteReader::new(name, self.bytes(length as usize)?))
+    }
+}
+
+/// Decodes a point coordinate delta from its flag and the bytes in the glyph stream
+fn decode_triplet(flag: u8, glyphs: &mut ByteReader) -> Result<(i32, i32)> {
+    let with_sign = |flag: u8, value: i32| if flag & 1 != 0 { value } else { -value };
+    let flag = flag & 0x7F;
+
+    Ok(match flag {
+        0..=9 => (0, with_sign(flag, (((flag & 14) as i32) << 7) + glyphs.u8()? as i32)),
+        10..=19 => (with_sign(flag, ((((flag - 10) & 14) as i32) << 7) + glyphs.u8()? as i32), 0),
+        20..=83 => {
+            let first = (flag - 20) as i32;
+            let second = glyphs.u8()? as i32;
+
+            (with_sign(flag, 1 + (first & 0x30) + (second >> 4)), with_sign(flag >> 1, 1 + ((first & 0x0C) << 2) + (second & 0x0F)))
+        },
+        84..=119 => {
+            let first = (flag - 84) as i32;
+            let bytes = glyphs.bytes(2)?;
+
+            (with_sign(flag, 1 + ((first / 12) << 8) + bytes[0] as i32), with_sign(flag >> 1, 1 + (((first % 12) >> 2) << 8) + bytes[1] as i32))
+        },
+        120..=123 => {
+            let bytes = glyphs.bytes(3)?;
+
+            (with_sign(flag, ((bytes[0] as i32) << 4) + (bytes[1] as i32 >> 4)), with_sign(flag >> 1, ((bytes[1] as i32 & 0x0F) << 8) + bytes[2] as i32))
+        },
+        _ => {
+            let bytes = glyphs.bytes(4)?;
+
+            (with_sign(flag, ((bytes[0] as i32) << 8) + bytes[1] as i32), with_sign(flag >> 1, ((bytes[2] as i32) << 8) + bytes[3] as i32))
+        }
+    })
+}
+
+fn push_i16(data: &mut Vec<u8>, value: i16) {
+    data.extend(value.to_be_bytes());
+}
+
+fn push_u16(data: &mut Vec<u8>, value: u16) {
+    data.extend(value.to_be_bytes());
+}
+
+/// Encodes a simple glyph in the regular glyf format
+fn encode_simple_glyph(end_points: &[u16], points: &[(i32, i32, bool)], instructions: &[u8], bounding_box: [i16; 4], overlap: bool) -> Vec<u8> {
+    let mut glyph = Vec::new();
+    push_i16(&mut glyph, end_points.len() as i16);
+    bounding_box.iter().for_each(|&value| push_i16(&mut glyph, value));
+    end_points.iter().for_each(|&end_point| push_u16(&mut glyph, end_point));
+    push_u16(&mut glyph, instructions.len() as u16);
+    glyph.extend(instructions);
+
+    let mut flags = Vec::with_capacity(points.len());
+    let mut x_coordinates = Vec::new();
+    let mut y_coordinates = Vec::new();
+    let (mut last_x, mut last_y) = (0, 0);
+
+    for &(x, y, on_curve) in points {
+        let mut flag = on_curve as u8;
+
+        // Short deltas store their sign in the flag, zero deltas aren't stored at all
+        for (delta, short_flag, same_flag, coordinates) in [(x - last_x, 0x02, 0x10, &mut x_coordinates), (y - last_y, 0x04, 0x20, &mut y_coordinates)] {
+            match delta {
+                0 => flag |= same_flag,
+                -255..=255 => {
+                    flag |= short_flag | if delta > 0 { same_flag } else { 0 };
+                    coordinates.push(delta.unsigned_abs() as u8);
+                },
+                _ => push_i16(coordinates, delta as i16)
+            }
+        }
+
+        flags.push(flag);
+        (last_x, last_y) = (x, y);
+    }
+
+    if let (true, Some(first)) = (overlap, flags.first_mut()) {
+        *first |= 0x40;
+    }
+
+    glyph.extend(flags);
+    glyph.extend(x_coordinates);
+    glyph.extend(y_coordinates);
+    glyph
+}
+
+/// Reads the components of a composite glyph from the composite stream. Returns their
+/// data and whether the glyph has instructions.
+fn read_composite<'a>(composites: &mut ByteReader<'a>) -> Result<(&'a [u8], bool)> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+    const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+    let data = &composites.data[composites.position..];
+    let start = composites.position;
+    let mut has_instructions = false;
+
+    loop {
+        let flags = composites.u16()?;
+        let mut size = 2 + if flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+
+        if flags & WE_HAVE_A_SCALE != 0 {
+            size += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            size += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            size += 8;
+        }
+
+        composites.bytes(size)?;
+        has_instructions |= flags & WE_HAVE_INSTRUCTIONS != 0;
+
+        if flags & MORE_COMPONENTS == 0 {
+            return Ok((&data[..composites.position - start], has_instructions));
+        }
+    }
+}
+
+/// Glyph data rebuilt from a transformed glyf table
+struct Glyphs {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    index_format: u16,
+    /// Left edge of every glyph's bounding box, needed to rebuild hmtx
+    x_mins: Vec<i16>
+}
+
+fn reconstruct_glyf(data: &[u8]) -> Result<Glyphs> {
+    let mut header = ByteReader::new("glyf header", data);
+    let _reserved = header.u16()?;
+    let option_flags = header.u16()?;
+    let num_glyphs = header.u16()?;
+    let index_format = header.u16()?;
+
+    let sizes = (0..7).map(|_| header.u32()).collect::<Result<Vec<u32>>>()?;
+    let mut contour_counts = header.stream("contour count", sizes[0])?;
+    let mut point_counts = header.stream("point count", sizes[1])?;
+    let mut flags = header.stream("flag", sizes[2])?;
+    let mut glyphs = header.stream("glyph", sizes[3])?;
+    let mut composites = header.stream("composite", sizes[4])?;
+    let mut bounding_boxes = header.stream("bounding box", sizes[5])?;
+    let mut instructions = header.stream("instruction", sizes[6])?;
+
+    let bitmap = bounding_boxes.bytes(num_glyphs.div_ceil(32) as usize * 4)?;
+    let overlap_bitmap = match option_flags & 1 != 0 {
+        true => Some(header.bytes(num_glyphs.div_ceil(8) as usize)?),
+        false => None
+    };
+    let has_bit = |bitmap: &[u8], glyph_index: u16| bitmap[glyph_index as usize >> 3] & (0x80 >> (glyph_index & 7)) != 0;
+
+    let mut glyf = Vec::new();
+    let mut offsets = Vec::with_capacity(num_glyphs as usize + 1);
+    let mut x_mins = Vec::with_capacity(num_glyphs as usize);
+
+    for glyph_index in 0..num_glyphs {
+        offsets.push(glyf.len() as u32);
+
+        let contour_count = contour_counts.i16()?;
+        let explicit_bounding_box = match has_bit(bitmap, glyph_index) {
+            true => Some([bounding_boxes.i16()?, bounding_boxes.i16()?, bounding_boxes.i16()?, bounding_boxes.i16()?]),
+            false => None
+        };
+
+        let glyph = match contour_count {
+            0 => {
+                x_mins.push(0);
+                continue;
+            },
+            1.. => {
+                let mut end_points = Vec::with_capacity(contour_count as usize);
+                let mut total = 0u32;
+                for _ in 0..contour_count {
+                    total += point_counts.u16_255()? as u32;
+                    end_points.push(total.wrapping_sub(1) as u16);
+                }
+
+                // Every point has a flag byte, so the count can't exceed what's left of the flags
+                if total as usize > flags.remaining() {
+                    return Err(FontError::FontFormatError(None, format!("Glyph {} has {} points, but only {} flags are left", glyph_index, total, flags.remaining())));
+                }
+
+                let mut points = Vec::with_capacity(total as usize);
+                let (mut x, mut y) = (0, 0);
+                for _ in 0..total {
+                    let flag = flags.u8()?;
+                    let (dx, dy) = decode_triplet(flag, &mut glyphs)?;
+
+                    (x, y) = (x + dx, y + dy);
+                    points.push((x, y, flag & 0x80 == 0));
+                }
+
+                let instruction_length = glyphs.u16_255()?;
+                let glyph_instructions = instructions.bytes(instruction_length as usize)?;
+
+                let bounding_box = explicit_bounding_box.unwrap_or_else(|| {
+                    let (x_min, x_max) = points.iter().fold((i32::MAX, i32::MIN), |(min, max), &(x, _, _)| (min.min(x), max.max(x)));
+                    let (y_min, y_max) = points.iter().fold((i32::MAX, i32::MIN), |(min, max), &(_, y, _)| (min.min(y), max.max(y)));
+
+                    match points.is_empty() {
+                        true => [0; 4],
+                        false => [x_min as i16, y_min as i16, x_max as i16, y_max as i16]
+                    }
+                });
+                x_mins.push(bounding_box[0]);
+
+                let overlap = overlap_bitmap.is_some_and(|bitmap| has_bit(bitmap, glyph_index));
+                encode_simple_glyph(&end_points, &points, glyph_instructions, bounding_box, overlap)
+            },
+            -1 => {
+                let Some(bounding_box) = explicit_bounding_box else {
+                    return Err(FontError::FontFormatError(None, format!("Composite glyph {} has no bounding box", glyph_index)));
+                };
+                x_mins.push(bounding_box[0]);
+
+                let (components, has_instructions) = read_composite(&mut composites)?;
+
+                let mut glyph = Vec::new();
+                push_i16(&mut glyph, -1);
+                bounding_box.iter().for_each(|&value| push_i16(&mut glyph, value));
+                glyph.extend(components);
+
+                if has_instructions {
+                    let instruction_length = glyphs.u16_255()?;
+                    push_u16(&mut glyph, instruction_length);
+                    glyph.extend(instructions.bytes(instruction_length as usize)?);
+                }
+
+                glyph
+            },
+            _ => return Err(FontError::FontFormatError(None, format!("Glyph {} has an invalid contour count {}", glyph_index, contour_count)))
+        };
+
+        glyf.extend(glyph);
+        glyf.resize(glyf.len().next_multiple_of(4), 0);
+    }
+    offsets.push(glyf.len() as u32);
+
+    // Offsets only grow, so the short format fits if the end of the last glyph does
+    let loca = match index_format {
+        0 if glyf.len() / 2 > u16::MAX as usize => {
+            return Err(FontError::FontFormatError(None, format!("glyf table of {} bytes is too long for short loca offsets", glyf.len())));
+        },
+        0 => offsets.iter().flat_map(|&offset| ((offset / 2) as u16).to_be_bytes()).collect(),
+        _ => offsets.iter().flat_map(|&offset| offset.to_be_bytes()).collect()
+    };
+
+    Ok(Glyphs {
+        glyf,
+        loca,
+        index_format,
+        x_mins
+    })
+}
+
+fn reconstruct_hmtx(data: &[u8], number_of_h_metrics: u16, x_mins: &[i16]) -> Result<Vec<u8>> {
+    let mut reader = ByteReader::new("hmtx", data);
+    let flags = reader.u8()?;
+    let number_of_h_metrics = (number_of_h_metrics as usize).min(x_mins.len());
+
+    let advances = (0..number_of_h_metrics).map(|_| reader.u16()).collect::<Result<Vec<u16>>>()?;
+    let side_bearings = match flags & 1 != 0 {
+        true => x_mins[..number_of_h_metrics].to_vec(),
+        false => (0..number_of_h_metrics).map(|_| reader.i16()).collect::<Result<Vec<i16>>>()?
+    };
+    let trailing_side_bearings = match flags & 2 != 0 {
+        true => x_mins[number_of_h_metrics..].to_vec(),
+        false => (number_of_h_metrics..x_mins.len()).map(|_| reader.i16()).collect::<Result<Vec<i16>>>()?
+    };
+
+    let mut hmtx = Vec::with_capacity(4 * number_of_h_metrics + 2 * trailing_side_bearings.len());
+    for (advance, side_bearing) in advances.into_iter().zip(side_bearings) {
+        push_u16(&mut hmtx, advance);
+        push_i16(&mut hmtx, side_bearing);
+    }
+    trailing_side_bearings.into_iter().for_each(|side_bearing| push_i16(&mut hmtx, side_bearing));
+
+    Ok(hmtx)
+}
+
+fn read_u16_at(table: Option<&Vec<u8>>, offset: usize) -> Option<u16> {
+    let bytes = table?.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// Presents the font inside a WOFF2 file as a regular sfnt font. The whole font is
+/// decompressed and its transformed tables are rebuilt when the file is opened.
+#[derive(Debug)]
+pub struct Woff2Stream {
+    header: Woff2Header,
+    entries: Vec<Woff2TableEntry>,
+
+    sfnt: Cursor<Vec<u8>>,
+    metadata: Option<Vec<u8>>,
+    private_data: Option<Vec<u8>>
+}
+
+impl Woff2Stream {
+    pub fn new<S>(mut stream: S) -> Result<Woff2Stream>
+        where S: Read + Seek
+    {
+        stream.seek(SeekFrom::Start(0))?;
+        let header: Woff2Header = file::deserialize_from(&mut stream)?;
+        debug!("{:?}", header);
+
+        if header.signature != WOFF2_SIGNATURE {
+            return Err(FontError::FontFormatError(Some(0), "Missing WOFF2 signature".into()));
+        }
+        if header.flavor == COLLECTION_TAG {
+            return Err(FontError::FontFormatError(Some(4), "WOFF2 font collections aren't supported".into()));
+        }
+
+        let entries = (0..header.num_tables)
+            .map(|_| Woff2TableEntry::load(&mut stream))
+            .collect::<Result<Vec<Woff2TableEntry>>>()?;
+
+        // Both sizes come from the header, so they're checked against the file and the size
+        // of the font before any data is read
+        let compressed_offset = stream.stream_position()? as u32;
+        let file_length = stream.seek(SeekFrom::End(0))?.min(header.length as u64);
+        stream.seek(SeekFrom::Start(compressed_offset as u64))?;
+
+        if compressed_offset as u64 + header.total_compressed_size as u64 > file_length {
+            return Err(FontError::FontFormatError(
+                Some(compressed_offset),
+                format!("WOFF2 data of {} bytes reaches past the end of the file", header.total_compressed_size)
+            ));
+        }
+
+        let expected_length: u64 = entries.iter().map(|entry| entry.stored_length() as u64).sum();
+        if expected_length > header.total_sfnt_size as u64 {
+            return Err(FontError::FontFormatError(
+                Some(compressed_offset),
+                format!("WOFF2 tables add up to {} bytes, but the font is only {} bytes long", expected_length, header.total_sfnt_size)
+            ));
+        }
+
+        let mut compressed = Vec::new();
+        (&mut stream).take(header.total_compressed_size as u64).read_to_end(&mut compressed)?;
+
+        let mut data = Vec::new();
+        brotli_decompressor::Decompressor::new(compressed.as_slice(), 4096)
+            .take(expected_length)
+            .read_to_end(&mut data)
+            .map_err(|err| FontError::FontFormatError(Some(compressed_offset), format!("Failed to decompress WOFF2 data: {}", err)))?;
+
+        if data.len() as u64 != expected_length {
+            return Err(FontError::FontFormatError(
+                Some(compressed_offset),
+                format!("WOFF2 data decompressed to {} bytes, expected {}", data.len(), expected_length)
+            ));
+        }
+
+        let metadata = match header.meta_offset != 0 && header.meta_length != 0 {
+            true => Some(Woff2Stream::read_block(&mut stream, header.meta_offset, header.meta_length)?),
+            false => None
+        };
+        let private_data = match header.priv_offset != 0 && header.priv_length != 0 {
+            true => Some(Woff2Stream::read_block(&mut stream, header.priv_offset, header.priv_length)?),
+            false => None
+        };
+
+        let sfnt = Woff2Stream::reconstruct(&header, &entries, &data)?;
+        info!("reconstructed {} byte font from WOFF2 data", sfnt.len());
+
+        Ok(Woff2Stream {
+            header,
+            entries,
+
+            sfnt: Cursor::new(sfnt),
+            metadata,
+            private_data
+        })
+    }
+
+    fn read_block<S>(stream: &mut S, offset: u32, length: u32) -> Result<Vec<u8>>
+        where S: Read + Seek
+    {
+        let mut data = vec![0; length as usize];
+
+        stream.seek(SeekFrom::Start(offset as u64))?;
+        stream.read_exact(&mut data)?;
+
+        Ok(data)
+    }
+
+    /// Rebuilds the tables and assembles them into an sfnt font
+    fn reconstruct(header: &Woff2Header, entries: &[Woff2TableEntry], data: &[u8]) -> Result<Vec<u8>> {
+        let mut tables: Vec<(u32, Vec<u8>)> = Vec::with_capacity(entries.len());
+
+        let mut offset = 0usize;
+        for entry in entries {
+            let length = entry.stored_length() as usize;
+            tables.push((entry.tag, data[offset..offset + length].to_vec()));
+            offset += length;
+        }
+
+        let find = |tables: &[(u32, Vec<u8>)], tag: u32| tables.iter().position(|(table_tag, _)| *table_tag == tag);
+
+        let is_transformed = |tag: u32| entries.iter().any(|entry| entry.tag == tag && entry.transform_length.is_some());
+        if is_transformed(GLYF) {
+            let glyphs = reconstruct_glyf(&tables[find(&tables, GLYF).unwrap_or_default()].1)?;
+
+            if is_transformed(HMTX) {
+                let number_of_h_metrics = read_u16_at(find(&tables, HHEA).map(|index| &tables[index].1), 34)
+                    .ok_or_else(|| FontError::FontFormatError(None, "Rebuilding hmtx requires the hhea table".into()))?;
+                let index = find(&tables, HMTX).unwrap_or_default();
+
+                tables[index].1 = reconstruct_hmtx(&tables[index].1, number_of_h_metrics, &glyphs.x_mins)?;
+            }
+
+            if let Some(index) = find(&tables, HEAD) {
+                if let Some(bytes) = tables[index].1.get_mut(50..52) {
+                    bytes.copy_from_slice(&glyphs.index_format.to_be_bytes());
+                }
+            }
+
+            if let Some(index) = find(&tables, LOCA) {
+                tables[index].1 = glyphs.loca;
+            }
+            if let Some(index) = find(&tables, GLYF) {
+                tables[index].1 = glyphs.glyf;
+            }
+        } else if is_transformed(HMTX) {
+            return Err(FontError::FontFormatError(None, "The hmtx transform requires a transformed glyf table".into()));
+        }
+
+        if let Some(num_glyphs) = read_u16_at(find(&tables, MAXP).map(|index| &tables[index].1), 4) {
+            debug!("rebuilt WOFF2 font with {} glyphs", num_glyphs);
+        }
+
+        Ok(assemble_sfnt(header.flavor, tables))
+    }
+
+    pub fn header(&self) -> &Woff2Header {
+        &self.header
+    }
+
+    pub fn table_entries(&self) -> &[Woff2TableEntry] {
+        &self.entries
+    }
+
+    /// Returns the extended metadata, an XML document, if the file has any
+    pub fn metadata(&self) -> Result<Option<String>> {
+        let Some(compressed) = &self.metadata else {
+            return Ok(None);
+        };
+
+        let mut data = Vec::with_capacity(self.header.meta_orig_length as usize);
+        brotli_decompressor::Decompressor::new(compressed.as_slice(), 4096)
+            .take(self.header.meta_orig_length as u64)
+            .read_to_end(&mut data)
+            .map_err(|err| FontError::FontFormatError(Some(self.header.meta_offset), format!("Failed to decompress WOFF2 metadata: {}", err)))?;
+
+        String::from_utf8(data)
+            .map(Some)
+            .map_err(|_| FontError::FontFormatError(Some(self.header.meta_offset), "WOFF2 metadata isn't valid UTF-8".into()))
+    }
+
+    /// Returns the private data block, if the file has one
+    pub fn private_data(&self) -> Option<&[u8]> {
+        self.private_data.as_deref()
+    }
+}
+
+/// Lays out tables in an sfnt font, sorted by tag, and fills in the table checksums
+/// and the checksum adjustment in head
+fn assemble_sfnt(flavor: u32, mut tables: Vec<(u32, Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| *tag);
+
+    let num_tables = tables.len() as u32;
+    let entry_selector = num_tables.max(1).ilog2();
+    let search_range = (1u32 << entry_selector) * 16;
+
+    let mut sfnt = Vec::new();
+    sfnt.extend(flavor.to_be_bytes());
+    sfnt.extend((num_tables as u16).to_be_bytes());
+    sfnt.extend((search_range as u16).to_be_bytes());
+    sfnt.extend((entry_selector as u16).to_be_bytes());
+    sfnt.extend(((num_tables * 16).saturating_sub(search_range) as u16).to_be_bytes());
+
+    let mut offset = 12 + 16 * tables.len() as u32;
+    let mut head_offset = None;
+    for (tag, table) in tables.iter_mut() {
+        if *tag == HEAD {
+            if let Some(adjustment) = table.get_mut(8..12) {
+                adjustment.fill(0);
+                head_offset = Some(offset as usize);
+            }
+        }
+
+        sfnt.extend(tag.to_be_bytes());
+        sfnt.extend(table_checksum(table).to_be_bytes());
+        sfnt.extend(offset.to_be_bytes());
+        sfnt.extend((table.len() as u32).to_be_bytes());
+
+        offset += (table.len() as u32).next_multiple_of(4);
+    }
+
+    for (_, table) in tables {
+        sfnt.extend(&table);
+        sfnt.resize(sfnt.len().next_multiple_of(4), 0);
+    }
+
+    if let Some(head_offset) = head_offset {
+        let adjustment = CHECKSUM_MAGIC.wrapping_sub(table_checksum(&sfnt));
+        sfnt[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+
+    sfnt
+}
+
+impl Read for Woff2Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.sfnt.read(buf)
+    }
+}
+
+impl Seek for Woff2Stream {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.sfnt.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{assemble_sfnt, decode_triplet, read_base128, reconstruct_glyf, ByteReader};
+
+    fn triplet(flag: u8, bytes: &[u8]) -> Option<(i32, i32)> {
+        decode_triplet(flag, &mut ByteReader::new("glyph", bytes)).ok()
+    }
+
+    fn base128(bytes: &[u8]) -> Option<u32> {
+        read_base128(&mut Cursor::new(bytes)).ok()
+    }
+
+    fn u16_255(bytes: &[u8]) -> Option<u16> {
+        ByteReader::new("glyph", bytes).u16_255().ok()
+    }
+
+    #[test]
+    fn triplets_in_every_flag_range() {
+        assert_eq!(triplet(0, &[5]), Some((0, -5)));
+        assert_eq!(triplet(1, &[5]), Some((0, 5)));
+        assert_eq!(triplet(9, &[255]), Some((0, 1279)));
+
+        assert_eq!(triplet(10, &[5]), Some((-5, 0)));
+        assert_eq!(triplet(19, &[255]), Some((1279, 0)));
+
+        assert_eq!(triplet(20, &[0x00]), Some((-1, -1)));
+        assert_eq!(triplet(21, &[0x00]), Some((1, -1)));
+        assert_eq!(triplet(22, &[0x00]), Some((-1, 1)));
+        assert_eq!(triplet(83, &[0xFF]), Some((64, 64)));
+
+        assert_eq!(triplet(84, &[0, 0]), Some((-1, -1)));
+        assert_eq!(triplet(119, &[255, 255]), Some((768, 768)));
+
+        assert_eq!(triplet(120, &[0x12, 0x34, 0x56]), Some((-0x123, -0x456)));
+        assert_eq!(triplet(123, &[0x12, 0x34, 0x56]), Some((0x123, 0x456)));
+
+        assert_eq!(triplet(124, &[0x12, 0x34, 0x56, 0x78]), Some((-0x1234, -0x5678)));
+        assert_eq!(triplet(127, &[0x12, 0x34, 0x56, 0x78]), Some((0x1234, 0x5678)));
+    }
+
+    #[test]
+    fn triplets_ignore_the_on_curve_bit() {
+        assert_eq!(triplet(0x80 | 1, &[5]), Some((0, 5)));
+        assert_eq!(triplet(0x80 | 127, &[0x12, 0x34, 0x56, 0x78]), Some((0x1234, 0x5678)));
+    }
+
+    #[test]
+    fn truncated_triplets_are_errors() {
+        assert_eq!(triplet(0, &[]), None);
+        assert_eq!(triplet(84, &[0]), None);
+        assert_eq!(triplet(120, &[0, 0]), None);
+        assert_eq!(triplet(124, &[0, 0, 0]), None);
+    }
+
+    #[test]
+    fn base128_values() {
+        assert_eq!(base128(&[0x00]), Some(0));
+        assert_eq!(base128(&[0x3F]), Some(63));
+        assert_eq!(base128(&[0x81, 0x00]), Some(128));
+        assert_eq!(base128(&[0x8F, 0xFF, 0xFF, 0xFF, 0x7F]), Some(u32::MAX));
+    }
+
+    #[test]
+    fn invalid_base128_values_are_errors() {
+        // leading zeros
+        assert_eq!(base128(&[0x80, 0x01]), None);
+        // more than 32 bits
+        assert_eq!(base128(&[0x90, 0x80, 0x80, 0x80, 0x00]), None);
+        // more than 5 bytes
+        assert_eq!(base128(&[0x81, 0x80, 0x80, 0x80, 0x80, 0x00]), None);
+        // truncated
+        assert_eq!(base128(&[0x81]), None);
+    }
+
+    #[test]
+    fn u16_255_values() {
+        assert_eq!(u16_255(&[0]), Some(0));
+        assert_eq!(u16_255(&[252]), Some(252));
+        assert_eq!(u16_255(&[255, 0]), Some(253));
+        assert_eq!(u16_255(&[255, 252]), Some(505));
+        assert_eq!(u16_255(&[254, 0]), Some(506));
+        assert_eq!(u16_255(&[254, 255]), Some(761));
+        assert_eq!(u16_255(&[253, 0x02, 0xFA]), Some(762));
+        assert_eq!(u16_255(&[253, 0xFF, 0xFF]), Some(u16::MAX));
+    }
+
+    #[test]
+    fn overlong_u16_255_values_are_decoded() {
+        assert_eq!(u16_255(&[253, 0, 5]), Some(5));
+        assert_eq!(u16_255(&[255, 0]), u16_255(&[253, 0, 253]));
+        assert_eq!(u16_255(&[254, 0]), u16_255(&[253, 0x01, 0xFA]));
+    }
+
+    #[test]
+    fn truncated_u16_255_values_are_errors() {
+        assert_eq!(u16_255(&[]), None);
+        assert_eq!(u16_255(&[253, 0]), None);
+        assert_eq!(u16_255(&[254]), None);
+        assert_eq!(u16_255(&[255]), None);
+    }
+
+    #[test]
+    fn large_table_directories_are_assembled() {
+        let sfnt = assemble_sfnt(0x00010000, (0..4096).map(|tag| (tag, vec![])).collect());
+
+        assert_eq!(sfnt[4..12], [0x10, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x00, 0x00]);
+        assert_eq!(sfnt.len(), 12 + 16 * 4096);
+    }
+
+    /// Builds a transformed glyf table from its seven streams, without an overlap bitmap
+    fn transformed_glyf(num_glyphs: u16, index_format: u16, streams: [&[u8]; 7]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend([0, 0, 0, 0]);
+        data.extend(num_glyphs.to_be_bytes());
+        data.extend(index_format.to_be_bytes());
+        streams.iter().for_each(|stream| data.extend((stream.len() as u32).to_be_bytes()));
+        streams.iter().for_each(|stream| data.extend(*stream));
+
+        data
+    }
+
+    #[test]
+    fn point_counts_past_the_flag_stream_are_errors() {
+        let glyf = transformed_glyf(1, 1, [&[0, 1], &[253, 0xFF, 0xFF], &[1], &[5, 0], &[], &[0; 4], &[]]);
+        assert!(reconstruct_glyf(&glyf).is_err());
+    }
+
+    #[test]
+    fn glyf_too_long_for_short_offsets_is_an_error() {
+        // 8192 glyphs with a 16 byte outline each make a glyf table of 128 KiB
+        let num_glyphs = 8192;
+        let contour_counts = [0, 1].repeat(num_glyphs);
+        let point_counts = vec![1; num_glyphs];
+        let flags = vec![1; num_glyphs];
+        let glyphs = [1, 0].repeat(num_glyphs);
+        let bounding_boxes = vec![0; num_glyphs / 8];
+        let streams: [&[u8]; 7] = [&contour_counts, &point_counts, &flags, &glyphs, &[], &bounding_boxes, &[]];
+
+        assert_eq!(reconstruct_glyf(&transformed_glyf(num_glyphs as u16, 1, streams)).unwrap().glyf.len(), 0x20000);
+        assert!(reconstruct_glyf(&transformed_glyf(num_glyphs as u16, 0, streams)).is_err());
+        assert!(reconstruct_glyf(&transformed_glyf(num_glyphs as u16 - 1, 0, streams)).is_ok());
+    }
+}
@@ -2,9 +2,7 @@ use std::error::Error;
 
 use log::{error, info};
 
-use crate::file::OpenTypeFont;
-
-mod file;
+use font_explorer::file::OpenTypeFont;
 
 fn main() {
     env_logger::init();
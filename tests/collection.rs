@@ -0,0 +1,34 @@
+use std::io::Cursor;
+
+use font_explorer::file::{loader::FontLoader, FontCollection};
+
+/// Two faces sharing every table except name and head
+const COLLECTION: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fonts/collection.ttc");
+
+#[test]
+fn faces_share_tables_with_the_same_data() {
+    let collection = FontCollection::load(COLLECTION).unwrap();
+    assert_eq!(collection.num_faces(), 2);
+
+    let first = collection.face(0).unwrap();
+    let second = collection.face(1).unwrap();
+
+    assert!(std::ptr::eq(first.os2(), second.os2()));
+    assert!(std::ptr::eq(first.postscript(), second.postscript()));
+    assert!(std::ptr::eq(first.horizontal_metrics(), second.horizontal_metrics()));
+
+    assert!(!std::ptr::eq(first.naming_table(), second.naming_table()));
+    assert_eq!(first.naming_table().family(), Some("Face A"));
+    assert_eq!(second.naming_table().family(), Some("Face B"));
+    assert_eq!(second.header().units_per_em, 2048);
+}
+
+#[test]
+fn face_count_is_checked_against_the_file_length() {
+    let mut data = b"ttcf".to_vec();
+    data.extend([0, 1, 0, 0]);
+    data.extend(u32::MAX.to_be_bytes());
+    data.extend(12u32.to_be_bytes());
+
+    assert!(FontLoader::new(Cursor::new(data)).is_err());
+}
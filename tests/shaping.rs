@@ -0,0 +1,42 @@
+use font_explorer::file::{table::{Direction, ShapedGlyph}, OpenTypeFont};
+
+/// A font without outlines, made for shaping:
+/// - glyphs: f (1), i (2), the fi ligature (3), a (4), a combining acute (5), V (6) and A (7)
+/// - GSUB: liga forms fi, ignoring marks
+/// - GPOS: kern moves A 80 units closer to V, mark attaches the acute to a and to both components of fi
+/// - GDEF: the acute is a mark, even though its advance in hmtx is 200
+const FONT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fonts/shaping.ttf");
+
+fn shape(text: &str, direction: Direction) -> Vec<ShapedGlyph> {
+    let font = OpenTypeFont::load(FONT).unwrap();
+    font.shape(text, &[], "DFLT", None, direction)
+}
+
+fn glyph(glyph_index: u16, cluster: usize, x_advance: i32, x_offset: i32, y_offset: i32) -> ShapedGlyph {
+    ShapedGlyph { glyph_index, cluster, x_advance, y_advance: 0, x_offset, y_offset }
+}
+
+#[test]
+fn kerning_pairs_are_applied() {
+    assert_eq!(shape("VA", Direction::LeftToRight), [glyph(6, 0, 520, 0, 0), glyph(7, 1, 600, 0, 0)]);
+}
+
+#[test]
+fn marks_attach_to_their_base_without_advancing() {
+    assert_eq!(shape("a\u{301}", Direction::LeftToRight), [glyph(4, 0, 500, 0, 0), glyph(5, 1, 0, 250 - 100 - 500, 500)]);
+}
+
+#[test]
+fn marks_attach_to_the_ligature_component_they_follow() {
+    assert_eq!(shape("f\u{301}i\u{301}", Direction::LeftToRight), [
+        glyph(3, 0, 550, 0, 0),
+        glyph(5, 1, 0, 150 - 100 - 550, 700),
+        glyph(5, 4, 0, 400 - 100 - 550, 700)
+    ]);
+}
+
+#[test]
+fn right_to_left_text_is_returned_in_visual_order() {
+    assert_eq!(shape("fi", Direction::RightToLeft), [glyph(3, 0, 550, 0, 0)]);
+    assert_eq!(shape("af", Direction::RightToLeft), [glyph(1, 1, 300, 0, 0), glyph(4, 0, 500, 0, 0)]);
+}
@@ -0,0 +1,71 @@
+use std::io::{Cursor, Read};
+
+use font_explorer::file::{stream::FontStream, woff::WoffStream, OpenTypeFont};
+
+/// A font whose table data isn't in the order of its table directory, and the same font as WOFF
+const TTF: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fonts/reordered.ttf");
+const WOFF: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fonts/reordered.woff");
+
+#[test]
+fn woff_is_decoded_to_the_original_font() {
+    let mut decoded = Vec::new();
+    FontStream::open(WOFF).unwrap().read_to_end(&mut decoded).unwrap();
+
+    assert_eq!(decoded, std::fs::read(TTF).unwrap());
+}
+
+#[test]
+fn woff_checksums_match_the_original_font() {
+    assert!(OpenTypeFont::load(TTF).unwrap().verify_checksums().unwrap().is_valid());
+    assert!(OpenTypeFont::load(WOFF).unwrap().verify_checksums().unwrap().is_valid());
+}
+
+/// Builds a WOFF header followed by the given table directory entries
+fn woff(entries: &[[u32; 5]]) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend(b"wOFF");
+    data.extend(0x00010000u32.to_be_bytes());
+    data.extend(0u32.to_be_bytes());
+    data.extend((entries.len() as u16).to_be_bytes());
+    data.extend([0; 2]);
+    data.extend([0; 4]);
+    data.extend([0, 1, 0, 0]);
+    data.extend([0; 20]);
+
+    for entry in entries {
+        data.extend(entry.iter().flat_map(|value| value.to_be_bytes()));
+    }
+
+    // Tables are empty, and stored right after the directory
+    data.resize(data.len().max(64), 0);
+    let length = data.len() as u32;
+    data[8..12].copy_from_slice(&length.to_be_bytes());
+
+    data
+}
+
+#[test]
+fn large_table_directories_are_supported() {
+    let stream = WoffStream::new(Cursor::new(woff(&[[0; 5]; 4096]))).unwrap();
+    assert_eq!(stream.table_entries().count(), 4096);
+}
+
+#[test]
+fn fonts_larger_than_4_gib_are_rejected() {
+    let entries = [[0x61616161, 64, 0, 0xFFFF_FFF0, 0], [0x62626262, 64, 0, 0xFFFF_FFF0, 0]];
+    assert!(WoffStream::new(Cursor::new(woff(&entries))).is_err());
+}
+
+#[test]
+fn blocks_past_the_end_of_the_file_are_rejected() {
+    assert!(WoffStream::new(Cursor::new(woff(&[[0x61616161, 64, 0, 0, 0]]))).is_ok());
+    assert!(WoffStream::new(Cursor::new(woff(&[[0x61616161, 64, 0xFFFF_FFF0, 0xFFFF_FFF0, 0]]))).is_err());
+
+    // The header length limits the blocks as well
+    let mut data = woff(&[[0x61616161, 64, 16, 16, 0]]);
+    data.resize(128, 0);
+    assert!(WoffStream::new(Cursor::new(data.clone())).is_err());
+
+    data[8..12].copy_from_slice(&128u32.to_be_bytes());
+    assert!(WoffStream::new(Cursor::new(data)).is_ok());
+}
@@ -0,0 +1,76 @@
+use std::io::Cursor;
+
+use font_explorer::file::{outlines::Outlines, stream::FontStream, woff2::Woff2Stream, OpenTypeFont};
+
+/// A TrueType font with a .notdef box, an empty glyph, a glyph with coordinate deltas of every
+/// size, one with instructions and a bounding box wider than its points, and a composite glyph.
+/// The WOFF2 version transforms glyf, loca and hmtx, deriving every left side bearing from xMin.
+const TTF: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fonts/outlines.ttf");
+const WOFF2: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fonts/outlines.woff2");
+
+#[test]
+fn woff2_glyphs_match_the_original_font() {
+    let mut ttf = OpenTypeFont::load(TTF).unwrap();
+    let mut woff2 = OpenTypeFont::load(WOFF2).unwrap();
+
+    assert_eq!(woff2.num_glyphs(), ttf.num_glyphs());
+    assert_eq!(woff2.header().index_to_loc_format, ttf.header().index_to_loc_format);
+
+    let (Outlines::TrueType(ttf_outlines), Outlines::TrueType(woff2_outlines)) = (ttf.outlines().clone(), woff2.outlines().clone()) else {
+        panic!("both fonts should have TrueType outlines");
+    };
+    let mut ttf_stream = FontStream::open(TTF).unwrap();
+    let mut woff2_stream = FontStream::open(WOFF2).unwrap();
+
+    for glyph_index in 0..ttf.num_glyphs() {
+        assert_eq!(
+            format!("{:?}", woff2.outline(glyph_index).unwrap()),
+            format!("{:?}", ttf.outline(glyph_index).unwrap()),
+            "outline of glyph {}", glyph_index
+        );
+        assert_eq!(
+            format!("{:?}", woff2_outlines.glyphs().header(glyph_index, &mut woff2_stream).unwrap()),
+            format!("{:?}", ttf_outlines.glyphs().header(glyph_index, &mut ttf_stream).unwrap()),
+            "header of glyph {}", glyph_index
+        );
+        assert_eq!(woff2.advance_width(glyph_index), ttf.advance_width(glyph_index), "advance of glyph {}", glyph_index);
+        assert_eq!(woff2.left_side_bearing(glyph_index), ttf.left_side_bearing(glyph_index), "bearing of glyph {}", glyph_index);
+    }
+}
+
+#[test]
+fn woff2_keeps_the_character_map() {
+    let ttf = OpenTypeFont::load(TTF).unwrap();
+    let woff2 = OpenTypeFont::load(WOFF2).unwrap();
+
+    for character in [' ', 'O', 'B', 'C', 'x'] {
+        assert_eq!(woff2.glyph_for_char(character), ttf.glyph_for_char(character));
+    }
+}
+
+#[test]
+fn woff2_checksums_are_unverifiable() {
+    assert!(OpenTypeFont::load(TTF).unwrap().verify_checksums().unwrap().is_valid());
+
+    let report = OpenTypeFont::load(WOFF2).unwrap().verify_checksums().unwrap();
+    assert!(report.unverifiable);
+    assert!(!report.is_valid());
+}
+
+/// Loads the WOFF2 font with one of its header fields replaced
+fn with_header_field(offset: usize, value: u32) -> font_explorer::file::error::Result<Woff2Stream> {
+    let mut data = std::fs::read(WOFF2).unwrap();
+    data[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+
+    Woff2Stream::new(Cursor::new(data))
+}
+
+#[test]
+fn compressed_size_past_the_end_of_the_file_is_rejected() {
+    assert!(with_header_field(20, 0xFFFF_FFF0).is_err());
+}
+
+#[test]
+fn tables_larger_than_the_font_are_rejected() {
+    assert!(with_header_field(16, 16).is_err());
+}